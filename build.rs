@@ -0,0 +1,97 @@
+//! Embeds details of the build in the binary so that logs and bug reports can
+//! identify exactly which build is running.
+
+use std::{
+  env,
+  path::Path,
+  process::Command,
+  time::{SystemTime, UNIX_EPOCH},
+};
+
+fn main() {
+  println!("cargo:rerun-if-changed=build.rs");
+  println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+  // rebuild when the checked out commit changes, but only if there is a
+  // repository, since cargo always reruns the script for missing paths
+  for path in [".git/HEAD", ".git/index"] {
+    if Path::new(path).exists() {
+      println!("cargo:rerun-if-changed={path}");
+    }
+  }
+
+  println!("cargo:rustc-env=BUILD_COMMIT={}", commit());
+  println!("cargo:rustc-env=BUILD_DATE={}", date());
+  println!(
+    "cargo:rustc-env=BUILD_TARGET={}",
+    env::var("TARGET").unwrap()
+  );
+  println!("cargo:rustc-env=BUILD_FEATURES={}", features());
+}
+
+/// Returns the abbreviated hash of the checked out commit, marked as dirty if
+/// there are uncommitted changes, or `unknown` outside of a git repository.
+fn commit() -> String {
+  let git = |args: &[&str]| {
+    let output = Command::new("git").args(args).output().ok()?;
+
+    match output.status.success() {
+      true => Some(String::from_utf8_lossy(&output.stdout).trim().to_string()),
+      false => None,
+    }
+  };
+
+  let Some(hash) = git(&["rev-parse", "--short=12", "HEAD"]) else {
+    return "unknown".into();
+  };
+
+  match git(&["status", "--porcelain", "--untracked-files=no"]) {
+    Some(changes) if !changes.is_empty() => format!("{hash}-dirty"),
+    _ => hash,
+  }
+}
+
+/// Returns the build date as `YYYY-MM-DD`, taken from `SOURCE_DATE_EPOCH` for
+/// reproducible builds if it is set.
+fn date() -> String {
+  let secs = env::var("SOURCE_DATE_EPOCH")
+    .ok()
+    .and_then(|s| s.parse().ok())
+    .unwrap_or_else(|| {
+      SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+    });
+
+  // convert days since the epoch to a civil date, see
+  // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+  let z = (secs / 86400) as i64 + 719468;
+  let era = z.div_euclid(146097);
+  let doe = z.rem_euclid(146097);
+  let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+  let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+  let mp = (5 * doy + 2) / 153;
+  let day = doy - (153 * mp + 2) / 5 + 1;
+  let month = if mp < 10 { mp + 3 } else { mp - 9 };
+  let year = yoe + era * 400 + i64::from(month <= 2);
+
+  format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Returns the enabled cargo features separated by commas, or `none`.
+fn features() -> String {
+  let mut features: Vec<String> = env::vars()
+    .filter_map(|(key, _)| {
+      let name = key.strip_prefix("CARGO_FEATURE_")?;
+      Some(name.to_ascii_lowercase().replace('_', "-"))
+    })
+    .collect();
+
+  if features.is_empty() {
+    return "none".into();
+  }
+
+  features.sort();
+  features.join(",")
+}
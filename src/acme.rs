@@ -0,0 +1,124 @@
+use anyhow::{Context, Result};
+use aws_sdk_route53::types::{ChangeAction, RrType};
+use clap::ValueEnum;
+use tracing::info;
+
+use ddns_route53::aws::{self, Accounts, AwsOptions};
+
+use crate::Domain;
+
+/// Prefix of the names of DNS-01 challenge records.
+const PREFIX: &str = "_acme-challenge.";
+
+/// TTL of challenge records, kept short so that retried challenges are not
+/// answered from stale caches.
+const TTL: i64 = 60;
+
+/// What to do with a challenge record.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Action {
+  /// Add the validation token to the challenge record.
+  #[value(alias = "present")]
+  Set,
+  /// Remove the validation token from the challenge record.
+  #[value(alias = "cleanup")]
+  Clean,
+}
+
+/// Sets or cleans a challenge record and waits until the change has
+/// propagated.
+///
+/// The challenge record may hold several tokens at once, for example while a
+/// certificate for both a domain and its wildcard is validated, so tokens are
+/// added to and removed from the record individually.
+pub async fn run(action: Action, arg: String, value: &str, options: &AwsOptions) -> Result<()> {
+  let (name, zone) = match arg.split_once('=') {
+    Some((name, zone)) => (name, Some(zone)),
+    None => (arg.as_str(), None),
+  };
+
+  let mut name = name.trim_end_matches('.').to_string();
+
+  if !name.starts_with(PREFIX) {
+    name.insert_str(0, PREFIX);
+  }
+
+  let domain = match zone {
+    Some(zone) => Domain::parse(format!("{name}={zone}"))?,
+    None => Domain::parse(name)?,
+  };
+
+  let mut accounts = Accounts::new(options);
+  let zone = accounts
+    .zone(&domain.identity, &domain.name, &domain.zone)
+    .await?;
+
+  let zone_id = &zone.id;
+  let route53 = accounts.client(&domain.identity).await;
+  let token = format!("\"{value}\"");
+
+  let existing = aws::find_record(route53, zone_id, &domain.name, RrType::Txt, None).await?;
+
+  let mut tokens: Vec<&str> = existing
+    .iter()
+    .flat_map(|set| set.resource_records())
+    .map(|record| record.value())
+    .collect();
+
+  let change = match action {
+    Action::Set if tokens.contains(&token.as_str()) => None,
+
+    Action::Set => {
+      tokens.push(&token);
+
+      Some(aws::change(
+        ChangeAction::Upsert,
+        RrType::Txt,
+        &domain.name,
+        &tokens,
+        TTL,
+      )?)
+    }
+
+    Action::Clean => match &existing {
+      Some(set) if tokens.contains(&token.as_str()) => {
+        tokens.retain(|t| *t != token);
+
+        match tokens.is_empty() {
+          true => Some(aws::delete(set.clone())?),
+
+          false => Some(aws::change(
+            ChangeAction::Upsert,
+            RrType::Txt,
+            &domain.name,
+            &tokens,
+            set.ttl.unwrap_or(TTL),
+          )?),
+        }
+      }
+
+      _ => None,
+    },
+  };
+
+  let Some(change) = change else {
+    info!("`{}` is already up to date.", domain.name);
+    return Ok(());
+  };
+
+  let change_id = aws::change_records(route53, zone_id, vec![change])
+    .await
+    .with_context(|| format!("Failed to change `{}`.", domain.name))?
+    .change_id;
+
+  info!("Waiting for `{}` to propagate.", domain.name);
+
+  aws::wait_for_sync(route53, &change_id).await?;
+
+  match action {
+    Action::Set => info!("Set `{}` to {token}.", domain.name),
+    Action::Clean => info!("Removed {token} from `{}`.", domain.name),
+  }
+
+  Ok(())
+}
@@ -0,0 +1,132 @@
+use std::net::SocketAddr;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::info;
+
+use crate::{
+  control::Controls,
+  http::Http,
+  server::{self, Response},
+};
+
+/// Whether updates of a domain are paused, as returned by the API.
+#[derive(Deserialize, Serialize)]
+pub struct DomainStatus {
+  pub name: String,
+  pub paused: bool,
+}
+
+/// Serves the control API of the daemon over HTTP on an address.
+///
+/// The API has no authentication, so it should only listen on a loopback
+/// address or another address only trusted clients can reach.
+///
+/// - `GET /domains` lists the domains and whether they are paused.
+/// - `POST /domains/NAME/pause` pauses updates of a domain.
+/// - `POST /domains/NAME/resume` resumes them.
+pub async fn listen(addr: SocketAddr, controls: Controls, domains: Vec<String>) -> Result<()> {
+  server::listen(addr, "control API", move |method, path| {
+    let (status, body) = route(method, path, &controls, &domains);
+
+    Response::json(status, body)
+  })
+  .await
+}
+
+/// Responds to a request, returning the status line and JSON body.
+fn route(
+  method: &str,
+  path: &str,
+  controls: &Controls,
+  domains: &[String],
+) -> (&'static str, serde_json::Value) {
+  let status = |name: &String| DomainStatus {
+    name: name.clone(),
+    paused: controls.is_domain_paused(name),
+  };
+
+  let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+  match (method, segments.as_slice()) {
+    ("GET", ["domains"]) => {
+      let list: Vec<_> = domains.iter().map(status).collect();
+
+      ("200 OK", json!(list))
+    }
+
+    ("POST", ["domains", name, action @ ("pause" | "resume")]) => {
+      let Some(name) = domains.iter().find(|d| d.eq_ignore_ascii_case(name)) else {
+        return (
+          "404 Not Found",
+          json!({ "error": format!("Domain `{name}` is not managed by this daemon.") }),
+        );
+      };
+
+      let paused = *action == "pause";
+
+      if controls.set_domain_paused(name, paused) {
+        match paused {
+          true => info!("Paused updates of `{name}`."),
+          false => info!("Resumed updates of `{name}`."),
+        }
+
+        // a pass applies the change and records it in the state file
+        controls.update_now();
+      }
+
+      ("200 OK", json!(status(name)))
+    }
+
+    (_, ["domains", ..]) => (
+      "405 Method Not Allowed",
+      json!({ "error": "Method not allowed." }),
+    ),
+
+    _ => ("404 Not Found", json!({ "error": "Not found." })),
+  }
+}
+
+/// Pauses or resumes updates of a domain in a running daemon, for the `pause`
+/// and `resume` commands.
+pub async fn set_paused(addr: Option<SocketAddr>, domain: &str, paused: bool) -> Result<()> {
+  let Some(addr) = addr else {
+    bail!("The pause and resume commands require the --control-addr of the running daemon.");
+  };
+
+  let action = match paused {
+    true => "pause",
+    false => "resume",
+  };
+
+  let http = Http::new()?;
+
+  let response = http
+    .any
+    .post(format!("http://{addr}/domains/{domain}/{action}"))
+    .send()
+    .await
+    .with_context(|| format!("Failed to reach the daemon at {addr}."))?;
+
+  if !response.status().is_success() {
+    let body: serde_json::Value = response.json().await.unwrap_or_default();
+
+    match body["error"].as_str() {
+      Some(error) => bail!("{error}"),
+      None => bail!("The daemon failed to {action} `{domain}`."),
+    }
+  }
+
+  let status: DomainStatus = response
+    .json()
+    .await
+    .context("Invalid response from the daemon.")?;
+
+  match status.paused {
+    true => println!("Updates of `{}` are paused.", status.name),
+    false => println!("Updates of `{}` are resumed.", status.name),
+  }
+
+  Ok(())
+}
@@ -0,0 +1,779 @@
+use std::{
+  error::Error,
+  future::Future,
+  net::IpAddr,
+  time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use aws_config::{retry::RetryConfig, sts::AssumeRoleProvider, Region};
+use aws_sdk_route53::{
+  self as route53,
+  error::{ProvideErrorMetadata, SdkError},
+  operation::{
+    change_resource_record_sets::ChangeResourceRecordSetsError, get_change::GetChangeError,
+    get_hosted_zone::GetHostedZoneError, list_hosted_zones::ListHostedZonesError,
+    list_resource_record_sets::ListResourceRecordSetsError, RequestId,
+  },
+  types::{
+    builders::ResourceRecordSetBuilder, Change, ChangeAction, ChangeBatch, ChangeStatus,
+    HostedZone, ResourceRecord, ResourceRecordSet, RrType,
+  },
+};
+use reqwest::Url;
+use tracing::{field, info_span, Span};
+
+use crate::{
+  http::{self, Http, HttpOptions},
+  name::DomainName,
+  pacer,
+  trace::traced,
+  zone::{self, ZoneChoice},
+};
+
+/// Error codes Route 53 responds with when it throttles a request.
+const THROTTLING: &[&str] = &[
+  "Throttling",
+  "ThrottlingException",
+  "PriorRequestNotComplete",
+];
+
+/// Options for loading AWS configuration.
+#[derive(clap::Args)]
+pub struct AwsOptions {
+  /// Name of the AWS profile to load credentials and settings from.
+  #[arg(long, global = true)]
+  pub profile: Option<String>,
+
+  /// AWS region to use, overriding the profile and environment.
+  #[arg(long, global = true)]
+  pub region: Option<String>,
+
+  /// ARN of an IAM role to assume for Route 53 requests, such as a role in
+  /// the AWS account that owns the hosted zones.
+  #[arg(long, global = true, value_name = "ARN")]
+  pub role_arn: Option<String>,
+
+  /// Maximum number of Route 53 API requests to send per second. Route 53
+  /// allows five per second for the whole AWS account.
+  #[arg(long, global = true, default_value_t = 2.0, value_name = "RATE", value_parser = parse_rate)]
+  pub route53_rate: f64,
+
+  /// Number of times to retry a Route 53 request that was throttled or failed
+  /// because of a network or server error. Requests that Route 53 rejected,
+  /// for example because access was denied, are not retried.
+  #[arg(long, global = true, default_value_t = 2, value_name = "COUNT")]
+  pub route53_retries: u32,
+
+  /// Longest time to wait before retrying a request to Route 53 or an IP
+  /// provider, or an update pass that failed.
+  #[arg(
+    long,
+    global = true,
+    default_value = "5m",
+    value_name = "DURATION",
+    value_parser = humantime::parse_duration
+  )]
+  pub max_backoff: Duration,
+
+  /// Proxy that requests to AWS are sent through, from `--http-proxy`.
+  #[arg(skip)]
+  pub http_proxy: Option<Url>,
+}
+
+/// Multivalue answer or weighted routing for the records of an instance, so
+/// that several instances can publish their records under the same name.
+pub struct Routing {
+  /// Identifier of this instance's member of each record set.
+  pub set_identifier: String,
+  /// ID of a Route 53 health check that decides whether this instance's
+  /// member is returned.
+  pub health_check_id: Option<String>,
+  /// Weight of this instance's member for weighted routing, instead of
+  /// multivalue answers.
+  pub weight: Option<i64>,
+}
+
+/// Credentials that the Route 53 requests of a domain are signed with, for
+/// domains in other AWS accounts than the rest.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Identity {
+  /// Name of the AWS profile to load credentials from, overriding `--profile`.
+  pub profile: Option<String>,
+  /// ARN of an IAM role to assume, overriding `--role-arn`. A domain with its
+  /// own profile assumes no role unless it is given one.
+  pub role_arn: Option<String>,
+}
+
+/// Creates a Route 53 client with AWS configuration from the environment,
+/// overridden by the given options.
+pub async fn connect(options: &AwsOptions) -> route53::Client {
+  connect_as(options, &Identity::default()).await
+}
+
+/// Creates a Route 53 client like [`connect`], with credentials of the given
+/// identity instead of the global ones where it has them.
+pub async fn connect_as(options: &AwsOptions, identity: &Identity) -> route53::Client {
+  let mut loader = aws_config::from_env();
+
+  if let Some(profile) = identity.profile.as_ref().or(options.profile.as_ref()) {
+    loader = loader.profile_name(profile);
+  }
+
+  if let Some(region) = &options.region {
+    loader = loader.region(Region::new(region.clone()));
+  }
+
+  // the SDK only retries throttling and transient errors, with exponential
+  // backoff and jitter
+  let retry = RetryConfig::standard()
+    .with_max_attempts(options.route53_retries.saturating_add(1))
+    .with_max_backoff(options.max_backoff);
+
+  pacer::configure(options.route53_rate);
+
+  // the SDK ignores proxies, so its requests are then sent like any other
+  if options.http_proxy.is_some() || http::proxy_in_env() {
+    let http_options = HttpOptions {
+      http_proxy: options.http_proxy.clone(),
+      ..HttpOptions::default()
+    };
+
+    match Http::with_options(&http_options) {
+      Ok(http) => loader = loader.http_client(http.sdk_client()),
+      Err(err) => tracing::error!("Failed to configure the proxy for AWS. {err:#}"),
+    }
+  }
+
+  let config = loader.retry_config(retry).load().await;
+
+  // a domain with its own profile assumes only its own role, if any, since
+  // the global role may not trust the profile
+  let role_arn = match identity.profile {
+    Some(_) => identity.role_arn.as_ref(),
+    None => identity.role_arn.as_ref().or(options.role_arn.as_ref()),
+  };
+
+  let Some(role_arn) = role_arn else {
+    return route53::Client::new(&config);
+  };
+
+  // the role is assumed with the credentials of the profile, and assumed
+  // again before its session expires
+  let credentials = AssumeRoleProvider::builder(role_arn)
+    .session_name("ddns-route53")
+    .configure(&config)
+    .build()
+    .await;
+
+  let config = route53::config::Builder::from(&config)
+    .credentials_provider(credentials)
+    .build();
+
+  route53::Client::from_conf(config)
+}
+
+/// Parses a positive request rate.
+fn parse_rate(s: &str) -> Result<f64> {
+  match s.parse::<f64>() {
+    Ok(rate) if rate > 0.0 && rate.is_finite() => Ok(rate),
+    _ => bail!("Expected a positive number of requests per second."),
+  }
+}
+
+/// Sends a Route 53 request once the pacer allows it, slowing down the pacer
+/// if Route 53 throttles it and counting failures.
+async fn paced<T, E: ProvideErrorMetadata>(
+  request: impl Future<Output = Result<T, SdkError<E>>>,
+) -> Result<T, SdkError<E>> {
+  pacer::wait().await;
+
+  let result = request.await;
+
+  match &result {
+    Ok(_) => pacer::succeeded(),
+
+    Err(err) => {
+      let code = err.as_service_error().and_then(|e| e.code());
+
+      pacer::failed();
+
+      if code.is_some_and(|code| THROTTLING.contains(&code)) {
+        pacer::throttled();
+      }
+    }
+  }
+
+  result
+}
+
+/// Returns `true` if an error was caused by a Route 53 request that failed
+/// because of throttling or a network or server error, rather than being
+/// rejected, so that trying again later may succeed.
+pub fn is_transient(err: &anyhow::Error) -> bool {
+  err.chain().any(|cause| {
+    transient::<ListHostedZonesError>(cause)
+      || transient::<GetHostedZoneError>(cause)
+      || transient::<ListResourceRecordSetsError>(cause)
+      || transient::<ChangeResourceRecordSetsError>(cause)
+      || transient::<GetChangeError>(cause)
+  })
+}
+
+/// Returns `true` if an error is a transient failure of a request of the
+/// operation with errors of type `E`.
+fn transient<E: ProvideErrorMetadata + Error + 'static>(cause: &(dyn Error + 'static)) -> bool {
+  match cause.downcast_ref::<SdkError<E>>() {
+    Some(SdkError::TimeoutError(_) | SdkError::ResponseError(_)) => true,
+    Some(SdkError::DispatchFailure(failure)) => failure.is_io() || failure.is_timeout(),
+
+    Some(SdkError::ServiceError(err)) => {
+      err.raw().status().is_server_error()
+        || err
+          .err()
+          .code()
+          .is_some_and(|code| THROTTLING.contains(&code))
+    }
+
+    _ => false,
+  }
+}
+
+/// Lists the hosted zones visible to the client, requesting every page of
+/// them since Route 53 returns at most 100 zones per page.
+pub async fn list_zones(route53: &route53::Client) -> Result<Vec<HostedZone>> {
+  traced(info_span!("route53_list_hosted_zones"), async {
+    let mut pages = route53.list_hosted_zones().into_paginator().send();
+    let mut zones = Vec::new();
+
+    // each page is a request of its own
+    while let Some(page) = paced(async { pages.next().await.transpose() }).await? {
+      zones.extend(page.hosted_zones);
+    }
+
+    Ok(zones)
+  })
+  .await
+  .with_context(|| "Failed to list Route 53 hosted zones.")
+}
+
+/// Route 53 clients of the identities that domains are looked up with, each
+/// with the hosted zones visible to it once they have been listed.
+///
+/// Zones are only listed for domains that are not given a zone ID, so that
+/// credentials without access to `ListHostedZones` can still look them up.
+pub struct Accounts<'a> {
+  options: &'a AwsOptions,
+  accounts: Vec<Account>,
+}
+
+/// Route 53 client of an identity.
+struct Account {
+  identity: Identity,
+  route53: route53::Client,
+  /// Hosted zones visible to the client, or why they could not be listed,
+  /// once they are.
+  zones: Option<Result<Vec<HostedZone>, String>>,
+}
+
+/// Hosted zone that the records of a domain belong to.
+pub struct Zone {
+  /// Full ID of the zone, such as `/hostedzone/Z123`.
+  pub id: String,
+  /// Name of the zone without the trailing dot, unless the zone was chosen
+  /// by ID and its name is not known.
+  pub name: Option<String>,
+}
+
+impl<'a> Accounts<'a> {
+  pub fn new(options: &'a AwsOptions) -> Self {
+    Self {
+      options,
+      accounts: Vec::new(),
+    }
+  }
+
+  /// Returns the client of an identity, creating it the first time.
+  pub async fn client(&mut self, identity: &Identity) -> &route53::Client {
+    let index = self.index(identity).await;
+
+    &self.accounts[index].route53
+  }
+
+  /// Finds the hosted zone of a domain, listing the zones visible to the
+  /// identity the first time a domain without a zone ID needs them.
+  pub async fn zone(
+    &mut self,
+    identity: &Identity,
+    name: &DomainName,
+    choice: &ZoneChoice,
+  ) -> Result<Zone> {
+    if let Some(id) = choice.zone_id() {
+      return Ok(Zone { id, name: None });
+    }
+
+    let zone = zone::find(self.zones(identity).await?, name, choice)?;
+
+    Ok(Zone {
+      id: zone.id.clone(),
+      name: Some(zone.name.trim_end_matches('.').into()),
+    })
+  }
+
+  /// Returns the hosted zones visible to an identity, listing them the first
+  /// time.
+  pub async fn zones(&mut self, identity: &Identity) -> Result<&[HostedZone]> {
+    let index = self.index(identity).await;
+    let Account { route53, zones, .. } = &mut self.accounts[index];
+
+    let zones = match zones {
+      Some(zones) => zones,
+      None => zones.insert(list_zones(route53).await.map_err(|err| format!("{err:#}"))),
+    };
+
+    match zones {
+      Ok(zones) => Ok(zones),
+      Err(err) => Err(anyhow!("{err}")),
+    }
+  }
+
+  /// Returns the index of the account of an identity, connecting it first if
+  /// it has none.
+  async fn index(&mut self, identity: &Identity) -> usize {
+    if let Some(index) = self.accounts.iter().position(|a| a.identity == *identity) {
+      return index;
+    }
+
+    self.accounts.push(Account {
+      identity: identity.clone(),
+      route53: connect_as(self.options, identity).await,
+      zones: None,
+    });
+
+    self.accounts.len() - 1
+  }
+}
+
+/// Returns the name servers of a public hosted zone, or nothing for a private
+/// zone.
+pub async fn name_servers(route53: &route53::Client, zone_id: &str) -> Result<Vec<String>> {
+  traced(info_span!("route53_get_hosted_zone", zone_id), async {
+    let output = paced(route53.get_hosted_zone().id(zone_id).send()).await?;
+
+    Ok(
+      output
+        .delegation_set
+        .map(|set| set.name_servers)
+        .unwrap_or_default(),
+    )
+  })
+  .await
+  .with_context(|| format!("Failed to get hosted zone `{zone_id}`."))
+}
+
+/// Finds the record set with the given name and type in a hosted zone.
+///
+/// With routing, finds this instance's member of a routed record set.
+/// Otherwise finds the first record set with the name and type.
+pub async fn find_record(
+  route53: &route53::Client,
+  zone_id: &str,
+  name: &str,
+  rr_type: RrType,
+  routing: Option<&Routing>,
+) -> Result<Option<ResourceRecordSet>> {
+  let span = info_span!("route53_list_resource_record_sets", zone_id, name);
+  let identifier = routing.map(|r| r.set_identifier.as_str());
+
+  let sets = traced(span, async {
+    let request = route53
+      .list_resource_record_sets()
+      .hosted_zone_id(zone_id)
+      .start_record_name(name)
+      .start_record_type(rr_type.clone())
+      .set_start_record_identifier(identifier.map(String::from))
+      .max_items(1)
+      .send();
+
+    Ok(paced(request).await?.resource_record_sets)
+  })
+  .await
+  .with_context(|| {
+    format!(
+      "Failed to look up the {} record of `{name}`.",
+      rr_type.as_str()
+    )
+  })?;
+
+  // the listing starts at the requested record but continues past it if the
+  // record does not exist
+  Ok(sets.into_iter().find(|set| {
+    set.r#type == rr_type
+      && DomainName::from_route53(&set.name).is_ok_and(|n| n.as_str() == name)
+      && identifier.is_none_or(|id| set.set_identifier.as_deref() == Some(id))
+  }))
+}
+
+/// Builds a change that upserts an A or AAAA record, depending on the family
+/// of the IP, optionally as a member of a routed record set.
+pub fn upsert(name: &str, ip: IpAddr, ttl: i64, routing: Option<&Routing>) -> Result<Change> {
+  let rr_type = match ip {
+    IpAddr::V4(_) => RrType::A,
+    IpAddr::V6(_) => RrType::Aaaa,
+  };
+
+  routed_upsert(record_set(rr_type, name, &[&ip.to_string()], ttl)?, routing)
+}
+
+/// Builds a change that sets the TXT record of a name to a value, as this
+/// instance's member of a routed record set if routing is given.
+pub fn upsert_txt(name: &str, value: &str, ttl: i64, routing: Option<&Routing>) -> Result<Change> {
+  routed_upsert(record_set(RrType::Txt, name, &[value], ttl)?, routing)
+}
+
+/// Builds a change that upserts a record set with the given routing.
+fn routed_upsert(mut set: ResourceRecordSetBuilder, routing: Option<&Routing>) -> Result<Change> {
+  if let Some(routing) = routing {
+    set = set
+      .set_identifier(&routing.set_identifier)
+      .set_health_check_id(routing.health_check_id.clone());
+
+    set = match routing.weight {
+      Some(weight) => set.weight(weight),
+      None => set.multi_value_answer(true),
+    };
+  }
+
+  Ok(
+    Change::builder()
+      .action(ChangeAction::Upsert)
+      .resource_record_set(set.build()?)
+      .build()?,
+  )
+}
+
+/// Builds a change that deletes a record set, which must be given exactly as it
+/// currently exists.
+pub fn delete(set: ResourceRecordSet) -> Result<Change> {
+  Ok(
+    Change::builder()
+      .action(ChangeAction::Delete)
+      .resource_record_set(set)
+      .build()?,
+  )
+}
+
+/// Builds a change to a record set with the given values.
+pub fn change(
+  action: ChangeAction,
+  rr_type: RrType,
+  name: &str,
+  values: &[&str],
+  ttl: i64,
+) -> Result<Change> {
+  Ok(
+    Change::builder()
+      .action(action)
+      .resource_record_set(record_set(rr_type, name, values, ttl)?.build()?)
+      .build()?,
+  )
+}
+
+/// Starts building a simple record set with the given values.
+fn record_set(
+  rr_type: RrType,
+  name: &str,
+  values: &[&str],
+  ttl: i64,
+) -> Result<ResourceRecordSetBuilder> {
+  let mut set = ResourceRecordSet::builder()
+    .r#type(rr_type)
+    .name(name)
+    .ttl(ttl);
+
+  for value in values {
+    set = set.resource_records(ResourceRecord::builder().value(*value).build()?);
+  }
+
+  Ok(set)
+}
+
+/// A batch of changes that Route 53 accepted.
+pub struct Submitted {
+  /// ID of the change, for waiting until it is in sync.
+  pub change_id: String,
+  /// ID AWS assigned to the request, for support cases.
+  pub request_id: Option<String>,
+}
+
+/// Submits a batch of changes to a hosted zone.
+///
+/// If Route 53 rejects the batch, the returned error names each rejected
+/// change along with the reason Route 53 gave for rejecting it.
+pub async fn change_records(
+  route53: &route53::Client,
+  zone_id: &str,
+  changes: Vec<Change>,
+) -> Result<Submitted> {
+  let batch = ChangeBatch::builder()
+    .set_changes(Some(changes.clone()))
+    .build()?;
+
+  let span = info_span!(
+    "route53_change_resource_record_sets",
+    zone_id,
+    changes = changes.len(),
+    aws_request_id = field::Empty,
+  );
+
+  traced(span, async {
+    let request = route53
+      .change_resource_record_sets()
+      .hosted_zone_id(zone_id)
+      .change_batch(batch)
+      .send();
+
+    let result = paced(request).await;
+    let request_id = match &result {
+      Ok(output) => output.request_id(),
+      Err(err) => err.request_id(),
+    };
+
+    Span::current().record("aws_request_id", request_id);
+
+    match result {
+      Ok(output) => Ok(Submitted {
+        request_id: output.request_id().map(String::from),
+        change_id: output.change_info.map(|info| info.id).unwrap_or_default(),
+      }),
+
+      Err(err) => Err(explain_rejection(err, &changes)),
+    }
+  })
+  .await
+}
+
+/// Waits until a change has propagated to all Route 53 name servers.
+pub async fn wait_for_sync(route53: &route53::Client, change_id: &str) -> Result<()> {
+  const INTERVAL: Duration = Duration::from_secs(5);
+  const TIMEOUT: Duration = Duration::from_secs(600);
+
+  let started = Instant::now();
+
+  loop {
+    let status = traced(info_span!("route53_get_change", change_id), async {
+      let output = paced(route53.get_change().id(change_id).send()).await?;
+
+      Ok(output.change_info.map(|info| info.status))
+    })
+    .await
+    .with_context(|| format!("Failed to get the status of change `{change_id}`."))?;
+
+    if status == Some(ChangeStatus::Insync) {
+      return Ok(());
+    }
+
+    if started.elapsed() > TIMEOUT {
+      bail!(
+        "Change `{change_id}` is still pending after {}.",
+        humantime::format_duration(TIMEOUT)
+      );
+    }
+
+    tokio::time::sleep(INTERVAL).await;
+  }
+}
+
+/// Converts an error from submitting a change batch into one that names each
+/// change Route 53 rejected, if it rejected any.
+fn explain_rejection(
+  err: SdkError<ChangeResourceRecordSetsError>,
+  changes: &[Change],
+) -> anyhow::Error {
+  let Some(ChangeResourceRecordSetsError::InvalidChangeBatch(rejection)) = err.as_service_error()
+  else {
+    return err.into();
+  };
+
+  let mut messages = rejection.messages().to_vec();
+
+  if messages.is_empty() {
+    messages.extend(rejection.message().map(String::from));
+  }
+
+  let mut report = String::from("Route 53 rejected the change batch:");
+
+  for message in &messages {
+    let change = match find_change(changes, message) {
+      Some(change) => describe(change),
+      None => "Unknown change".into(),
+    };
+
+    report.push_str(&format!("\n  {change}: {message}"));
+  }
+
+  anyhow!(report)
+}
+
+/// Finds the change an error message from Route 53 refers to.
+///
+/// Messages mention the record name, usually with a trailing dot, so the
+/// change with the longest name that appears in the message is chosen to
+/// avoid confusing a record with its subdomains. Of changes with the same
+/// name, such as the A and AAAA records of a domain, the one of the type the
+/// message mentions is chosen.
+fn find_change<'a>(changes: &'a [Change], message: &str) -> Option<&'a Change> {
+  let rr_type = mentioned_type(message);
+
+  changes
+    .iter()
+    .filter_map(|change| {
+      let set = change.resource_record_set()?;
+      let name = set.name().trim_end_matches('.');
+      let mentioned = message.match_indices(name).any(|(i, _)| {
+        let before = message[..i].chars().next_back();
+        let after = message[i + name.len()..].chars().next();
+
+        // the name must not be part of a longer name
+        !before.is_some_and(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+          && !after.is_some_and(|c| c.is_ascii_alphanumeric() || c == '-')
+      });
+
+      let same_type = rr_type.is_some_and(|t| set.r#type().as_str() == t);
+
+      mentioned.then_some(((name.len(), same_type), change))
+    })
+    .max_by_key(|(key, _)| *key)
+    .map(|(_, change)| change)
+}
+
+/// Returns the record type an error message from Route 53 mentions, as in
+/// `[name='home.example.com.', type='A']` or `RRSet of type A with DNS name`.
+fn mentioned_type(message: &str) -> Option<&str> {
+  let (_, rest) = message
+    .split_once("type='")
+    .or_else(|| message.split_once("of type "))?;
+
+  rest.split(|c: char| !c.is_ascii_alphanumeric()).next()
+}
+
+/// Describes a change as its action, record type, name, values, and TTL.
+pub fn describe(change: &Change) -> String {
+  let Some(set) = change.resource_record_set() else {
+    return change.action().as_str().into();
+  };
+
+  let values: Vec<&str> = set.resource_records().iter().map(|r| r.value()).collect();
+
+  let mut description = format!(
+    "{} {} `{}` → {}",
+    change.action().as_str(),
+    set.r#type().as_str(),
+    set.name(),
+    values.join(", "),
+  );
+
+  if let Some(ttl) = set.ttl() {
+    description.push_str(&format!(" (TTL {ttl}s)"));
+  }
+
+  if let Some(id) = set.set_identifier() {
+    description.push_str(&format!(" as `{id}`"));
+  }
+
+  if let Some(weight) = set.weight() {
+    description.push_str(&format!(" with weight {weight}"));
+  }
+
+  description
+}
+
+#[cfg(test)]
+mod tests {
+  use aws_sdk_route53::types::error::InvalidChangeBatch;
+  use aws_smithy_runtime_api::http::Response;
+  use aws_smithy_types::body::SdkBody;
+
+  use super::*;
+
+  fn changes() -> Vec<Change> {
+    [
+      ("home.example.com", "203.0.113.7"),
+      ("home.example.com", "2001:db8::7"),
+      ("nas.home.example.com", "203.0.113.8"),
+    ]
+    .into_iter()
+    .map(|(name, ip)| upsert(name, ip.parse().unwrap(), 300, None).unwrap())
+    .collect()
+  }
+
+  fn found(changes: &[Change], message: &str) -> Option<String> {
+    find_change(changes, message).map(describe)
+  }
+
+  #[test]
+  fn finds_the_change_of_the_mentioned_name_and_type() {
+    let changes = changes();
+
+    assert_eq!(
+      found(
+        &changes,
+        "Tried to create resource record set [name='home.example.com.', type='AAAA'] but it \
+         already exists"
+      ),
+      Some(describe(&changes[1]))
+    );
+
+    assert_eq!(
+      found(
+        &changes,
+        "Tried to delete resource record set [name='home.example.com.', type='A', \
+         set-identifier='nas'] but it was not found"
+      ),
+      Some(describe(&changes[0]))
+    );
+
+    assert_eq!(
+      found(
+        &changes,
+        "RRSet of type A with DNS name nas.home.example.com. is not permitted because a \
+         conflicting RRSet of type CNAME with the same DNS name already exists in zone \
+         example.com."
+      ),
+      Some(describe(&changes[2]))
+    );
+
+    assert_eq!(
+      found(
+        &changes,
+        "Invalid Resource Record: 'FATAL problem: ARRDATAIllegalIPv4Address (Value is not a \
+         valid IPv4 address) encountered with '203.0.113''"
+      ),
+      None
+    );
+  }
+
+  #[test]
+  fn names_each_rejected_change() {
+    let changes = changes();
+    let rejection = InvalidChangeBatch::builder()
+      .messages(
+        "Tried to create resource record set [name='home.example.com.', type='AAAA'] but it \
+         already exists",
+      )
+      .messages("RRSet with DNS name other.example.org. is not permitted in zone example.com.")
+      .build();
+
+    let err = SdkError::service_error(
+      ChangeResourceRecordSetsError::InvalidChangeBatch(rejection),
+      Response::new(400.try_into().unwrap(), SdkBody::empty()),
+    );
+
+    let report = explain_rejection(err, &changes).to_string();
+    let lines: Vec<&str> = report.lines().collect();
+
+    assert_eq!(lines[0], "Route 53 rejected the change batch:");
+    assert!(lines[1].starts_with(&format!("  {}: Tried to create", describe(&changes[1]))));
+    assert!(lines[2].starts_with("  Unknown change: RRSet with DNS name other.example.org."));
+  }
+}
@@ -0,0 +1,33 @@
+use std::{
+  hash::{BuildHasher, RandomState},
+  time::Duration,
+};
+
+/// Exponential backoff between retries, with random jitter so that clients
+/// that failed at the same time do not all retry at the same time.
+#[derive(Clone, Copy)]
+pub struct Backoff {
+  /// Delay before the first retry, before jitter.
+  pub base: Duration,
+  /// Longest delay before any retry.
+  pub max: Duration,
+}
+
+impl Backoff {
+  /// Returns how long to wait before retrying after a number of earlier
+  /// retries.
+  ///
+  /// The delay doubles with each retry up to the maximum, and a random part
+  /// of up to half of it is taken off.
+  pub fn delay(&self, retries: u32) -> Duration {
+    let delay = self.base.saturating_mul(1 << retries.min(20)).min(self.max);
+
+    delay.mul_f64(1.0 - random() / 2.0)
+  }
+}
+
+/// Returns a random number between 0 and 1.
+fn random() -> f64 {
+  // the hasher is randomly seeded, which is random enough for jitter
+  RandomState::new().hash_one(()) as f64 / u64::MAX as f64
+}
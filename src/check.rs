@@ -0,0 +1,229 @@
+use std::{collections::BTreeSet, path::Path, time::Duration};
+
+use anyhow::{bail, Result};
+use aws_sdk_route53::types::RrType;
+use chrono::{DateTime, TimeDelta, Utc};
+use clap::ValueEnum;
+
+use ddns_route53::{
+  aws::{self, Accounts, AwsOptions},
+  provider,
+  state::State,
+};
+
+use crate::{format_duration, Domain};
+
+/// Output format of the check.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+  /// A human-readable report.
+  Text,
+  /// A Nagios plugin status line with performance data.
+  Nagios,
+}
+
+/// Health of the daemon, ordered from best to worst, with the exit codes of
+/// the Nagios plugin interface.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Status {
+  Ok = 0,
+  Warning = 1,
+  Critical = 2,
+  Unknown = 3,
+}
+
+/// Checks the health of a running daemon from its state file and returns the
+/// status code to exit with.
+pub fn run(
+  state_file: Option<&Path>,
+  format: Format,
+  warning: Duration,
+  critical: Duration,
+) -> i32 {
+  let (status, summary, perfdata) = match check(state_file, warning, critical) {
+    Ok(report) => report,
+    Err(err) => (Status::Unknown, format!("{err:#}"), String::new()),
+  };
+
+  let label = match status {
+    Status::Ok => "OK",
+    Status::Warning => "WARNING",
+    Status::Critical => "CRITICAL",
+    Status::Unknown => "UNKNOWN",
+  };
+
+  match format {
+    Format::Text => println!("{label}: {summary}"),
+    Format::Nagios if perfdata.is_empty() => println!("DDNS {label} - {summary}"),
+    Format::Nagios => println!("DDNS {label} - {summary} | {perfdata}"),
+  }
+
+  status as i32
+}
+
+/// Checks that domains are valid, that their credentials can list hosted
+/// zones, and that the records of each domain can be looked up in its zone,
+/// without making changes. Returns the status code to exit with.
+pub async fn setup(domains: Result<Vec<Domain>>, format: Format, options: &AwsOptions) -> i32 {
+  let domains = match domains {
+    Ok(domains) => domains,
+
+    Err(err) => {
+      match format {
+        Format::Text => println!("CRITICAL: {err:#}"),
+        Format::Nagios => println!("DDNS CRITICAL - {err:#}"),
+      }
+
+      return Status::Critical as i32;
+    }
+  };
+
+  let mut accounts = Accounts::new(options);
+  let mut failing = Vec::new();
+
+  for domain in &domains {
+    // zones of other providers are checked when the daemon starts
+    if domain.provider != provider::Kind::Route53 {
+      continue;
+    }
+
+    let result = async {
+      let zone = accounts
+        .zone(&domain.identity, &domain.name, &domain.zone)
+        .await?;
+
+      let route53 = accounts.client(&domain.identity).await;
+
+      for rr_type in [RrType::A, RrType::Aaaa] {
+        aws::find_record(route53, &zone.id, &domain.name, rr_type, None).await?;
+      }
+
+      anyhow::Ok(zone)
+    };
+
+    match (result.await, format) {
+      (Ok(zone), Format::Text) => match zone.name {
+        Some(name) => println!("OK: `{}` is in zone {} ({name}).", domain.name, zone.id),
+        None => println!("OK: `{}` is in zone {}.", domain.name, zone.id),
+      },
+
+      (Ok(_), Format::Nagios) => {}
+
+      (Err(err), format) => {
+        if let Format::Text = format {
+          println!("CRITICAL: `{}`: {err:#}", domain.name);
+        }
+
+        failing.push(domain.name.as_str());
+      }
+    }
+  }
+
+  let (status, label) = match failing.is_empty() {
+    true => (Status::Ok, "OK"),
+    false => (Status::Critical, "CRITICAL"),
+  };
+
+  let summary = match failing.is_empty() {
+    true => format!("{} domains are ready to update", domains.len()),
+    false => format!("cannot update: {}", failing.join(", ")),
+  };
+
+  match format {
+    Format::Text => println!("{label}: {summary}"),
+    Format::Nagios => println!(
+      "DDNS {label} - {summary} | domains={};;;0 failing={};;0;0",
+      domains.len(),
+      failing.len()
+    ),
+  }
+
+  status as i32
+}
+
+/// Determines the health of the daemon along with a summary and perfdata.
+fn check(
+  state_file: Option<&Path>,
+  warning: Duration,
+  critical: Duration,
+) -> Result<(Status, String, String)> {
+  let Some(path) = state_file else {
+    bail!("The check command requires --state-file.");
+  };
+
+  let state = State::load(path)?;
+  let now = Utc::now();
+  let (warning, critical) = (
+    TimeDelta::from_std(warning)?,
+    TimeDelta::from_std(critical)?,
+  );
+
+  let mismatched: Vec<&str> = state
+    .domains
+    .iter()
+    .filter(|(_, stats)| stats.last_error.is_some())
+    .map(|(name, _)| name)
+    .chain(state.pending.keys())
+    .chain(state.pending_v6.keys())
+    .map(String::as_str)
+    .collect::<BTreeSet<_>>()
+    .into_iter()
+    .collect();
+
+  // unknown values are reported as `U` without a unit
+  let seconds = |time: Option<DateTime<Utc>>| match time {
+    Some(time) => format!("{}s", (now - time).num_seconds().max(0)),
+    None => "U".into(),
+  };
+
+  let since_pass = seconds(state.checked_at);
+  let since_write = seconds(state.last_write_at);
+
+  let mut status = Status::Ok;
+  let mut problems = Vec::new();
+
+  match state.checked_at.map(|time| now - time) {
+    None => {
+      status = Status::Warning;
+      problems.push("the daemon has not completed an update pass".into());
+    }
+
+    Some(age) if age > warning => {
+      status = if age > critical {
+        Status::Critical
+      } else {
+        Status::Warning
+      };
+
+      problems.push(format!("no update pass for {}", format_duration(age)));
+    }
+
+    Some(_) => {}
+  }
+
+  if !mismatched.is_empty() {
+    status = status.max(Status::Critical);
+    problems.push(format!("out of date: {}", mismatched.join(", ")));
+  }
+
+  if state.held_until.is_some_and(|until| until > now) {
+    status = status.max(Status::Warning);
+    problems.push("updates are held down because the public IP is flapping".into());
+  }
+
+  let summary = match (problems.is_empty(), state.public_ip) {
+    (true, Some(ip)) => format!("{} domains up to date at {ip}", state.domains.len()),
+    (true, None) => format!("{} domains up to date", state.domains.len()),
+    (false, _) => problems.join("; "),
+  };
+
+  let perfdata = format!(
+    "seconds_since_pass={since_pass};{};{};0 seconds_since_write={since_write};;;0 \
+     mismatched={};;0;0",
+    warning.num_seconds(),
+    critical.num_seconds(),
+    mismatched.len(),
+  );
+
+  Ok((status, summary, perfdata))
+}
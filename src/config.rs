@@ -0,0 +1,170 @@
+use std::{collections::BTreeMap, ffi::OsString, fs, net::Ipv6Addr, path::Path, time::Duration};
+
+use anyhow::{bail, Context, Result};
+use clap::{parser::ValueSource, ArgAction, ArgMatches, Command};
+use serde::{Deserialize, Deserializer};
+use toml::{Table, Value};
+
+use ddns_route53::{ip::Family, private::PrivateSource, provider::Kind};
+
+/// Settings loaded from a TOML file with `--config`.
+pub struct Config {
+  /// Command line arguments equivalent to the global settings of the file,
+  /// except for those that were given on the command line itself.
+  pub args: Vec<OsString>,
+  /// Domains declared in the file, with their settings.
+  pub domains: Vec<DomainConfig>,
+}
+
+/// Settings of a domain declared in a config file.
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct DomainConfig {
+  #[serde(skip)]
+  pub name: String,
+  /// Hosted zone to choose: `public`, `private`, or a zone ID. A zone ID may
+  /// also be given as `zone-id`.
+  #[serde(alias = "zone-id")]
+  pub zone: Option<String>,
+  /// TTL of the domain's records, overriding the global TTL.
+  #[serde(default, deserialize_with = "duration")]
+  pub ttl: Option<Duration>,
+  /// Types of the records that follow the public IP.
+  #[serde(rename = "type")]
+  pub record_types: Option<RecordTypes>,
+  /// AWS profile whose credentials update the domain, if they differ from the
+  /// global ones.
+  pub profile: Option<String>,
+  /// ARN of an IAM role to assume to update the domain, such as a role of the
+  /// AWS account that owns its hosted zone.
+  pub role_arn: Option<String>,
+  /// Private address of this machine that the domain's records point at
+  /// instead of the public IP: `ec2-metadata` or `local`.
+  pub source: Option<PrivateSource>,
+  /// Interface identifier of a host in the delegated IPv6 prefix, which the
+  /// domain's AAAA record combines with the current prefix.
+  pub suffix: Option<Ipv6Addr>,
+  /// Provider that hosts the zone of the domain, `route53` unless given.
+  #[serde(default)]
+  pub provider: Kind,
+  /// Whether the domain is managed. Disabled domains are left alone.
+  #[serde(default = "enabled")]
+  pub enabled: bool,
+}
+
+/// Types of the records of a domain that follow the public IP.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+pub enum RecordTypes {
+  /// Only the A record.
+  A,
+  /// Only the AAAA record.
+  #[serde(rename = "AAAA")]
+  Aaaa,
+  /// Both the A and AAAA records.
+  #[serde(rename = "both")]
+  Both,
+}
+
+impl RecordTypes {
+  /// Returns `true` if the record of a family is included.
+  pub const fn includes(self, family: Family) -> bool {
+    matches!(
+      (self, family),
+      (Self::Both, _) | (Self::A, Family::V4) | (Self::Aaaa, Family::V6)
+    )
+  }
+}
+
+/// Loads a config file.
+///
+/// Each global setting corresponds to the command line option with the same
+/// name, such as `interval = "1m"` to `--interval 1m`. Settings of options
+/// that were given on the command line or in the environment are ignored so
+/// that those take precedence.
+pub fn load(path: &Path, command: &Command, matches: &ArgMatches) -> Result<Config> {
+  let text =
+    fs::read_to_string(path).with_context(|| format!("Failed to read config file {path:?}."))?;
+
+  let mut table: Table =
+    toml::from_str(&text).with_context(|| format!("Failed to parse config file {path:?}."))?;
+
+  let domains = match table.remove("domains") {
+    Some(value) => parse_domains(value).with_context(|| format!("Invalid domains in {path:?}."))?,
+    None => Vec::new(),
+  };
+
+  let mut args = Vec::new();
+
+  for (key, value) in table {
+    let arg = command
+      .get_arguments()
+      .find(|arg| arg.get_long() == Some(key.as_str()) && key != "config");
+
+    let Some(arg) = arg else {
+      bail!("Unknown setting `{key}` in config file {path:?}.");
+    };
+
+    let explicit = matches!(
+      matches.value_source(arg.get_id().as_str()),
+      Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+    );
+
+    if explicit {
+      continue;
+    }
+
+    let values = match value {
+      Value::Array(values) => values,
+      value => vec![value],
+    };
+
+    for value in values {
+      let value = match value {
+        Value::String(s) => s,
+        Value::Integer(n) => n.to_string(),
+        Value::Float(n) => n.to_string(),
+
+        Value::Boolean(set) if matches!(arg.get_action(), ArgAction::SetTrue) => {
+          if set {
+            args.push(format!("--{key}").into());
+          }
+
+          continue;
+        }
+
+        _ => bail!("Invalid value for `{key}` in config file {path:?}."),
+      };
+
+      args.push(format!("--{key}={value}").into());
+    }
+  }
+
+  Ok(Config { args, domains })
+}
+
+/// Parses the `[domains]` table, keyed by domain name.
+fn parse_domains(value: Value) -> Result<Vec<DomainConfig>> {
+  let table: BTreeMap<String, DomainConfig> = value.try_into()?;
+
+  Ok(
+    table
+      .into_iter()
+      .map(|(name, domain)| DomainConfig { name, ..domain })
+      .collect(),
+  )
+}
+
+/// Deserializes a duration such as `5m` or `1h`.
+fn duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+  match Option::<String>::deserialize(deserializer)? {
+    Some(s) => humantime::parse_duration(&s)
+      .map(Some)
+      .map_err(serde::de::Error::custom),
+
+    None => Ok(None),
+  }
+}
+
+const fn enabled() -> bool {
+  true
+}
@@ -0,0 +1,91 @@
+use std::net::IpAddr;
+
+use anyhow::{anyhow, Context, Result};
+use reqwest::Url;
+use serde_json::json;
+use tracing::{info, info_span};
+
+use crate::{http::Http, trace::traced};
+
+/// Command line options for registering with Consul.
+#[derive(clap::Args)]
+#[command(next_help_heading = "Consul")]
+pub struct ConsulOptions {
+  /// Base URL of the Consul agent to register the public IP with as the
+  /// address of a service.
+  #[arg(long, value_name = "URL", requires = "consul_service")]
+  consul_url: Option<Url>,
+
+  /// Name of the Consul service to register.
+  #[arg(long, value_name = "NAME", requires = "consul_url")]
+  consul_service: Option<String>,
+
+  /// ACL token to register the service with.
+  #[arg(long, value_name = "TOKEN", requires = "consul_url")]
+  consul_token: Option<String>,
+}
+
+/// A Consul agent that a service is registered with.
+pub struct Consul {
+  http: reqwest::Client,
+  url: Url,
+  service: String,
+  token: Option<String>,
+  /// Address the service was last registered with.
+  registered_ip: Option<IpAddr>,
+}
+
+impl Consul {
+  /// Creates a client for the Consul agent given in the options, if any.
+  pub fn new(options: ConsulOptions, http: &Http) -> Option<Self> {
+    Some(Self {
+      http: http.any.clone(),
+      url: options.consul_url?,
+      service: options.consul_service?,
+      token: options.consul_token,
+      registered_ip: None,
+    })
+  }
+
+  /// Registers the service with the given address, tagged with the domain
+  /// names that point at it, unless it is already registered with it.
+  pub async fn register(&mut self, ip: IpAddr, domains: &[&str]) -> Result<()> {
+    if self.registered_ip == Some(ip) {
+      return Ok(());
+    }
+
+    let mut url = self.url.clone();
+
+    url
+      .path_segments_mut()
+      .map_err(|()| anyhow!("Invalid Consul URL."))?
+      .pop_if_empty()
+      .extend(["v1", "agent", "service", "register"]);
+
+    let mut request = self.http.put(url).json(&json!({
+      "ID": self.service,
+      "Name": self.service,
+      "Address": ip.to_string(),
+      "Meta": { "domains": domains.join(",") },
+    }));
+
+    if let Some(token) = &self.token {
+      request = request.header("X-Consul-Token", token);
+    }
+
+    let span = info_span!("consul_register", service = self.service);
+
+    traced(span, async {
+      request.send().await?.error_for_status()?;
+
+      Ok(())
+    })
+    .await
+    .with_context(|| format!("Failed to register `{}` with Consul.", self.service))?;
+
+    info!("Registered `{}` with Consul at {ip}.", self.service);
+    self.registered_ip = Some(ip);
+
+    Ok(())
+  }
+}
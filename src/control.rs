@@ -0,0 +1,62 @@
+use std::{
+  collections::BTreeSet,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+  },
+  time::Duration,
+};
+
+use tokio::sync::Notify;
+
+/// Handles for controlling the update loop from outside of it.
+#[derive(Clone, Default)]
+pub struct Controls {
+  paused: Arc<AtomicBool>,
+  /// Names of domains whose updates are paused individually.
+  paused_domains: Arc<Mutex<BTreeSet<String>>>,
+  wake: Arc<Notify>,
+}
+
+impl Controls {
+  /// Returns `true` if updates are paused.
+  pub fn is_paused(&self) -> bool {
+    self.paused.load(Ordering::Relaxed)
+  }
+
+  /// Pauses or resumes updates.
+  #[cfg_attr(not(feature = "tray"), allow(dead_code))]
+  pub fn set_paused(&self, paused: bool) {
+    self.paused.store(paused, Ordering::Relaxed);
+  }
+
+  /// Returns `true` if updates of a domain are paused.
+  pub fn is_domain_paused(&self, name: &str) -> bool {
+    self.paused_domains.lock().unwrap().contains(name)
+  }
+
+  /// Pauses or resumes updates of a domain.
+  ///
+  /// Returns `false` if the domain was already paused or resumed.
+  pub fn set_domain_paused(&self, name: &str, paused: bool) -> bool {
+    let mut domains = self.paused_domains.lock().unwrap();
+
+    match paused {
+      true => domains.insert(name.into()),
+      false => domains.remove(name),
+    }
+  }
+
+  /// Requests an update pass as soon as possible.
+  pub fn update_now(&self) {
+    self.wake.notify_one();
+  }
+
+  /// Waits for the given duration or until an update pass is requested.
+  pub async fn sleep(&self, duration: Duration) {
+    tokio::select! {
+      _ = tokio::time::sleep(duration) => {}
+      _ = self.wake.notified() => {}
+    }
+  }
+}
@@ -0,0 +1,312 @@
+use std::{
+  fmt,
+  net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+  time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use reqwest::Client;
+use tokio::{
+  io::{AsyncReadExt, AsyncWriteExt},
+  net::{lookup_host, TcpStream, UdpSocket},
+  time::timeout,
+};
+
+/// How long to wait for a name server to answer by default.
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Media type of DNS messages sent over HTTPS.
+const DNS_MESSAGE: &str = "application/dns-message";
+
+/// Types of records that can be queried.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum RecordType {
+  A,
+  Aaaa,
+  Txt,
+}
+
+impl RecordType {
+  const fn code(self) -> u16 {
+    match self {
+      Self::A => 1,
+      Self::Aaaa => 28,
+      Self::Txt => 16,
+    }
+  }
+}
+
+impl fmt::Display for RecordType {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(match self {
+      Self::A => "A",
+      Self::Aaaa => "AAAA",
+      Self::Txt => "TXT",
+    })
+  }
+}
+
+/// Resolves the address of a name server given by host name.
+pub async fn server(host: &str) -> Result<SocketAddr> {
+  lookup_host((host.trim_end_matches('.'), 53))
+    .await
+    .ok()
+    .and_then(|mut addrs| addrs.next())
+    .with_context(|| format!("Failed to resolve name server `{host}`."))
+}
+
+/// Asks a name server for the records of a name.
+///
+/// Without `recursive`, an authoritative name server answers directly so that
+/// the answer does not come from a cache. With it, a recursive resolver looks
+/// the name up on our behalf.
+///
+/// Returns the records in presentation format, with the strings of each TXT
+/// record concatenated. The query is sent over UDP and repeated over TCP if
+/// the answer is truncated.
+pub async fn query(
+  server: SocketAddr,
+  name: &str,
+  rr_type: RecordType,
+  recursive: bool,
+  wait: Duration,
+) -> Result<Vec<String>> {
+  let id = query_id();
+  let request = encode(id, name, rr_type, recursive)?;
+
+  let response = async {
+    let response = query_udp(server, &request, wait).await?;
+
+    // the truncation flag is set when the answer does not fit in a datagram
+    match response.get(2).is_some_and(|flags| flags & 0x02 != 0) {
+      true => query_tcp(server, &request, wait).await,
+      false => Ok(response),
+    }
+  }
+  .await
+  .with_context(|| format!("Failed to query {server} for `{name}`."))?;
+
+  decode(id, rr_type, &response).with_context(|| format!("Invalid answer from {server}."))
+}
+
+/// Asks a DNS-over-HTTPS endpoint to resolve the records of a name, as
+/// described in RFC 8484.
+pub async fn query_https(
+  http: &Client,
+  url: &str,
+  name: &str,
+  rr_type: RecordType,
+  wait: Duration,
+) -> Result<Vec<String>> {
+  // the ID is zero so that HTTP caches can share answers between queries
+  let request = encode(0, name, rr_type, true)?;
+
+  let response = async {
+    let response = http
+      .post(url)
+      .header("Content-Type", DNS_MESSAGE)
+      .header("Accept", DNS_MESSAGE)
+      .timeout(wait)
+      .body(request)
+      .send()
+      .await?
+      .error_for_status()?;
+
+    response.bytes().await
+  }
+  .await
+  .with_context(|| format!("Failed to query {url} for `{name}`."))?;
+
+  decode(0, rr_type, &response).with_context(|| format!("Invalid answer from {url}."))
+}
+
+async fn query_udp(server: SocketAddr, request: &[u8], wait: Duration) -> Result<Vec<u8>> {
+  let bind: SocketAddr = match server {
+    SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+    SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+  };
+
+  let socket = UdpSocket::bind(bind).await?;
+
+  socket.connect(server).await?;
+  socket.send(request).await?;
+
+  let mut buf = vec![0; 4096];
+  let len = timeout(wait, socket.recv(&mut buf))
+    .await
+    .context("Timed out.")??;
+
+  buf.truncate(len);
+
+  Ok(buf)
+}
+
+async fn query_tcp(server: SocketAddr, request: &[u8], wait: Duration) -> Result<Vec<u8>> {
+  let exchange = async {
+    let mut stream = TcpStream::connect(server).await?;
+
+    // messages over TCP are prefixed with their length
+    stream.write_u16(request.len() as u16).await?;
+    stream.write_all(request).await?;
+
+    let len = stream.read_u16().await?;
+    let mut buf = vec![0; len as usize];
+
+    stream.read_exact(&mut buf).await?;
+
+    anyhow::Ok(buf)
+  };
+
+  timeout(wait, exchange).await.context("Timed out.")?
+}
+
+/// Encodes a query for a single question.
+fn encode(id: u16, name: &str, rr_type: RecordType, recursive: bool) -> Result<Vec<u8>> {
+  let mut msg = Vec::with_capacity(512);
+
+  msg.extend(id.to_be_bytes());
+  // a standard query with one question, optionally asking for recursion
+  msg.extend([recursive as u8, 0, 0, 1, 0, 0, 0, 0, 0, 0]);
+
+  for label in name.trim_end_matches('.').split('.') {
+    ensure!(
+      !label.is_empty() && label.len() < 64,
+      "Invalid name `{name}`."
+    );
+
+    msg.push(label.len() as u8);
+    msg.extend(label.as_bytes());
+  }
+
+  msg.push(0);
+  msg.extend(rr_type.code().to_be_bytes());
+  // class IN
+  msg.extend(1u16.to_be_bytes());
+
+  Ok(msg)
+}
+
+/// Decodes the records of a type in the answer to a query.
+fn decode(id: u16, rr_type: RecordType, msg: &[u8]) -> Result<Vec<String>> {
+  let mut reader = Reader { msg, pos: 0 };
+
+  ensure!(reader.u16()? == id, "Answer is for a different query.");
+
+  let flags = reader.u16()?;
+  let questions = reader.u16()?;
+  let answers = reader.u16()?;
+
+  reader.pos += 4;
+
+  match flags & 0x0f {
+    0 => {}
+    3 => return Ok(Vec::new()),
+    code => bail!("Name server responded with error code {code}."),
+  }
+
+  for _ in 0..questions {
+    reader.skip_name()?;
+    reader.pos += 4;
+  }
+
+  let mut records = Vec::new();
+
+  for _ in 0..answers {
+    reader.skip_name()?;
+
+    let code = reader.u16()?;
+
+    reader.pos += 6;
+
+    let len = reader.u16()? as usize;
+    let data = reader.take(len)?;
+
+    if code != rr_type.code() {
+      continue;
+    }
+
+    records.push(match rr_type {
+      RecordType::A => {
+        let octets: [u8; 4] = data.try_into().context("Invalid A record.")?;
+
+        Ipv4Addr::from(octets).to_string()
+      }
+
+      RecordType::Aaaa => {
+        let octets: [u8; 16] = data.try_into().context("Invalid AAAA record.")?;
+
+        Ipv6Addr::from(octets).to_string()
+      }
+
+      RecordType::Txt => {
+        let mut text = Vec::new();
+        let mut rest = data;
+
+        // TXT data is a sequence of length-prefixed strings
+        while let Some((&len, tail)) = rest.split_first() {
+          ensure!(tail.len() >= len as usize, "Invalid TXT record.");
+
+          text.extend(&tail[..len as usize]);
+          rest = &tail[len as usize..];
+        }
+
+        String::from_utf8_lossy(&text).into_owned()
+      }
+    });
+  }
+
+  Ok(records)
+}
+
+/// Reads fields of a DNS message.
+struct Reader<'a> {
+  msg: &'a [u8],
+  pos: usize,
+}
+
+impl<'a> Reader<'a> {
+  fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+    let bytes = self
+      .msg
+      .get(self.pos..self.pos + len)
+      .context("Message is truncated.")?;
+
+    self.pos += len;
+
+    Ok(bytes)
+  }
+
+  fn u16(&mut self) -> Result<u16> {
+    let bytes = self.take(2)?;
+
+    Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+  }
+
+  /// Skips a possibly compressed name.
+  fn skip_name(&mut self) -> Result<()> {
+    loop {
+      let len = self.take(1)?[0];
+
+      match len {
+        0 => return Ok(()),
+        // a pointer to a name elsewhere ends the name
+        len if len & 0xc0 == 0xc0 => {
+          self.take(1)?;
+          return Ok(());
+        }
+        len => {
+          self.take(len as usize)?;
+        }
+      }
+    }
+  }
+}
+
+/// Returns an ID that differs between queries, to tell answers apart.
+fn query_id() -> u16 {
+  let nanos = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map_or(0, |d| d.subsec_nanos());
+
+  (nanos ^ std::process::id()) as u16
+}
@@ -0,0 +1,146 @@
+use std::io;
+
+use anyhow::Result;
+use aws_sdk_route53::types::RrType;
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use serde::Serialize;
+
+use ddns_route53::{
+  aws::{self, Accounts, AwsOptions},
+  lease,
+};
+
+use crate::Domain;
+
+/// Format of exported records.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+  Json,
+  Csv,
+}
+
+/// A record of a domain as it currently exists in Route 53.
+#[derive(Clone, Default, Serialize)]
+struct Row {
+  domain: String,
+  zone_id: String,
+  /// Name of the hosted zone, or empty if the domain chose it by ID.
+  zone_name: String,
+  /// Type of the record, or empty if the domain has no A or AAAA record.
+  r#type: String,
+  /// Values of the record, separated by spaces.
+  values: String,
+  ttl: Option<i64>,
+  /// `lease` if the record is registered with a lease, `unmanaged` if it is
+  /// not, or `missing` if it does not exist.
+  ownership: &'static str,
+  lease_expires: Option<DateTime<Utc>>,
+  /// Error that prevented looking up the record, if any.
+  error: String,
+}
+
+/// Prints the live A and AAAA records of domains along with the hosted zones
+/// they belong to and whether they are leased.
+pub async fn run(domains: &[Domain], format: Format, options: &AwsOptions) -> Result<()> {
+  let mut accounts = Accounts::new(options);
+  let mut rows = Vec::new();
+
+  for domain in domains {
+    let zone = accounts
+      .zone(&domain.identity, &domain.name, &domain.zone)
+      .await;
+
+    let zone = match zone {
+      Ok(zone) => zone,
+
+      Err(err) => {
+        rows.push(Row {
+          domain: domain.name.to_string(),
+          ownership: "missing",
+          error: format!("{err:#}"),
+          ..Row::default()
+        });
+
+        continue;
+      }
+    };
+
+    let base = Row {
+      domain: domain.name.to_string(),
+      zone_id: zone.id.clone(),
+      zone_name: zone.name.unwrap_or_default(),
+      ownership: "missing",
+      ..Row::default()
+    };
+
+    let lease_name = lease::record_name(&domain.name);
+
+    let route53 = accounts.client(&domain.identity).await;
+
+    let lookup = async {
+      let lease = aws::find_record(route53, &zone.id, &lease_name, RrType::Txt, None).await?;
+      let a = aws::find_record(route53, &zone.id, &domain.name, RrType::A, None).await?;
+      let aaaa = aws::find_record(route53, &zone.id, &domain.name, RrType::Aaaa, None).await?;
+
+      anyhow::Ok((lease, [a, aaaa]))
+    };
+
+    let (lease, sets) = match lookup.await {
+      Ok(found) => found,
+
+      Err(err) => {
+        rows.push(Row {
+          error: format!("{err:#}"),
+          ..base
+        });
+
+        continue;
+      }
+    };
+
+    let lease_expires = lease.as_ref().and_then(lease::expiry);
+    let ownership = match lease {
+      Some(_) => "lease",
+      None => "unmanaged",
+    };
+
+    let len = rows.len();
+
+    for set in sets.into_iter().flatten() {
+      let values: Vec<&str> = set.resource_records().iter().map(|r| r.value()).collect();
+
+      rows.push(Row {
+        r#type: set.r#type().as_str().into(),
+        values: values.join(" "),
+        ttl: set.ttl(),
+        ownership,
+        lease_expires,
+        ..base.clone()
+      });
+    }
+
+    if rows.len() == len {
+      rows.push(base);
+    }
+  }
+
+  match format {
+    Format::Json => {
+      serde_json::to_writer_pretty(io::stdout().lock(), &rows)?;
+      println!();
+    }
+
+    Format::Csv => {
+      let mut writer = csv::Writer::from_writer(io::stdout().lock());
+
+      for row in &rows {
+        writer.serialize(row)?;
+      }
+
+      writer.flush()?;
+    }
+  }
+
+  Ok(())
+}
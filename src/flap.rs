@@ -0,0 +1,309 @@
+use std::{collections::VecDeque, net::IpAddr};
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+/// Detects a public IP flapping between two values and holds updates down
+/// while it does.
+pub struct FlapDetector {
+  /// Recent IP changes, oldest first.
+  changes: VecDeque<(DateTime<Utc>, IpAddr)>,
+  /// Time until which updates are held down, if they are.
+  pub held_until: Option<DateTime<Utc>>,
+  hold_down: TimeDelta,
+  threshold: usize,
+  window: TimeDelta,
+}
+
+impl FlapDetector {
+  /// Creates a detector that holds updates down for `hold_down` once the IP
+  /// changes `threshold` times within `window` among at most two values.
+  ///
+  /// A threshold of zero disables flap detection.
+  pub fn new(threshold: usize, window: TimeDelta, hold_down: TimeDelta) -> Self {
+    Self {
+      changes: VecDeque::new(),
+      held_until: None,
+      hold_down,
+      threshold,
+      window,
+    }
+  }
+
+  /// Records a change of the public IP.
+  ///
+  /// Returns the addresses the IP is flapping between if it has just started
+  /// flapping. While it keeps flapping, the hold-down is extended.
+  pub fn observe(&mut self, ip: IpAddr, now: DateTime<Utc>) -> Option<Vec<IpAddr>> {
+    if self.threshold == 0 {
+      return None;
+    }
+
+    self.changes.push_back((now, ip));
+
+    while self
+      .changes
+      .front()
+      .is_some_and(|(time, _)| now - *time > self.window)
+    {
+      self.changes.pop_front();
+    }
+
+    if self.changes.len() < self.threshold {
+      return None;
+    }
+
+    let mut values: Vec<IpAddr> = self.changes.iter().map(|(_, ip)| *ip).collect();
+
+    values.sort();
+    values.dedup();
+
+    if values.len() > 2 {
+      return None;
+    }
+
+    let was_held = self.held_until.replace(now + self.hold_down).is_some();
+
+    (!was_held).then_some(values)
+  }
+
+  /// Returns `true` if updates are currently held down.
+  pub fn is_holding(&self, now: DateTime<Utc>) -> bool {
+    self.held_until.is_some_and(|until| now < until)
+  }
+
+  /// Ends an expired hold-down, returning `true` if there was one.
+  pub fn release(&mut self, now: DateTime<Utc>) -> bool {
+    if self.held_until.is_some_and(|until| now >= until) {
+      self.held_until = None;
+      self.changes.clear();
+      return true;
+    }
+
+    false
+  }
+}
+
+/// Requires a changed public IP to be detected on several checks in a row
+/// before it is trusted, so that a single bogus response is not written.
+pub struct Confirmations {
+  /// Number of checks in a row that must detect a changed IP.
+  required: u32,
+  /// Changed IPs that are not trusted yet, at most one of each family, with
+  /// the number of checks in a row that detected them.
+  candidates: Vec<(IpAddr, u32)>,
+}
+
+impl Confirmations {
+  /// Creates a threshold of `required` checks in a row. A threshold of one
+  /// trusts every IP right away.
+  pub const fn new(required: u32) -> Self {
+    Self {
+      required,
+      candidates: Vec::new(),
+    }
+  }
+
+  /// Records that a check detected an IP, where `known` is the last trusted
+  /// IP of its family.
+  ///
+  /// Returns the number of checks that still have to detect it before it is
+  /// trusted, which is zero once it is or if it has not changed.
+  pub fn observe(&mut self, known: Option<IpAddr>, ip: IpAddr) -> u32 {
+    let same_family = |candidate: &IpAddr| candidate.is_ipv4() == ip.is_ipv4();
+
+    // an IP that has not changed does not need confirming, and nothing is
+    // known to compare the first IP with
+    if known.is_none_or(|known| known == ip) {
+      self
+        .candidates
+        .retain(|(candidate, _)| !same_family(candidate));
+      return 0;
+    }
+
+    let count = match self.candidates.iter_mut().find(|(c, _)| same_family(c)) {
+      Some((candidate, count)) if *candidate == ip => {
+        *count += 1;
+        *count
+      }
+
+      Some(entry) => {
+        *entry = (ip, 1);
+        1
+      }
+
+      None => {
+        self.candidates.push((ip, 1));
+        1
+      }
+    };
+
+    let remaining = self.required.saturating_sub(count);
+
+    if remaining == 0 {
+      self.confirm(ip);
+    }
+
+    remaining
+  }
+
+  /// Trusts an IP that was confirmed another way, such as by another
+  /// provider.
+  pub fn confirm(&mut self, ip: IpAddr) {
+    self
+      .candidates
+      .retain(|(candidate, _)| candidate.is_ipv4() != ip.is_ipv4());
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn ip(ip: &str) -> IpAddr {
+    ip.parse().unwrap()
+  }
+
+  fn at(minutes: i64) -> DateTime<Utc> {
+    DateTime::UNIX_EPOCH + TimeDelta::minutes(minutes)
+  }
+
+  fn detector(threshold: usize) -> FlapDetector {
+    FlapDetector::new(threshold, TimeDelta::minutes(10), TimeDelta::minutes(30))
+  }
+
+  #[test]
+  fn detects_an_ip_flapping_between_two_values() {
+    let mut flaps = detector(4);
+    let (a, b) = (ip("203.0.113.1"), ip("203.0.113.2"));
+
+    assert_eq!(flaps.observe(a, at(0)), None);
+    assert_eq!(flaps.observe(b, at(1)), None);
+    assert_eq!(flaps.observe(a, at(2)), None);
+    assert_eq!(flaps.observe(b, at(3)), Some(vec![a, b]));
+    assert_eq!(flaps.held_until, Some(at(33)));
+
+    // flapping on extends the hold-down without reporting it again
+    assert_eq!(flaps.observe(a, at(4)), None);
+    assert_eq!(flaps.held_until, Some(at(34)));
+    assert!(flaps.is_holding(at(33)));
+  }
+
+  #[test]
+  fn ignores_an_ip_changing_between_more_than_two_values() {
+    let mut flaps = detector(3);
+
+    flaps.observe(ip("203.0.113.1"), at(0));
+    flaps.observe(ip("203.0.113.2"), at(1));
+
+    assert_eq!(flaps.observe(ip("203.0.113.3"), at(2)), None);
+    assert!(!flaps.is_holding(at(2)));
+  }
+
+  #[test]
+  fn forgets_changes_outside_the_window() {
+    let mut flaps = detector(3);
+    let (a, b) = (ip("203.0.113.1"), ip("203.0.113.2"));
+
+    flaps.observe(a, at(0));
+    flaps.observe(b, at(5));
+
+    assert_eq!(flaps.observe(a, at(11)), None);
+    assert_eq!(flaps.observe(b, at(12)), Some(vec![a, b]));
+  }
+
+  #[test]
+  fn a_threshold_of_zero_disables_detection() {
+    let mut flaps = detector(0);
+
+    for minute in 0..10 {
+      let value = if minute % 2 == 0 {
+        "203.0.113.1"
+      } else {
+        "203.0.113.2"
+      };
+
+      assert_eq!(flaps.observe(ip(value), at(minute)), None);
+    }
+
+    assert!(!flaps.is_holding(at(10)));
+  }
+
+  #[test]
+  fn releases_the_hold_down_once_it_expires() {
+    let mut flaps = detector(2);
+
+    flaps.observe(ip("203.0.113.1"), at(0));
+    flaps.observe(ip("203.0.113.2"), at(1));
+
+    assert!(!flaps.release(at(30)));
+    assert!(flaps.is_holding(at(30)));
+    assert!(flaps.release(at(31)));
+    assert!(!flaps.is_holding(at(31)));
+    assert!(!flaps.release(at(32)));
+
+    // the changes before the hold-down do not count towards the next one
+    assert_eq!(flaps.observe(ip("203.0.113.1"), at(32)), None);
+  }
+
+  #[test]
+  fn requires_a_changed_ip_several_times_in_a_row() {
+    let mut confirmations = Confirmations::new(3);
+    let known = Some(ip("203.0.113.1"));
+
+    assert_eq!(confirmations.observe(known, ip("203.0.113.2")), 2);
+    assert_eq!(confirmations.observe(known, ip("203.0.113.2")), 1);
+    assert_eq!(confirmations.observe(known, ip("203.0.113.2")), 0);
+
+    // a confirmed IP starts over if it is detected again
+    assert_eq!(confirmations.observe(known, ip("203.0.113.2")), 2);
+  }
+
+  #[test]
+  fn starts_over_when_a_different_ip_is_detected() {
+    let mut confirmations = Confirmations::new(2);
+    let known = Some(ip("203.0.113.1"));
+
+    assert_eq!(confirmations.observe(known, ip("203.0.113.2")), 1);
+    assert_eq!(confirmations.observe(known, ip("203.0.113.3")), 1);
+    assert_eq!(confirmations.observe(known, ip("203.0.113.3")), 0);
+  }
+
+  #[test]
+  fn trusts_unchanged_and_first_ips_right_away() {
+    let mut confirmations = Confirmations::new(2);
+    let known = Some(ip("203.0.113.1"));
+
+    assert_eq!(confirmations.observe(None, ip("203.0.113.1")), 0);
+    assert_eq!(confirmations.observe(known, ip("203.0.113.2")), 1);
+
+    // going back to the known IP drops the candidate
+    assert_eq!(confirmations.observe(known, ip("203.0.113.1")), 0);
+    assert_eq!(confirmations.observe(known, ip("203.0.113.2")), 1);
+  }
+
+  #[test]
+  fn tracks_each_family_separately() {
+    let mut confirmations = Confirmations::new(2);
+    let known = Some(ip("203.0.113.1"));
+    let known_v6 = Some(ip("2001:db8::1"));
+
+    assert_eq!(confirmations.observe(known, ip("203.0.113.2")), 1);
+    assert_eq!(confirmations.observe(known_v6, ip("2001:db8::2")), 1);
+    assert_eq!(confirmations.observe(known, ip("203.0.113.2")), 0);
+    assert_eq!(confirmations.observe(known_v6, ip("2001:db8::2")), 0);
+  }
+
+  #[test]
+  fn confirms_an_ip_another_way() {
+    let mut confirmations = Confirmations::new(2);
+    let known = Some(ip("203.0.113.1"));
+    let known_v6 = Some(ip("2001:db8::1"));
+
+    confirmations.observe(known, ip("203.0.113.2"));
+    confirmations.observe(known_v6, ip("2001:db8::2"));
+    confirmations.confirm(ip("203.0.113.2"));
+
+    assert_eq!(confirmations.observe(known, ip("203.0.113.2")), 1);
+    assert_eq!(confirmations.observe(known_v6, ip("2001:db8::2")), 0);
+  }
+}
@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use tokio::{io::AsyncWriteExt, net::TcpStream, time::timeout};
+use tracing::info_span;
+
+use crate::{metrics::Snapshot, trace::traced};
+
+/// How long sending metrics to Graphite may take.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Command line options for sending metrics to Graphite.
+#[derive(clap::Args)]
+#[command(next_help_heading = "Graphite")]
+pub struct GraphiteOptions {
+  /// HOST:PORT of a Graphite server, such as Carbon on port 2003, to send the
+  /// metrics of each update pass to over the plaintext protocol.
+  #[arg(long, value_name = "HOST:PORT")]
+  graphite_addr: Option<String>,
+
+  /// Prefix of the paths of the metrics sent to Graphite.
+  #[arg(
+    long,
+    value_name = "PREFIX",
+    default_value = "ddns_route53",
+    requires = "graphite_addr"
+  )]
+  graphite_prefix: String,
+}
+
+/// Client for a Graphite server.
+pub struct Graphite {
+  addr: String,
+  prefix: String,
+}
+
+impl Graphite {
+  /// Creates a client for the Graphite server given in the options, if any.
+  pub fn new(options: GraphiteOptions) -> Option<Self> {
+    Some(Self {
+      addr: options.graphite_addr?,
+      prefix: options.graphite_prefix.trim_end_matches('.').into(),
+    })
+  }
+
+  /// Sends the metrics of a pass.
+  pub async fn send(&self, snapshot: &Snapshot) -> Result<()> {
+    let lines = snapshot.graphite(&self.prefix);
+
+    let exchange = async {
+      let mut stream = TcpStream::connect(&self.addr).await?;
+
+      stream.write_all(lines.as_bytes()).await?;
+      stream.shutdown().await?;
+
+      anyhow::Ok(())
+    };
+
+    traced(info_span!("graphite_send", addr = self.addr), async {
+      timeout(TIMEOUT, exchange).await.context("Timed out.")?
+    })
+    .await
+    .with_context(|| format!("Failed to send metrics to Graphite at {}.", self.addr))
+  }
+}
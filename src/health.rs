@@ -0,0 +1,146 @@
+use std::{
+  net::{IpAddr, SocketAddr},
+  sync::{Arc, Mutex},
+  time::Duration,
+};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::server::{self, Response};
+
+/// Command line options for the health and status endpoints.
+#[derive(clap::Args)]
+#[command(next_help_heading = "Health")]
+pub struct HealthOptions {
+  /// Address to serve health checks on, such as `0.0.0.0:8080`, for liveness
+  /// probes of Docker or Kubernetes.
+  ///
+  /// `/healthz` responds with status 200 while update passes succeed and 503
+  /// otherwise, and `/status` responds with the public IP and the records of
+  /// each domain as JSON.
+  #[arg(long, value_name = "ADDR")]
+  health_addr: Option<SocketAddr>,
+
+  /// Number of update intervals since the last successful pass after which
+  /// `/healthz` reports the daemon as unhealthy.
+  #[arg(
+    long,
+    value_name = "N",
+    default_value_t = 3,
+    value_parser = clap::value_parser!(u32).range(1..),
+    requires = "health_addr"
+  )]
+  health_intervals: u32,
+}
+
+/// Status of the daemon as of its latest update pass.
+#[derive(Serialize)]
+pub struct Status {
+  /// Time the pass completed.
+  pub time: DateTime<Utc>,
+  /// Whether the public IP was determined and every update succeeded in the
+  /// pass.
+  pub healthy: bool,
+  /// Time of the most recent pass that was healthy.
+  pub last_success: Option<DateTime<Utc>>,
+  pub public_ip: Option<IpAddr>,
+  pub public_ipv6: Option<IpAddr>,
+  pub domains: Vec<DomainStatus>,
+}
+
+/// Status of the records of a domain.
+#[derive(Serialize)]
+pub struct DomainStatus {
+  pub name: String,
+  pub zone_id: Option<String>,
+  /// IPs the records were last written with, an A or AAAA record each.
+  pub records: Vec<IpAddr>,
+  pub ttl: i64,
+  /// Whether a failed change of a record is waiting to be retried.
+  pub pending: bool,
+  pub paused: bool,
+  /// Time of the most recent successful record update.
+  pub last_change: Option<DateTime<Utc>>,
+  /// Message of the most recent failure, if the domain has failed since its
+  /// last successful update.
+  pub last_error: Option<String>,
+}
+
+/// Serves the health and status of the daemon as of its latest pass.
+pub struct Health {
+  latest: Arc<Mutex<Option<Status>>>,
+}
+
+impl Health {
+  /// Serves the endpoints on the address given in the options, if any.
+  pub async fn listen(options: HealthOptions, interval: Duration) -> Result<Option<Self>> {
+    let Some(addr) = options.health_addr else {
+      return Ok(None);
+    };
+
+    let max_age = TimeDelta::from_std(interval * options.health_intervals)
+      .context("Invalid health check period.")?;
+
+    let latest = Arc::new(Mutex::new(None::<Status>));
+    let status = latest.clone();
+
+    server::listen(addr, "health checks", move |method, path| {
+      let status = status.lock().unwrap();
+
+      match (method, path) {
+        ("GET", "/healthz") => {
+          let stale = |time: DateTime<Utc>| Utc::now() - time > max_age;
+
+          match status.as_ref().and_then(|s| s.last_success) {
+            Some(time) if !stale(time) => text("200 OK", "OK"),
+            Some(_) => text(
+              "503 Service Unavailable",
+              "No update pass has succeeded recently.",
+            ),
+            None => text(
+              "503 Service Unavailable",
+              "No update pass has succeeded yet.",
+            ),
+          }
+        }
+
+        ("GET", "/status") => match &*status {
+          Some(status) => Response::json("200 OK", json!(status)),
+          None => Response::json(
+            "503 Service Unavailable",
+            json!({ "error": "No update pass has completed yet." }),
+          ),
+        },
+
+        _ => text("404 Not Found", "Not found."),
+      }
+    })
+    .await?;
+
+    Ok(Some(Self { latest }))
+  }
+
+  /// Replaces the status with that of the pass that just completed.
+  pub fn publish(&self, mut status: Status) {
+    let mut latest = self.latest.lock().unwrap();
+
+    status.last_success = match status.healthy {
+      true => Some(status.time),
+      false => latest.as_ref().and_then(|s| s.last_success),
+    };
+
+    *latest = Some(status);
+  }
+}
+
+/// Creates a plain text response.
+fn text(status: &'static str, body: &str) -> Response {
+  Response {
+    status,
+    content_type: "text/plain",
+    body: format!("{body}\n"),
+  }
+}
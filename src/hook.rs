@@ -0,0 +1,71 @@
+use tokio::process::Command;
+use tracing::{error, info};
+
+/// A command that runs when updates or IP detection keep failing, so that the
+/// user can attempt remediation such as restarting a modem.
+pub struct FailureHook {
+  /// Shell command to run.
+  pub command: String,
+  /// Number of consecutive failures after which the command runs.
+  pub threshold: u64,
+}
+
+/// A failure that the hook is told about.
+pub struct Failure<'a> {
+  /// What failed: `update` for a domain or `ip` for IP detection.
+  pub kind: &'static str,
+  /// Domain that failed to update, if any.
+  pub domain: Option<&'a str>,
+  /// Number of consecutive failures so far.
+  pub count: u64,
+  /// Error of the most recent failure.
+  pub error: &'a anyhow::Error,
+}
+
+impl FailureHook {
+  /// Runs the command in the background if a failure has just reached the
+  /// threshold of consecutive failures.
+  ///
+  /// The command runs once per streak of failures. Details of the failure are
+  /// passed in `DDNS_FAILURE`, `DDNS_DOMAIN`, `DDNS_FAILURES`, and
+  /// `DDNS_ERROR` environment variables.
+  pub fn observe(&self, failure: Failure) {
+    if failure.count != self.threshold {
+      return;
+    }
+
+    let mut command = shell(&self.command);
+
+    command
+      .env("DDNS_FAILURE", failure.kind)
+      .env("DDNS_DOMAIN", failure.domain.unwrap_or_default())
+      .env("DDNS_FAILURES", failure.count.to_string())
+      .env("DDNS_ERROR", format!("{:#}", failure.error));
+
+    info!(
+      "Running failure hook after {} consecutive failures.",
+      failure.count
+    );
+
+    tokio::spawn(async move {
+      match command.status().await {
+        Ok(status) if status.success() => {}
+        Ok(status) => error!("Failure hook exited with {status}."),
+        Err(err) => error!("Failed to run failure hook. {err}"),
+      }
+    });
+  }
+}
+
+/// Returns a command that runs a command line in the platform's shell.
+fn shell(command_line: &str) -> Command {
+  #[cfg(windows)]
+  let (shell, flag) = ("cmd", "/C");
+  #[cfg(not(windows))]
+  let (shell, flag) = ("sh", "-c");
+
+  let mut command = Command::new(shell);
+
+  command.arg(flag).arg(command_line);
+  command
+}
@@ -0,0 +1,32 @@
+use anyhow::{bail, Context, Result};
+
+/// Returns the host name of this machine, without any domain.
+pub fn name() -> Result<String> {
+  let name = read().context("Failed to determine the host name.")?;
+  let name = name.trim().split('.').next().unwrap_or_default();
+
+  if name.is_empty() {
+    bail!("The host name is empty.");
+  }
+
+  Ok(name.to_ascii_lowercase())
+}
+
+/// Reads the host name from the kernel.
+#[cfg(target_os = "linux")]
+fn read() -> Result<String> {
+  Ok(std::fs::read_to_string("/proc/sys/kernel/hostname")?)
+}
+
+/// Reads the host name with the `hostname` command, which every other
+/// supported system has.
+#[cfg(not(target_os = "linux"))]
+fn read() -> Result<String> {
+  let output = std::process::Command::new("hostname").output()?;
+
+  if !output.status.success() {
+    bail!("`hostname` exited with {}.", output.status);
+  }
+
+  Ok(String::from_utf8(output.stdout)?)
+}
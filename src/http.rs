@@ -0,0 +1,202 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use aws_smithy_runtime_api::{
+  client::{
+    http::{
+      HttpClient, HttpConnector, HttpConnectorFuture, HttpConnectorSettings, SharedHttpClient,
+      SharedHttpConnector,
+    },
+    orchestrator::{HttpRequest, HttpResponse},
+    result::ConnectorError,
+    runtime_components::RuntimeComponents,
+  },
+  http::StatusCode,
+};
+use aws_smithy_types::body::SdkBody;
+use reqwest::{Client, ClientBuilder, NoProxy, Proxy, Url};
+
+use crate::ip::Family;
+
+/// User agent sent with every request.
+const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// Options for the requests the daemon sends.
+#[derive(clap::Args)]
+pub struct HttpOptions {
+  /// URL of a proxy to send HTTP requests through, such as
+  /// `http://proxy.example.com:3128`. Without it, the proxies in the
+  /// `HTTPS_PROXY`, `HTTP_PROXY`, and `NO_PROXY` environment variables are
+  /// used.
+  #[arg(long, global = true, value_name = "URL")]
+  pub http_proxy: Option<Url>,
+
+  /// How long an HTTP request other than one to an IP provider, such as a
+  /// webhook, may take before it fails.
+  #[arg(
+    long,
+    global = true,
+    default_value = "30s",
+    value_name = "DURATION",
+    value_parser = humantime::parse_duration
+  )]
+  pub http_timeout: Duration,
+}
+
+impl Default for HttpOptions {
+  fn default() -> Self {
+    Self {
+      http_proxy: None,
+      http_timeout: Duration::from_secs(30),
+    }
+  }
+}
+
+/// HTTP clients shared by everything the daemon requests, so that connections
+/// are pooled and kept alive across update passes.
+#[derive(Clone)]
+pub struct Http {
+  /// Client for requests over any address family.
+  pub any: Client,
+  /// Client for requests that never go through a proxy, such as those to the
+  /// EC2 instance metadata service.
+  pub direct: Client,
+  v4: Client,
+  v6: Client,
+}
+
+impl Http {
+  pub fn new() -> Result<Self> {
+    Self::with_options(&HttpOptions::default())
+  }
+
+  pub fn with_options(options: &HttpOptions) -> Result<Self> {
+    Ok(Self {
+      any: build(options, None)?,
+      direct: build_direct(options)?,
+      v4: build(options, Some(Family::V4))?,
+      v6: build(options, Some(Family::V6))?,
+    })
+  }
+
+  /// Returns the client whose connections are restricted to an address
+  /// family.
+  pub const fn family(&self, family: Family) -> &Client {
+    match family {
+      Family::V4 => &self.v4,
+      Family::V6 => &self.v6,
+    }
+  }
+
+  /// Returns an HTTP client for the AWS SDK that sends its requests like any
+  /// other, so that they go through the same proxy.
+  pub fn sdk_client(&self) -> SharedHttpClient {
+    SharedHttpClient::new(SdkClient(self.any.clone()))
+  }
+}
+
+/// Returns `true` if a proxy is set in the environment, for HTTPS requests or
+/// all of them.
+pub fn proxy_in_env() -> bool {
+  ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]
+    .iter()
+    .any(|name| std::env::var_os(name).is_some_and(|value| !value.is_empty()))
+}
+
+/// HTTP client of the AWS SDK backed by a `reqwest` client, since the SDK's
+/// own client does not support proxies.
+#[derive(Clone, Debug)]
+struct SdkClient(Client);
+
+impl HttpClient for SdkClient {
+  fn http_connector(
+    &self,
+    _: &HttpConnectorSettings,
+    _: &RuntimeComponents,
+  ) -> SharedHttpConnector {
+    SharedHttpConnector::new(self.clone())
+  }
+}
+
+impl HttpConnector for SdkClient {
+  fn call(&self, request: HttpRequest) -> HttpConnectorFuture {
+    let client = self.0.clone();
+
+    HttpConnectorFuture::new(async move {
+      // requests to Route 53 have small bodies that are already in memory
+      let body = request
+        .body()
+        .bytes()
+        .ok_or_else(|| ConnectorError::other("Streaming bodies are not supported.".into(), None))?
+        .to_vec();
+
+      let method = request
+        .method()
+        .parse()
+        .map_err(|err| ConnectorError::other(Box::new(err), None))?;
+      let mut builder = client.request(method, request.uri()).body(body);
+
+      for (name, value) in request.headers() {
+        builder = builder.header(name, value);
+      }
+
+      let response = builder.send().await.map_err(|err| match err.is_timeout() {
+        true => ConnectorError::timeout(Box::new(err)),
+        false => ConnectorError::io(Box::new(err)),
+      })?;
+
+      let status = StatusCode::try_from(response.status().as_u16())
+        .map_err(|err| ConnectorError::other(Box::new(err), None))?;
+
+      let headers = response.headers().clone();
+      let bytes = response
+        .bytes()
+        .await
+        .map_err(|err| ConnectorError::io(Box::new(err)))?;
+      let mut sdk_response = HttpResponse::new(status, SdkBody::from(bytes.to_vec()));
+
+      for (name, value) in &headers {
+        if let Ok(value) = value.to_str() {
+          sdk_response
+            .headers_mut()
+            .append(name.as_str().to_string(), value.to_string());
+        }
+      }
+
+      Ok(sdk_response)
+    })
+  }
+}
+
+/// Builds a client, optionally restricted to an address family.
+///
+/// Through a proxy, the restriction applies to the connection to the proxy,
+/// which then connects to the server over whichever family it likes.
+fn build(options: &HttpOptions, family: Option<Family>) -> Result<Client> {
+  let mut builder = builder(options).local_address(family.map(Family::unspecified));
+
+  if let Some(url) = &options.http_proxy {
+    let proxy = Proxy::all(url.clone())
+      .with_context(|| format!("Invalid HTTP proxy `{url}`."))?
+      .no_proxy(NoProxy::from_env());
+
+    builder = builder.proxy(proxy);
+  }
+
+  Ok(builder.build()?)
+}
+
+/// Builds a client that ignores the proxy of the options and the environment.
+fn build_direct(options: &HttpOptions) -> Result<Client> {
+  Ok(builder(options).no_proxy().build()?)
+}
+
+/// Returns a builder with the settings every client shares.
+fn builder(options: &HttpOptions) -> ClientBuilder {
+  Client::builder()
+    .user_agent(USER_AGENT)
+    .connect_timeout(Duration::from_secs(10).min(options.http_timeout))
+    .timeout(options.http_timeout)
+    .pool_idle_timeout(Duration::from_secs(90))
+    .tcp_keepalive(Duration::from_secs(60))
+}
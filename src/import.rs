@@ -0,0 +1,410 @@
+use std::{collections::BTreeMap, fs, net::IpAddr, path::Path};
+
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use tracing::{info, warn};
+
+use ddns_route53::{
+  inventory::{self, HostSource},
+  name::DomainName,
+};
+
+/// Format of a file to import domain names from.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+  /// A BIND zone file.
+  Zone,
+  /// A CSV file with a `name` column and optional `type` and `value` columns.
+  Csv,
+}
+
+/// Which source imported domain names are given in the inventory.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ImportSource {
+  /// The public IP of the machine running the daemon.
+  Public,
+  /// The address of the imported A record.
+  Static,
+}
+
+/// An address record found in an imported file.
+struct Record {
+  name: String,
+  ip: Option<IpAddr>,
+}
+
+/// Imports the names of A and AAAA records from a file into the inventory,
+/// keeping the entries it already has.
+///
+/// Without an inventory file, the resulting inventory is printed instead.
+pub fn run(
+  file: &Path,
+  format: Option<Format>,
+  origin: Option<&str>,
+  source: ImportSource,
+  inventory: Option<&Path>,
+) -> Result<()> {
+  let text = fs::read_to_string(file).with_context(|| format!("Failed to read {file:?}."))?;
+
+  let format = format.unwrap_or(match file.extension() {
+    Some(ext) if ext.eq_ignore_ascii_case("csv") => Format::Csv,
+    _ => Format::Zone,
+  });
+
+  let records = match format {
+    Format::Zone => parse_zone(&text, origin),
+    Format::Csv => parse_csv(&text),
+  }
+  .with_context(|| format!("Failed to parse {file:?}."))?;
+
+  let mut entries = match inventory {
+    Some(path) if path.exists() => inventory::load(path)?,
+    _ => BTreeMap::new(),
+  };
+
+  let mut added = 0;
+
+  for record in records {
+    let name = match record.name.parse::<DomainName>() {
+      Ok(name) => name,
+
+      Err(err) => {
+        warn!(
+          domain = record.name,
+          "Skipping invalid domain name. {err:#}"
+        );
+        continue;
+      }
+    };
+
+    // entries may be followed by a zone choice
+    let listed = entries.keys().any(|key| {
+      let entry = key.split('=').next().unwrap_or_default();
+      entry.parse::<DomainName>().is_ok_and(|entry| entry == name)
+    });
+
+    if listed {
+      continue;
+    }
+
+    let source = match (source, record.ip) {
+      (ImportSource::Public, _) => HostSource::Public,
+      (ImportSource::Static, Some(ip @ IpAddr::V4(_))) => HostSource::Ip(ip),
+
+      (ImportSource::Static, _) => {
+        warn!(
+          domain = name.as_str(),
+          "Skipping domain without an A record."
+        );
+        continue;
+      }
+    };
+
+    entries.insert(name.to_string(), source);
+    added += 1;
+  }
+
+  match inventory {
+    Some(path) => {
+      inventory::save(path, &entries)?;
+      info!("Added {added} domains to {path:?}.");
+    }
+
+    None => println!("{}", serde_json::to_string_pretty(&entries)?),
+  }
+
+  Ok(())
+}
+
+/// Finds the A and AAAA records in a BIND zone file.
+///
+/// A records are listed before AAAA records of the same name so that their
+/// addresses are preferred.
+fn parse_zone(text: &str, origin: Option<&str>) -> Result<Vec<Record>> {
+  let mut origin = origin.map(|o| o.trim_end_matches('.').to_lowercase());
+  let mut owner = None::<String>;
+  let mut records = Vec::new();
+  let mut aaaa = Vec::new();
+
+  for (number, line) in logical_lines(text) {
+    let starts_blank = line.starts_with([' ', '\t']);
+    let mut tokens = line.split_whitespace();
+
+    let Some(first) = tokens.clone().next() else {
+      continue;
+    };
+
+    if first.eq_ignore_ascii_case("$ORIGIN") {
+      origin = tokens
+        .nth(1)
+        .map(|o| o.trim_end_matches('.').to_lowercase());
+      continue;
+    }
+
+    if first.starts_with('$') {
+      continue;
+    }
+
+    if !starts_blank {
+      let name = tokens.next().unwrap_or_default();
+
+      owner = Some(absolute(name, origin.as_deref()).with_context(|| format!("Line {number}."))?);
+    }
+
+    // skip the optional TTL and class before the record type
+    let mut tokens =
+      tokens.skip_while(|t| t.starts_with(|c: char| c.is_ascii_digit()) || is_class(t));
+
+    let (Some(rr_type), Some(value)) = (tokens.next(), tokens.next()) else {
+      continue;
+    };
+
+    let Some(name) = &owner else {
+      bail!("Line {number}: Record has no owner name.");
+    };
+
+    let list = match rr_type.to_ascii_uppercase().as_str() {
+      "A" => &mut records,
+      "AAAA" => &mut aaaa,
+      _ => continue,
+    };
+
+    list.push(Record {
+      name: name.clone(),
+      ip: Some(
+        value
+          .parse()
+          .with_context(|| format!("Line {number}: Invalid address {value:?}."))?,
+      ),
+    });
+  }
+
+  records.append(&mut aaaa);
+
+  Ok(records)
+}
+
+/// Splits a zone file into numbered lines without comments, joining lines
+/// enclosed in parentheses.
+///
+/// Semicolons and parentheses within quoted strings, or escaped with a
+/// backslash, are part of the record data.
+fn logical_lines(text: &str) -> Vec<(usize, String)> {
+  let mut lines = Vec::new();
+  let mut pending: Option<(usize, String)> = None;
+  let mut depth = 0usize;
+
+  for (i, line) in text.lines().enumerate() {
+    let (number, mut joined) = pending.take().unwrap_or((i + 1, String::new()));
+    let mut quoted = false;
+    let mut escaped = false;
+
+    for c in line.chars() {
+      match c {
+        _ if escaped => escaped = false,
+        '\\' => escaped = true,
+        '"' => quoted = !quoted,
+        _ if quoted => {}
+        ';' => break,
+
+        '(' | ')' => {
+          depth = match c {
+            '(' => depth + 1,
+            _ => depth.saturating_sub(1),
+          };
+
+          joined.push(' ');
+          continue;
+        }
+
+        _ => {}
+      }
+
+      joined.push(c);
+    }
+
+    joined.push(' ');
+
+    match depth {
+      0 => lines.push((number, joined)),
+      _ => pending = Some((number, joined)),
+    }
+  }
+
+  lines.extend(pending);
+  lines
+}
+
+/// Returns `true` if a zone file token is a DNS class.
+fn is_class(token: &str) -> bool {
+  ["IN", "CH", "HS", "CS"]
+    .iter()
+    .any(|class| token.eq_ignore_ascii_case(class))
+}
+
+/// Converts an owner name from a zone file into an absolute domain name.
+fn absolute(name: &str, origin: Option<&str>) -> Result<String> {
+  let name = name.to_lowercase();
+
+  if let Some(name) = name.strip_suffix('.') {
+    return Ok(name.into());
+  }
+
+  let Some(origin) = origin else {
+    bail!("Relative name `{name}` without an origin. Pass `--origin`.");
+  };
+
+  match name.as_str() {
+    "@" => Ok(origin.into()),
+    _ => Ok(format!("{name}.{origin}")),
+  }
+}
+
+/// Finds the address records in a CSV file with a `name` column and optional
+/// `type` and `value` columns.
+fn parse_csv(text: &str) -> Result<Vec<Record>> {
+  let mut reader = csv::Reader::from_reader(text.as_bytes());
+
+  let headers = reader.headers()?.clone();
+  let column = |name: &str| {
+    headers
+      .iter()
+      .position(|h| h.trim().eq_ignore_ascii_case(name))
+  };
+
+  let Some(name_column) = column("name") else {
+    bail!("Missing `name` column.");
+  };
+
+  let (type_column, value_column) = (column("type"), column("value"));
+  let mut records = Vec::new();
+
+  for row in reader.records() {
+    let row = row?;
+    let field = |column: Option<usize>| column.and_then(|c| row.get(c)).map(str::trim);
+
+    let rr_type = field(type_column).unwrap_or("A").to_ascii_uppercase();
+
+    if rr_type != "A" && rr_type != "AAAA" {
+      continue;
+    }
+
+    let ip = match field(value_column) {
+      Some(value) if !value.is_empty() => Some(
+        value
+          .parse()
+          .with_context(|| format!("Invalid address {value:?}."))?,
+      ),
+      _ => None,
+    };
+
+    let name = field(Some(name_column)).unwrap_or_default();
+
+    records.push(Record {
+      name: name.trim_end_matches('.').to_lowercase(),
+      ip,
+    });
+  }
+
+  // prefer the addresses of A records over those of AAAA records
+  records.sort_by_key(|r| !matches!(r.ip, None | Some(IpAddr::V4(_))));
+
+  Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse(text: &str, origin: Option<&str>) -> Vec<(String, String)> {
+    parse_zone(text, origin)
+      .unwrap()
+      .into_iter()
+      .map(|r| (r.name, r.ip.unwrap().to_string()))
+      .collect()
+  }
+
+  fn pair(name: &str, ip: &str) -> (String, String) {
+    (name.into(), ip.into())
+  }
+
+  #[test]
+  fn resolves_owner_names() {
+    let zone = "\
+$ORIGIN example.com.
+$TTL 3600
+@       IN 300 A    192.0.2.1
+www     300 IN A    192.0.2.2
+        IN     AAAA 2001:db8::2
+Mail.Example.com. A 192.0.2.3
+$ORIGIN lab.example.com.
+nas     A 192.0.2.4
+";
+
+    assert_eq!(
+      parse(zone, None),
+      [
+        pair("example.com", "192.0.2.1"),
+        pair("www.example.com", "192.0.2.2"),
+        pair("mail.example.com", "192.0.2.3"),
+        pair("nas.lab.example.com", "192.0.2.4"),
+        pair("www.example.com", "2001:db8::2"),
+      ]
+    );
+  }
+
+  #[test]
+  fn joins_multi_line_records() {
+    let zone = "\
+@ IN SOA ns1.example.com. admin.example.com. (
+        2024010101 ; serial
+        3600       ; refresh (1 hour)
+        )
+host A 192.0.2.1
+";
+
+    assert_eq!(
+      parse(zone, Some("Example.com.")),
+      [pair("host.example.com", "192.0.2.1")]
+    );
+
+    let lines = logical_lines(zone);
+
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1].0, 5);
+  }
+
+  #[test]
+  fn keeps_quoted_semicolons_and_parentheses() {
+    let zone = "\
+txt IN TXT \"v=spf1 (-all; not a comment\" ; a comment (
+esc IN TXT v=1\\(\\;
+host A 192.0.2.1
+";
+
+    assert_eq!(
+      parse(zone, Some("example.com")),
+      [pair("host.example.com", "192.0.2.1")]
+    );
+
+    let lines = logical_lines(zone);
+
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].1.contains("\"v=spf1 (-all; not a comment\""));
+  }
+
+  #[test]
+  fn requires_an_origin_for_relative_names() {
+    let Err(err) = parse_zone("host A 192.0.2.1\n", None) else {
+      panic!("Relative name was accepted without an origin.");
+    };
+
+    assert!(format!("{err:#}").contains("--origin"));
+    assert!(parse_zone("@ A 192.0.2.1\n", None).is_err());
+
+    assert_eq!(
+      parse("host.example.com. A 192.0.2.1\n", None),
+      [pair("host.example.com", "192.0.2.1")]
+    );
+  }
+}
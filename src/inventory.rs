@@ -0,0 +1,104 @@
+use std::{
+  collections::BTreeMap,
+  fs,
+  net::{IpAddr, Ipv6Addr},
+  path::Path,
+};
+
+use anyhow::{Context, Result};
+use chrono::TimeDelta;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{ip::Provider, private::PrivateSource};
+
+/// Where the IP of a host managed by this instance comes from.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HostSource {
+  /// The public IP detected by this instance.
+  Public,
+  /// A fixed address.
+  Ip(IpAddr),
+  /// An HTTP endpoint that responds with the host's IP.
+  Url(String),
+  /// A record registered by another instance with a lease.
+  Lease,
+  /// The interface identifier of a host in the IPv6 prefix delegated to this
+  /// network, such as `::1:2:3:4`.
+  Suffix(Ipv6Addr),
+}
+
+/// Where the IP a domain's record should have comes from.
+pub enum Source {
+  /// The public IP detected by this instance.
+  Public,
+  /// A fixed address.
+  Static(IpAddr),
+  /// The last IP fetched from another host's IP endpoint, if any.
+  Remote {
+    provider: Provider,
+    ip: Option<IpAddr>,
+  },
+  /// Another instance that registers the record with a lease, which is
+  /// deleted when its lease lapses.
+  Lease,
+  /// The last private address of this machine that was detected, if any.
+  Private {
+    source: PrivateSource,
+    ip: Option<IpAddr>,
+  },
+  /// The current delegated IPv6 prefix followed by the suffix of a host, once
+  /// the prefix is known.
+  Prefix {
+    suffix: Ipv6Addr,
+    ip: Option<IpAddr>,
+  },
+}
+
+/// What to do about inventory hosts that have stopped reporting their IP.
+pub struct Reaper {
+  /// How long a host may go without reporting before it is reaped.
+  pub after: TimeDelta,
+  pub action: ReapAction,
+}
+
+/// How to reap a host that has stopped reporting its IP.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReapAction {
+  /// Only send a notification.
+  Alert,
+  /// Delete the host's record and send a notification.
+  Delete,
+}
+
+/// Loads an inventory file mapping domain names, optionally followed by zone
+/// choices, to the sources of their IPs.
+pub fn load(path: &Path) -> Result<BTreeMap<String, HostSource>> {
+  let json =
+    fs::read_to_string(path).with_context(|| format!("Failed to read inventory {path:?}."))?;
+
+  serde_json::from_str(&json).with_context(|| format!("Failed to parse inventory {path:?}."))
+}
+
+/// Saves an inventory file.
+pub fn save(path: &Path, inventory: &BTreeMap<String, HostSource>) -> Result<()> {
+  let json = serde_json::to_string_pretty(inventory)?;
+
+  fs::write(path, json + "\n").with_context(|| format!("Failed to write inventory {path:?}."))
+}
+
+impl From<HostSource> for Source {
+  fn from(source: HostSource) -> Self {
+    match source {
+      HostSource::Public => Self::Public,
+      HostSource::Ip(ip) => Self::Static(ip),
+      HostSource::Url(url) => Self::Remote {
+        provider: Provider::new(url),
+        ip: None,
+      },
+      HostSource::Lease => Self::Lease,
+      HostSource::Suffix(suffix) => Self::Prefix { suffix, ip: None },
+    }
+  }
+}
@@ -0,0 +1,382 @@
+use std::{
+  fmt,
+  net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+  time::Duration,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::header::CONTENT_TYPE;
+
+use crate::{
+  dns::{self, RecordType},
+  http::Http,
+};
+
+/// Maximum accepted size of an IP echo response body, in bytes.
+const MAX_RESPONSE_LEN: usize = 64;
+
+/// Built-in services that echo back the public IPv4 of the client, in the
+/// order they are tried.
+pub const PROVIDERS_V4: &[&str] = &[
+  "https://api.ipify.org",
+  "https://ipv4.icanhazip.com",
+  "https://checkip.amazonaws.com",
+];
+
+/// Built-in services that echo back the public IPv6 of the client, in the
+/// order they are tried.
+pub const PROVIDERS_V6: &[&str] = &["https://api6.ipify.org", "https://ipv6.icanhazip.com"];
+
+/// Built-in name servers that answer a query for a special name with the
+/// public IP of the client, in the order they are tried.
+///
+/// Each is listed once per family: OpenDNS answers `myip.opendns.com` with an
+/// A or AAAA record, and Google answers `o-o.myaddr.l.google.com` with a TXT
+/// record, for the family the query arrived over.
+const DNS_PROVIDERS: &[(IpAddr, &str, RecordType)] = &[
+  (ipv4(208, 67, 222, 222), "myip.opendns.com", RecordType::A),
+  (
+    ipv4(216, 239, 32, 10),
+    "o-o.myaddr.l.google.com",
+    RecordType::Txt,
+  ),
+  (
+    ipv6([0x2620, 0x119, 0x35, 0, 0, 0, 0, 0x35]),
+    "myip.opendns.com",
+    RecordType::Aaaa,
+  ),
+  (
+    ipv6([0x2001, 0x4860, 0x4802, 0x32, 0, 0, 0, 0xa]),
+    "o-o.myaddr.l.google.com",
+    RecordType::Txt,
+  ),
+];
+
+/// How the public IP of this machine is discovered.
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+pub enum Source {
+  /// Ask HTTP services that respond with the IP.
+  #[default]
+  Http,
+  /// Ask name servers that answer special names with the IP.
+  Dns,
+}
+
+/// An IP address family.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Family {
+  V4,
+  V6,
+}
+
+/// A service that echoes back the public IP of the client.
+pub struct Provider {
+  /// URL of the service, or a `dns://SERVER/NAME` URL for a name server.
+  pub url: String,
+  /// Number of responses from the service that did not contain a valid IP.
+  pub garbage: u64,
+  /// Query that asks a name server for the IP, if the service is one.
+  query: Option<(SocketAddr, &'static str, RecordType)>,
+}
+
+impl Provider {
+  pub const fn new(url: String) -> Self {
+    Self {
+      url,
+      garbage: 0,
+      query: None,
+    }
+  }
+
+  /// Returns providers for built-in HTTP services.
+  pub fn builtin(urls: &[&str]) -> Vec<Self> {
+    urls.iter().map(|url| Self::new(url.to_string())).collect()
+  }
+
+  /// Returns the built-in name servers that answer with the public IP of a
+  /// family.
+  pub fn dns(family: Family) -> Vec<Self> {
+    DNS_PROVIDERS
+      .iter()
+      .filter(|(ip, ..)| Family::of(*ip) == family)
+      .map(|&(ip, name, rr_type)| {
+        let server = SocketAddr::new(ip, 53);
+
+        Self {
+          url: format!("dns://{server}/{name}"),
+          garbage: 0,
+          query: Some((server, name, rr_type)),
+        }
+      })
+      .collect()
+  }
+
+  /// Requests the public IP of the given family from the service.
+  ///
+  /// The connection is made over the requested family only, so a dual-stack
+  /// service cannot answer with the address of the other family.
+  ///
+  /// Responses that arrive but do not contain a valid IP are counted as garbage
+  /// so that unreliable services can be tried last. The whole request,
+  /// including reading the response, may take at most `timeout`.
+  pub async fn fetch(&mut self, http: &Http, family: Family, timeout: Duration) -> Result<IpAddr> {
+    if let Some((server, name, rr_type)) = self.query {
+      // name servers are listed by family, so the query is sent over it
+      let answers = dns::query(server, name, rr_type, false, timeout).await?;
+
+      return match answers.as_slice() {
+        [answer] => parse(answer.as_bytes(), family).inspect_err(|_| self.garbage += 1),
+        _ => Err(self.reject(format!("Expected one answer, got {}.", answers.len()))),
+      };
+    }
+
+    let request = http.family(family).get(&self.url).timeout(timeout);
+    let mut response = request.send().await?;
+    let status = response.status();
+
+    if !status.is_success() {
+      return Err(self.reject(format!("Unexpected HTTP status {status}.")));
+    }
+
+    if let Some(content_type) = response.headers().get(CONTENT_TYPE) {
+      let content_type = String::from_utf8_lossy(content_type.as_bytes()).into_owned();
+
+      if !content_type.starts_with("text/plain") {
+        return Err(self.reject(format!("Unexpected content type {content_type:?}.")));
+      }
+    }
+
+    if response
+      .content_length()
+      .is_some_and(|len| len > MAX_RESPONSE_LEN as u64)
+    {
+      return Err(self.reject(format!("Response is longer than {MAX_RESPONSE_LEN} bytes.")));
+    }
+
+    let mut body = Vec::new();
+
+    while let Some(chunk) = response.chunk().await? {
+      body.extend_from_slice(&chunk);
+
+      if body.len() > MAX_RESPONSE_LEN {
+        return Err(self.reject(format!("Response is longer than {MAX_RESPONSE_LEN} bytes.")));
+      }
+    }
+
+    parse(&body, family).inspect_err(|_| self.garbage += 1)
+  }
+
+  /// Counts a garbage response and returns an error describing it.
+  fn reject(&mut self, message: String) -> anyhow::Error {
+    self.garbage += 1;
+    anyhow!(message)
+  }
+}
+
+impl Family {
+  /// Returns the family of an IP.
+  pub const fn of(ip: IpAddr) -> Self {
+    match ip {
+      IpAddr::V4(_) => Self::V4,
+      IpAddr::V6(_) => Self::V6,
+    }
+  }
+
+  /// Returns the unspecified local address of this family, which restricts a
+  /// socket bound to it to this family.
+  pub const fn unspecified(self) -> IpAddr {
+    match self {
+      Self::V4 => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+      Self::V6 => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    }
+  }
+}
+
+/// Returns the globally routable address of a family that is bound to a
+/// network interface.
+///
+/// An interface may have several such addresses, such as temporary IPv6
+/// addresses, in which case the first one listed is returned.
+pub fn interface_address(name: &str, family: Family) -> Result<IpAddr> {
+  let interfaces = if_addrs::get_if_addrs().context("Failed to list network interfaces.")?;
+  let mut addrs = interfaces.iter().filter(|i| i.name == name).peekable();
+
+  if addrs.peek().is_none() {
+    bail!("Network interface `{name}` does not exist or has no addresses.");
+  }
+
+  addrs
+    .map(|i| i.ip())
+    .find(|&ip| Family::of(ip) == family && is_global(ip))
+    .with_context(|| {
+      format!("Network interface `{name}` has no globally routable {family} address.")
+    })
+}
+
+/// Returns `true` if an IP is globally routable, rather than private,
+/// loopback, link-local, multicast, or reserved for another special purpose.
+pub const fn is_global(ip: IpAddr) -> bool {
+  match ip {
+    IpAddr::V4(ip) => {
+      let [a, b, c, _] = ip.octets();
+
+      !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        || a == 0
+        // shared address space for carrier-grade NAT
+        || (a == 100 && b & 0xc0 == 64)
+        // IETF protocol assignments
+        || (a == 192 && b == 0 && c == 0)
+        // benchmarking
+        || (a == 198 && b & 0xfe == 18)
+        // reserved for future use
+        || a >= 240)
+    }
+
+    IpAddr::V6(ip) => {
+      let [a, b, ..] = ip.segments();
+
+      // only global unicast addresses are routable, except for documentation
+      a & 0xe000 == 0x2000 && !(a == 0x2001 && b == 0xdb8)
+    }
+  }
+}
+
+/// Replaces the bits of an address after its first `len` bits with those of a
+/// suffix, such as to address another host in the same delegated prefix.
+pub const fn with_suffix(prefix: Ipv6Addr, len: u8, suffix: Ipv6Addr) -> Ipv6Addr {
+  let mask = match u128::MAX.checked_shl(128 - len as u32) {
+    Some(mask) => mask,
+    None => 0,
+  };
+
+  Ipv6Addr::from_bits((prefix.to_bits() & mask) | (suffix.to_bits() & !mask))
+}
+
+const fn ipv4(a: u8, b: u8, c: u8, d: u8) -> IpAddr {
+  IpAddr::V4(Ipv4Addr::new(a, b, c, d))
+}
+
+const fn ipv6([a, b, c, d, e, f, g, h]: [u16; 8]) -> IpAddr {
+  IpAddr::V6(Ipv6Addr::new(a, b, c, d, e, f, g, h))
+}
+
+impl fmt::Display for Family {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::V4 => f.write_str("IPv4"),
+      Self::V6 => f.write_str("IPv6"),
+    }
+  }
+}
+
+/// Strictly parses a response body containing an address of the given family
+/// and nothing else besides surrounding whitespace.
+fn parse(body: &[u8], family: Family) -> Result<IpAddr> {
+  let text = std::str::from_utf8(body).context("Response is not valid UTF-8.")?;
+
+  let Ok(ip) = text.trim().parse() else {
+    bail!("Response {text:?} is not an IP address.");
+  };
+
+  if Family::of(ip) != family {
+    bail!("Response {ip} is not an {family} address.");
+  }
+
+  Ok(ip)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn ip(s: &str) -> IpAddr {
+    s.parse().unwrap()
+  }
+
+  #[test]
+  fn accepts_globally_routable_addresses() {
+    for addr in [
+      "1.1.1.1",
+      "100.63.255.255",
+      "100.128.0.1",
+      "198.20.0.1",
+      "2606:4700::1111",
+    ] {
+      assert!(is_global(ip(addr)), "{addr}");
+    }
+  }
+
+  #[test]
+  fn rejects_special_purpose_ranges() {
+    let bogons = [
+      // carrier-grade NAT
+      "100.64.0.1",
+      "100.127.255.255",
+      // benchmarking
+      "198.18.0.1",
+      "198.19.255.255",
+      // documentation
+      "192.0.2.1",
+      "198.51.100.9",
+      "203.0.113.7",
+      "2001:db8::1",
+      // reserved for future use
+      "240.0.0.1",
+      "255.255.255.254",
+      // unique local
+      "fd00::2",
+      "fc00::1",
+      // link-local
+      "169.254.1.1",
+      "fe80::1",
+      // multicast
+      "224.0.0.1",
+      "239.255.255.250",
+      "ff02::1",
+      // private and loopback
+      "10.1.2.3",
+      "192.168.1.1",
+      "127.0.0.1",
+      "::1",
+    ];
+
+    for addr in bogons {
+      assert!(!is_global(ip(addr)), "{addr}");
+    }
+  }
+
+  fn v6(s: &str) -> Ipv6Addr {
+    s.parse().unwrap()
+  }
+
+  #[test]
+  fn combines_prefix_with_suffix() {
+    let prefix = v6("2001:db8:aaaa:bbbb:cccc:dddd:eeee:ffff");
+    let suffix = v6("::1:2:3:4");
+
+    assert_eq!(
+      with_suffix(prefix, 56, suffix),
+      v6("2001:db8:aaaa:bb00:1:2:3:4")
+    );
+    assert_eq!(
+      with_suffix(prefix, 64, suffix),
+      v6("2001:db8:aaaa:bbbb:1:2:3:4")
+    );
+    assert_eq!(with_suffix(prefix, 128, suffix), prefix);
+    assert_eq!(with_suffix(prefix, 0, suffix), suffix);
+  }
+
+  #[test]
+  fn masks_suffix_bits_inside_prefix() {
+    let prefix = v6("2001:db8:1:2::");
+    let suffix = v6("ffff:ffff:ffff:ffff::abcd");
+
+    assert_eq!(with_suffix(prefix, 64, suffix), v6("2001:db8:1:2::abcd"));
+    assert_eq!(with_suffix(prefix, 56, suffix), v6("2001:db8:1:ff::abcd"));
+  }
+}
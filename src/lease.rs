@@ -0,0 +1,38 @@
+use anyhow::Result;
+use aws_sdk_route53::types::{Change, ChangeAction, ResourceRecordSet, RrType};
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::aws;
+
+/// Returns the name of the TXT record that holds the lease of a domain's
+/// record.
+pub fn record_name(domain: &str) -> String {
+  format!("_lease.{domain}")
+}
+
+/// Builds a change that sets the expiry of a domain's lease.
+pub fn renew(domain: &str, expires: DateTime<Utc>, ttl: i64) -> Result<Change> {
+  let value = format!(
+    "\"expires={}\"",
+    expires.to_rfc3339_opts(SecondsFormat::Secs, true)
+  );
+
+  aws::change(
+    ChangeAction::Upsert,
+    RrType::Txt,
+    &record_name(domain),
+    &[&value],
+    ttl,
+  )
+}
+
+/// Returns the expiry of a lease record, if it holds one.
+pub fn expiry(set: &ResourceRecordSet) -> Option<DateTime<Utc>> {
+  set.resource_records().iter().find_map(|record| {
+    let value = record.value().trim_matches('"').strip_prefix("expires=")?;
+
+    DateTime::parse_from_rfc3339(value)
+      .ok()
+      .map(|time| time.to_utc())
+  })
+}
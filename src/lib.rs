@@ -0,0 +1,53 @@
+//! Keeps Route 53 records pointed at the public IP of a machine.
+//!
+//! The `ddns-route53` binary is built on the modules of this library, which
+//! other programs can use to embed the same building blocks. [`updater`] is
+//! the simplest entry point: an [`IpSource`] detects an IP and an [`Updater`]
+//! writes it to the record of a [`RecordTarget`] in the zones of a
+//! [`DnsProvider`].
+//!
+//! The daemon only updates the records of other providers than Route 53
+//! through an [`Updater`]. Its Route 53 updates are batched per hosted zone
+//! and also cover routing policies, leases, metadata, and deletions, so it
+//! builds them from [`aws`] and [`zone`] directly. Its public IP detection,
+//! with retries, fallbacks, and bogon filtering, is likewise its own.
+
+pub mod api;
+pub mod aws;
+pub mod backoff;
+pub mod consul;
+pub mod control;
+pub mod dns;
+pub mod flap;
+pub mod graphite;
+pub mod health;
+pub mod hook;
+pub mod host;
+pub mod http;
+pub mod inventory;
+pub mod ip;
+pub mod lease;
+pub mod metadata;
+pub mod metrics;
+pub mod name;
+pub mod notify;
+pub mod pacer;
+pub mod private;
+pub mod provider;
+pub mod pushgateway;
+pub mod server;
+pub mod signal;
+pub mod sink;
+pub mod state;
+pub mod systemd;
+pub mod trace;
+pub mod ttl;
+pub mod updater;
+pub mod verify;
+pub mod zabbix;
+pub mod zone;
+
+pub use self::{
+  provider::DnsProvider,
+  updater::{IpSource, RecordTarget, Updater},
+};
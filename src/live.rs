@@ -0,0 +1,275 @@
+use std::{io, net::IpAddr};
+
+use anyhow::{anyhow, Result};
+use aws_sdk_route53::types::RrType;
+use clap::ValueEnum;
+use serde::Serialize;
+
+use ddns_route53::{
+  aws::{self, Accounts},
+  http::Http,
+  inventory::Source,
+  ip::{self, Family, Provider},
+  provider,
+};
+
+use crate::{config::RecordTypes, ip_providers, Args, Domain};
+
+/// Output format of the comparison.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+  /// A table.
+  Text,
+  Json,
+}
+
+/// A record of a domain compared with the IP it should have.
+#[derive(Serialize)]
+struct Row {
+  domain: String,
+  r#type: String,
+  /// Values of the record in Route 53, separated by spaces, or empty if it
+  /// does not exist.
+  record: String,
+  /// IP the record should have, if it is known.
+  expected: Option<IpAddr>,
+  /// `current`, `outdated`, `missing`, `unknown` if the IP the record should
+  /// have is not known to this instance, or `error` if it could not be
+  /// compared.
+  status: &'static str,
+  error: Option<String>,
+}
+
+/// Compares the records of domains in Route 53 with the IPs they should have
+/// and prints the result. Returns `false` if any record is not current.
+pub async fn run(args: &Args, domains: &[Domain], format: Format) -> Result<bool> {
+  let http = Http::with_options(&args.http)?;
+  let default_types = args.record_types();
+
+  // the public IP of a family is only detected if a domain follows it, or
+  // the delegated prefix is taken from the public IPv6
+  let mut public = Vec::new();
+
+  for family in [Family::V4, Family::V6] {
+    let follows = domains.iter().any(|domain| match domain.source {
+      Source::Public => domain
+        .record_types
+        .unwrap_or(default_types)
+        .includes(family),
+      Source::Prefix { .. } => family == Family::V6 && args.prefix_interface.is_none(),
+      _ => false,
+    });
+
+    if follows {
+      public.push((family, detect_public_ip(args, &http, family).await));
+    }
+  }
+
+  let mut accounts = Accounts::new(&args.aws);
+  let mut rows = Vec::new();
+
+  for domain in domains {
+    let types = domain.record_types.unwrap_or(default_types);
+
+    let families = match &domain.source {
+      Source::Public => [Family::V4, Family::V6]
+        .into_iter()
+        .filter(|&family| types.includes(family))
+        .collect(),
+      Source::Static(ip) => vec![Family::of(*ip)],
+      // the record is the AAAA record only if the domain has no A record
+      _ if types == RecordTypes::Aaaa => vec![Family::V6],
+      _ => vec![Family::V4],
+    };
+
+    if domain.provider != provider::Kind::Route53 {
+      for family in families {
+        rows.push(Row {
+          domain: domain.name.to_string(),
+          r#type: rr_type(family).as_str().into(),
+          record: String::new(),
+          expected: None,
+          status: "unknown",
+          error: Some("Only records in Route 53 are compared.".into()),
+        });
+      }
+
+      continue;
+    }
+
+    let public_ip = |family| match public.iter().find(|(f, _)| *f == family) {
+      Some((_, Ok(ip))) => Ok(Some(*ip)),
+      Some((_, Err(err))) => Err(anyhow!("{err:#}")),
+      None => Ok(None),
+    };
+
+    for family in families {
+      let expected = match &domain.source {
+        Source::Public => public_ip(family),
+
+        Source::Static(ip) => Ok(Some(*ip)),
+        Source::Private { source, .. } => source
+          .detect(&http, family, args.ip_timeout)
+          .await
+          .map(Some),
+
+        Source::Remote { provider, .. } => Provider::new(provider.url.clone())
+          .fetch(&http, family, args.ip_timeout)
+          .await
+          .map(Some),
+
+        // the record is managed by the instance that holds the lease
+        Source::Lease => Ok(None),
+
+        Source::Prefix { suffix, .. } => {
+          let prefix = match &args.prefix_interface {
+            Some(interface) => ip::interface_address(interface, Family::V6).map(Some),
+            None => public_ip(Family::V6),
+          };
+
+          prefix.map(|prefix| match prefix {
+            Some(IpAddr::V6(prefix)) => Some(IpAddr::V6(ip::with_suffix(
+              prefix,
+              args.prefix_length,
+              *suffix,
+            ))),
+            _ => None,
+          })
+        }
+      };
+
+      let lookup = async {
+        let zone = accounts
+          .zone(&domain.identity, &domain.name, &domain.zone)
+          .await?;
+
+        let route53 = accounts.client(&domain.identity).await;
+
+        aws::find_record(route53, &zone.id, &domain.name, rr_type(family), None).await
+      };
+
+      let mut row = Row {
+        domain: domain.name.to_string(),
+        r#type: rr_type(family).as_str().into(),
+        record: String::new(),
+        expected: None,
+        status: "error",
+        error: None,
+      };
+
+      match (lookup.await, expected) {
+        (Err(err), _) | (_, Err(err)) => row.error = Some(format!("{err:#}")),
+
+        (Ok(set), Ok(expected)) => {
+          let values: Vec<&str> = set
+            .iter()
+            .flat_map(|set| set.resource_records())
+            .map(|r| r.value())
+            .collect();
+
+          row.record = values.join(" ");
+          row.expected = expected;
+          row.status = match (expected, values.as_slice()) {
+            (_, []) => "missing",
+            (None, _) => "unknown",
+            (Some(ip), [value]) if value.parse() == Ok(ip) => "current",
+            (Some(_), _) => "outdated",
+          };
+        }
+      }
+
+      rows.push(row);
+    }
+  }
+
+  match format {
+    Format::Json => {
+      serde_json::to_writer_pretty(io::stdout().lock(), &rows)?;
+      println!();
+    }
+
+    Format::Text => print_table(&rows),
+  }
+
+  Ok(
+    rows
+      .iter()
+      .all(|row| matches!(row.status, "current" | "unknown")),
+  )
+}
+
+/// Detects the public IP of a family like the daemon does, without retries.
+async fn detect_public_ip(args: &Args, http: &Http, family: Family) -> Result<IpAddr> {
+  if let Some(interface) = &args.interface {
+    match ip::interface_address(interface, family) {
+      Ok(ip) => return Ok(ip),
+      Err(err) if !args.interface_fallback => return Err(err),
+      Err(_) => {}
+    }
+  }
+
+  let mut last_err = anyhow!("No IP providers are configured.");
+
+  for mut provider in ip_providers(&args.ip_url, args.ip_source, family) {
+    last_err = match provider.fetch(http, family, args.ip_timeout).await {
+      Ok(ip) if args.allow_bogons || ip::is_global(ip) => return Ok(ip),
+      Ok(ip) => anyhow!(
+        "{} responded with {ip}, which is not globally routable.",
+        provider.url
+      ),
+      Err(err) => err.context(format!("Failed to get public IP from {}.", provider.url)),
+    };
+  }
+
+  Err(last_err)
+}
+
+/// Prints the rows of the comparison as a table.
+fn print_table(rows: &[Row]) {
+  let width = |column: fn(&Row) -> usize, min| rows.iter().map(column).max().unwrap_or(0).max(min);
+
+  let domain_width = width(|row| row.domain.len(), 6);
+  let record_width = width(|row| row.record.len(), 6);
+  let expected_width = width(|row| expected(row).len(), 8);
+
+  println!(
+    "{:domain_width$}  TYPE  {:record_width$}  {:expected_width$}  STATUS",
+    "DOMAIN", "RECORD", "EXPECTED"
+  );
+
+  for row in rows {
+    let record = match row.record.is_empty() {
+      true => "-",
+      false => &row.record,
+    };
+
+    print!(
+      "{:domain_width$}  {:4}  {record:record_width$}  {:expected_width$}  {}",
+      row.domain,
+      row.r#type,
+      expected(row),
+      row.status,
+    );
+
+    match &row.error {
+      Some(err) => println!("  {err}"),
+      None => println!(),
+    }
+  }
+}
+
+/// Returns the expected IP of a row as text.
+fn expected(row: &Row) -> String {
+  match row.expected {
+    Some(ip) => ip.to_string(),
+    None => "-".into(),
+  }
+}
+
+/// Returns the type of the record that holds an IP of a family.
+fn rr_type(family: Family) -> RrType {
+  match family {
+    Family::V4 => RrType::A,
+    Family::V6 => RrType::Aaaa,
+  }
+}
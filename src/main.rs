@@ -1,225 +1,3950 @@
-use std::{net::Ipv4Addr, time::Duration};
+mod acme;
+mod check;
+mod config;
+mod export;
+mod import;
+mod live;
+mod selftest;
+mod summary;
+#[cfg(feature = "tray")]
+mod tray;
+mod update;
 
-use anyhow::{bail, Context, Result};
+use std::{
+  collections::BTreeMap,
+  env, mem,
+  net::{IpAddr, SocketAddr},
+  path::{Path, PathBuf},
+  process,
+  time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
 use aws_sdk_route53::{
   self as route53,
-  types::{Change, ChangeAction::Upsert, ChangeBatch, ResourceRecord, ResourceRecordSet, RrType},
+  types::{Change, HostedZone, ResourceRecordSet, RrType},
 };
-use clap::Parser;
+use chrono::{DateTime, TimeDelta, Utc};
+use clap::{
+  builder::BoolishValueParser, ArgAction, CommandFactory, FromArgMatches, Parser, Subcommand,
+};
+use futures_util::{stream, StreamExt};
+use reqwest::Url;
+use tracing::{debug, error, field, info, info_span, level_filters::LevelFilter};
 
-/// Basic log macro.
-macro_rules! log {
-  ($($args:tt)*) => {
-    {
-      print!("[{}] ", chrono::Utc::now().format("%F %T"));
-      println!($($args)*);
-    }
-  };
-}
+use ddns_route53::{
+  api,
+  aws::{self, connect_as, list_zones, Accounts, AwsOptions},
+  backoff::Backoff,
+  consul::{Consul, ConsulOptions},
+  control::Controls,
+  flap::{Confirmations, FlapDetector},
+  graphite::{Graphite, GraphiteOptions},
+  health::{DomainStatus, Health, HealthOptions, Status},
+  hook::{Failure, FailureHook},
+  host,
+  http::{Http, HttpOptions},
+  inventory::{self, ReapAction, Reaper, Source},
+  ip::{self, Family, Provider},
+  lease, metadata,
+  metrics::{DomainMetrics, Exporter, Snapshot},
+  name::DomainName,
+  notify::{Event, Notifier, NotifyOptions},
+  pacer, provider,
+  pushgateway::{Pushgateway, PushgatewayOptions},
+  signal::{self, Shutdown},
+  sink::{LogFormat, LogTarget},
+  state::{DomainStats, PendingChange, State},
+  systemd::Systemd,
+  trace::{self, traced},
+  ttl::DynamicTtl,
+  verify::{Unverified, Verifier, VerifyOptions},
+  zabbix::{Zabbix, ZabbixOptions},
+  zone::{self, visibility, ZoneCache, ZoneChoice, ZoneVerdict},
+};
 
-/// Basic error log macro.
-macro_rules! log_err {
-  ($($args:tt)*) => {
-    {
-      eprint!("[{}] ERROR — ", chrono::Utc::now().format("%F %T"));
-      eprintln!($($args)*);
-    }
-  };
-}
+use self::{
+  config::{DomainConfig, RecordTypes},
+  summary::Baseline,
+};
+
+/// Version of the program followed by details of the build, which are set by
+/// the build script.
+const VERSION: &str = concat!(
+  env!("CARGO_PKG_VERSION"),
+  " (commit ",
+  env!("BUILD_COMMIT"),
+  ", built ",
+  env!("BUILD_DATE"),
+  " for ",
+  env!("BUILD_TARGET"),
+  ", features: ",
+  env!("BUILD_FEATURES"),
+  ")",
+);
 
 #[derive(Parser)]
-#[command(version, about)]
+#[command(
+  version,
+  long_version = VERSION,
+  about,
+  subcommand_negates_reqs = true,
+  args_override_self = true
+)]
 struct Args {
   /// Domain names to update.
-  #[arg(required = true)]
+  ///
+  /// A name may be followed by `=public`, `=private`, or `=ZONE_ID` to choose
+  /// between hosted zones that share the same name.
+  #[arg(
+    required_unless_present_any = ["inventory", "config", "use_hostname"],
+    value_name = "DOMAIN[=ZONE]"
+  )]
   domains: Vec<String>,
+
+  /// Also update a domain named after the host name of this machine under
+  /// the domain given with `--zone`, such as `nas.example.com` on a machine
+  /// named `nas` with `--zone example.com`, so that the same settings can be
+  /// deployed to many machines.
+  #[arg(long, requires = "zone")]
+  use_hostname: bool,
+
+  /// Domain that `--use-hostname` names this machine under, optionally
+  /// followed by a zone choice like other domain names.
+  #[arg(long, value_name = "DOMAIN[=ZONE]", requires = "use_hostname")]
+  zone: Option<String>,
+
+  /// Template of the name that `--use-hostname` puts under the domain, in
+  /// which `{host}` stands for the host name, such as `{host}-lan`.
+  #[arg(
+    long,
+    value_name = "TEMPLATE",
+    default_value = "{host}",
+    requires = "use_hostname"
+  )]
+  hostname_template: String,
+
+  /// Path of a TOML file of settings and domains.
+  ///
+  /// Settings have the names of the command line options, such as
+  /// `interval = "1m"`, and options given on the command line or in their
+  /// `DDNS_` environment variables take precedence over them. Domains are
+  /// declared in a `[domains."NAME"]` table each, with optional `zone`,
+  /// `ttl`, `type` (`A`, `AAAA`, or `both`), `profile`, `role-arn`,
+  /// `provider` (`route53` or `cloudflare`), `source` (`ec2-metadata` or
+  /// `local`), `suffix` (an interface identifier in the delegated IPv6
+  /// prefix), and `enabled` settings.
+  #[arg(long, global = true, value_name = "FILE")]
+  config: Option<PathBuf>,
+
+  /// Domains declared in the config file.
+  #[arg(skip)]
+  config_domains: Vec<DomainConfig>,
+
+  /// How long to wait between update passes.
+  #[arg(long, default_value = "5m", value_parser = humantime::parse_duration)]
+  interval: Duration,
+
+  /// Run a single update pass and exit, with a non-zero status if the public
+  /// IP could not be determined or any domain failed to update, for running
+  /// from cron or scripts.
+  #[arg(long, conflicts_with = "tray")]
+  once: bool,
+
+  /// Run a single update pass that prints the changes it would submit to
+  /// Route 53 instead of submitting them, and exit without saving state,
+  /// sending notifications, or deleting records.
+  #[arg(long, conflicts_with = "tray")]
+  dry_run: bool,
+
+  /// TTL to write records with.
+  #[arg(
+    long,
+    default_value = "5m",
+    value_parser = humantime::parse_duration,
+    conflicts_with = "dynamic_ttl"
+  )]
+  ttl: Duration,
+
+  /// How to discover the public IP: from HTTP services, or from name servers
+  /// that answer special names with it where HTTP services are blocked.
+  #[arg(long, value_enum, default_value_t)]
+  ip_source: ip::Source,
+
+  /// Read the public IP from the globally routable addresses of this network
+  /// interface instead of asking external services.
+  #[arg(long, value_name = "NAME")]
+  interface: Option<String>,
+
+  /// Ask external services for the public IP if the interface given with
+  /// `--interface` has no globally routable address.
+  #[arg(long, requires = "interface")]
+  interface_fallback: bool,
+
+  /// URL of a service that responds with the public IP of the client as plain
+  /// text, tried before the built-in ones. May be given more than once.
+  #[arg(long, value_name = "URL")]
+  ip_url: Vec<Url>,
+
+  /// How long a request to an IP provider may take before it fails and the
+  /// next provider is tried.
+  #[arg(
+    long,
+    default_value = "5s",
+    value_name = "DURATION",
+    value_parser = humantime::parse_duration
+  )]
+  ip_timeout: Duration,
+
+  /// Number of times to retry a request to an IP provider that fails, with
+  /// backoff between attempts, before falling back to the next provider.
+  #[arg(long, default_value_t = 0, value_name = "COUNT")]
+  ip_retries: u32,
+
+  /// How long the list of hosted zones is kept before it is requested again.
+  /// A domain that matches none of the kept zones always has them listed
+  /// again, since its zone may have been created since. `0s` lists them on
+  /// every pass.
+  #[arg(
+    long,
+    default_value = "1h",
+    value_name = "DURATION",
+    value_parser = humantime::parse_duration
+  )]
+  zone_cache_ttl: Duration,
+
+  /// Number of hosted zones whose changes are submitted at the same time.
+  #[arg(
+    long,
+    default_value_t = 4,
+    value_name = "COUNT",
+    value_parser = clap::value_parser!(u64).range(1..)
+  )]
+  zone_concurrency: u64,
+
+  /// Path of a JSON inventory file of additional domain names to manage, each
+  /// mapped to the source of its IP.
+  ///
+  /// A source is `"public"` for the public IP of this machine, `{"ip": IP}` for
+  /// a fixed address, `{"url": URL}` for an HTTP endpoint on another host
+  /// that responds with that host's IP, or `"lease"` for a record registered
+  /// by another instance with `--lease` that is deleted when its lease lapses.
+  #[arg(long, global = true, value_name = "FILE")]
+  inventory: Option<PathBuf>,
+
+  /// Register records as leases that expire unless renewed, by writing the
+  /// expiry to a companion `_lease.DOMAIN` TXT record and renewing it while
+  /// the daemon runs.
+  #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration)]
+  lease: Option<Duration>,
+
+  /// Publish each record as this instance's member of a multivalue answer
+  /// record set, identified by ID, so that several instances can publish
+  /// their IPs under the same name.
+  #[arg(long, value_name = "ID", conflicts_with = "lease")]
+  set_identifier: Option<String>,
+
+  /// ID of a Route 53 health check that decides whether this instance's
+  /// member of each record set is returned.
+  #[arg(long, value_name = "ID", requires = "set_identifier")]
+  health_check_id: Option<String>,
+
+  /// Publish each record as a member of a weighted record set instead of a
+  /// multivalue answer record set. Route 53 returns the member in proportion
+  /// to its weight among the weights of all members, and returns a member
+  /// with a weight of 0 only if every member has a weight of 0.
+  #[arg(
+    long,
+    value_name = "WEIGHT",
+    requires = "set_identifier",
+    value_parser = clap::value_parser!(i64).range(0..=255)
+  )]
+  weight: Option<i64>,
+
+  /// Whenever the records of a domain are written, also write a companion
+  /// `_ddns.DOMAIN` TXT record with the time, the host name of this machine,
+  /// and the version of the daemon, as a trail of which host last updated
+  /// them.
+  #[arg(long)]
+  write_txt_metadata: bool,
+
+  /// Also detect the public IPv6 of this machine and keep AAAA records of
+  /// domains that follow the public IP up to date, alongside their A records.
+  #[arg(long)]
+  dual_stack: bool,
+
+  /// Detect only the public IPv6 of this machine and keep the AAAA records of
+  /// domains that follow the public IP up to date instead of their A records.
+  #[arg(long, conflicts_with = "dual_stack")]
+  ipv6_only: bool,
+
+  /// Length of the IPv6 prefix delegated to this network, which the AAAA
+  /// records of hosts with a `suffix` combine with their suffixes, such as 56.
+  #[arg(long, default_value_t = 64, value_parser = clap::value_parser!(u8).range(1..=128))]
+  prefix_length: u8,
+
+  /// Read the delegated IPv6 prefix from the globally routable IPv6 address
+  /// of this network interface instead of the public IPv6 of this machine.
+  #[arg(long, value_name = "NAME")]
+  prefix_interface: Option<String>,
+
+  /// Delete a domain's record of the other address family in the same change
+  /// that updates it, so that clients do not keep trying an address the host
+  /// can no longer be reached at after it lost IPv4 or IPv6 connectivity.
+  /// With `--dual-stack`, a record is only deleted while the public IP of its
+  /// family cannot be detected.
+  #[arg(long)]
+  delete_stale_family: bool,
+
+  /// Reap inventory hosts with IP endpoints that have not reported their IP
+  /// for this long.
+  #[arg(
+    long,
+    value_name = "DURATION",
+    value_parser = humantime::parse_duration,
+    requires = "inventory"
+  )]
+  reap_after: Option<Duration>,
+
+  /// What to do with reaped hosts.
+  #[arg(long, default_value = "alert", requires = "reap_after")]
+  reap_action: ReapAction,
+
+  /// Delete the A and AAAA records this instance wrote, along with their
+  /// lease and metadata records, when it is stopped with SIGTERM or Ctrl+C,
+  /// so that the names of short-lived machines do not outlive them.
+  #[arg(long, conflicts_with = "dry_run")]
+  delete_on_exit: bool,
+
+  /// Point the records at this IP on exit instead of deleting them, such as
+  /// at a maintenance page. Records of the other address family are still
+  /// deleted.
+  #[arg(long, value_name = "IP", requires = "delete_on_exit")]
+  exit_ip: Option<IpAddr>,
+
+  /// Delete the A and AAAA records of domains in the state file that are no
+  /// longer configured, along with their lease and metadata records, when
+  /// the daemon starts.
+  #[arg(long, requires = "state_file")]
+  prune: bool,
+
+  /// Path of a JSON file in which to save daemon state and statistics.
+  #[arg(long, global = true)]
+  state_file: Option<PathBuf>,
+
+  /// Minimum level of messages to log: `error`, `warn`, `info`, `debug` for
+  /// diagnostic messages such as the timing of external calls, `trace`, or
+  /// `off`.
+  #[arg(long, global = true, default_value = "info", value_name = "LEVEL")]
+  log_level: LevelFilter,
+
+  /// Format of log messages: lines of `text`, or `json` objects with the
+  /// fields of each message, such as `domain` and `new_ip`, for log
+  /// aggregators.
+  #[arg(
+    long,
+    global = true,
+    value_enum,
+    default_value_t,
+    value_name = "FORMAT"
+  )]
+  log_format: LogFormat,
+
+  /// Where to write log messages: `stdout`, the Windows Event Log with
+  /// `eventlog`, or macOS unified logging with `oslog`.
+  ///
+  /// Diagnostic messages enabled with `--log-level` are still printed to
+  /// standard error.
+  #[arg(
+    long,
+    global = true,
+    value_enum,
+    default_value_t,
+    value_name = "TARGET"
+  )]
+  log_target: LogTarget,
+
+  /// Address to serve the control API on, such as `127.0.0.1:8053`, and for
+  /// the `pause` and `resume` commands to reach the running daemon at.
+  ///
+  /// The API has no authentication, so only listen on addresses that trusted
+  /// clients alone can reach.
+  #[arg(long, global = true, value_name = "ADDR")]
+  control_addr: Option<SocketAddr>,
+
+  /// Address to serve Prometheus metrics on at `/metrics`, such as
+  /// `0.0.0.0:9100`.
+  #[arg(long, value_name = "ADDR")]
+  metrics_addr: Option<SocketAddr>,
+
+  /// Show the daemon's status in the system tray, with menu items to update
+  /// immediately or pause updates.
+  #[arg(long)]
+  tray: bool,
+
+  /// Check GitHub once a day for a newer release and log when one is
+  /// available. Nothing is installed without `self-update`.
+  #[arg(long)]
+  check_updates: bool,
+
+  /// How long to keep retrying with backoff, at startup, while the public IP
+  /// cannot be determined because the network is still coming up.
+  #[arg(long, default_value = "5m", value_parser = humantime::parse_duration)]
+  network_wait: Duration,
+
+  /// Ignore statistics saved in the state file and start counting from zero.
+  #[arg(long)]
+  reset_stats: bool,
+
+  /// Number of IP changes among at most two addresses within the flap window
+  /// that puts updates on hold. Zero disables flap detection.
+  #[arg(long, default_value_t = 4)]
+  flap_threshold: usize,
+
+  /// Time window in which IP changes count towards the flap threshold.
+  #[arg(long, default_value = "1h", value_parser = humantime::parse_duration)]
+  flap_window: Duration,
+
+  /// How long to hold updates after the IP last flapped.
+  #[arg(long, default_value = "1h", value_parser = humantime::parse_duration)]
+  hold_down: Duration,
+
+  /// Number of checks in a row that must detect a changed public IP before
+  /// records are updated to it, unless another IP provider confirms it
+  /// sooner.
+  #[arg(
+    long,
+    default_value_t = 1,
+    value_name = "COUNT",
+    value_parser = clap::value_parser!(u32).range(1..)
+  )]
+  confirmations: u32,
+
+  /// Accept public IPs in private, link-local, or other special-purpose
+  /// ranges from IP providers, which are otherwise rejected as garbage.
+  #[arg(long)]
+  allow_bogons: bool,
+
+  /// Shortest time between two changes of the records, so that the next IP
+  /// change is only written once this long has passed since the last write.
+  #[arg(
+    long,
+    default_value = "0s",
+    value_name = "DURATION",
+    value_parser = humantime::parse_duration
+  )]
+  min_change_interval: Duration,
+
+  /// Adjust record TTLs automatically: keep them at `--min-ttl` while the
+  /// public IP changes often and raise them towards `--max-ttl` as it stays
+  /// stable.
+  #[arg(long)]
+  dynamic_ttl: bool,
+
+  /// Lowest TTL to use with `--dynamic-ttl`.
+  #[arg(
+    long,
+    default_value = "1m",
+    value_parser = humantime::parse_duration,
+    requires = "dynamic_ttl"
+  )]
+  min_ttl: Duration,
+
+  /// Highest TTL to use with `--dynamic-ttl`.
+  #[arg(
+    long,
+    default_value = "1h",
+    value_parser = humantime::parse_duration,
+    requires = "dynamic_ttl"
+  )]
+  max_ttl: Duration,
+
+  /// Shell command to run when a domain fails to update or the public IP
+  /// cannot be determined for `--failure-threshold` attempts in a row.
+  ///
+  /// The command receives details in the `DDNS_FAILURE` (`update` or `ip`),
+  /// `DDNS_DOMAIN`, `DDNS_FAILURES`, and `DDNS_ERROR` environment variables.
+  #[arg(long, value_name = "COMMAND")]
+  on_failure: Option<String>,
+
+  /// Number of consecutive failures after which `--on-failure` runs.
+  #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u64).range(1..))]
+  failure_threshold: u64,
+
+  #[command(flatten)]
+  aws: AwsOptions,
+
+  /// API token that may edit the DNS records of the Cloudflare zones of
+  /// domains with `provider = "cloudflare"` in the config file.
+  #[arg(
+    long,
+    env = "CLOUDFLARE_API_TOKEN",
+    hide_env_values = true,
+    value_name = "TOKEN"
+  )]
+  cloudflare_token: Option<String>,
+
+  #[command(flatten)]
+  http: HttpOptions,
+
+  #[command(flatten)]
+  notify: NotifyOptions,
+
+  #[command(flatten)]
+  consul: ConsulOptions,
+
+  #[command(flatten)]
+  verify: VerifyOptions,
+
+  #[command(flatten)]
+  health: HealthOptions,
+
+  #[command(flatten)]
+  pushgateway: PushgatewayOptions,
+
+  #[command(flatten)]
+  graphite: GraphiteOptions,
+
+  #[command(flatten)]
+  zabbix: ZabbixOptions,
+
+  #[command(subcommand)]
+  command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+  /// Keep the records of domains up to date until stopped, as is done
+  /// without a subcommand.
+  Run {
+    /// Domain names to update, in addition to those given before the
+    /// subcommand.
+    #[arg(value_name = "DOMAIN[=ZONE]")]
+    domains: Vec<String>,
+  },
+
+  /// Run a single update pass and exit, like `--once`.
+  Update {
+    /// Domain names to update, in addition to those given before the
+    /// subcommand.
+    #[arg(value_name = "DOMAIN[=ZONE]")]
+    domains: Vec<String>,
+  },
+
+  /// Compare the public IP with the records of domains in Route 53, exiting
+  /// with a non-zero status if any record is out of date. With
+  /// `--state-file`, print per-domain statistics of a running daemon instead
+  /// unless `--live` is given.
+  Status {
+    /// Domain names to compare, in addition to those of the config file and
+    /// inventory.
+    #[arg(value_name = "DOMAIN[=ZONE]")]
+    domains: Vec<String>,
+
+    /// Compare the live records even with `--state-file`.
+    #[arg(long)]
+    live: bool,
+
+    /// Output format of the comparison.
+    #[arg(long, default_value = "text")]
+    format: live::Format,
+  },
+
+  /// Check the health of a running daemon from its state file, exiting with
+  /// the status codes of the Nagios plugin interface. With `--setup`, check
+  /// the domains and the credentials they are updated with instead.
+  Check {
+    /// Domain names to check with `--setup`, in addition to those of the
+    /// config file and inventory.
+    #[arg(value_name = "DOMAIN[=ZONE]", requires = "setup")]
+    domains: Vec<String>,
+
+    /// Validate the domains, list hosted zones with each of their credentials,
+    /// and look up their records without making changes.
+    #[arg(long, conflicts_with_all = ["warning", "critical"])]
+    setup: bool,
+
+    /// Output format.
+    #[arg(long, default_value = "text")]
+    format: check::Format,
+
+    /// How long the daemon may go without an update pass before the check
+    /// warns.
+    #[arg(long, default_value = "15m", value_parser = humantime::parse_duration)]
+    warning: Duration,
+
+    /// How long the daemon may go without an update pass before the check is
+    /// critical.
+    #[arg(long, default_value = "1h", value_parser = humantime::parse_duration)]
+    critical: Duration,
+  },
+
+  /// Explain which hosted zone a domain name is matched to and why.
+  Explain {
+    /// Domain name to explain, optionally followed by a zone choice.
+    #[arg(value_name = "DOMAIN[=ZONE]")]
+    domain: String,
+  },
+
+  /// List visible hosted zones and the zones domain names are matched to.
+  Zones {
+    /// Domain names to match along with the configured domains, optionally
+    /// followed by zone choices.
+    #[arg(value_name = "DOMAIN[=ZONE]")]
+    domains: Vec<String>,
+  },
+
+  /// Export the live Route 53 records of domains as JSON or CSV.
+  Export {
+    /// Domain names to export, optionally followed by zone choices, in
+    /// addition to those in the inventory.
+    #[arg(value_name = "DOMAIN[=ZONE]")]
+    domains: Vec<String>,
+
+    /// Output format.
+    #[arg(long, default_value = "json")]
+    format: export::Format,
+  },
+
+  /// Import the names of A and AAAA records from a BIND zone file or CSV file
+  /// into the `--inventory` file, or print them as an inventory without one.
+  Import {
+    /// File to import.
+    file: PathBuf,
+
+    /// Format of the file. Detected from its extension by default.
+    #[arg(long)]
+    format: Option<import::Format>,
+
+    /// Origin of relative names in a zone file without an `$ORIGIN`
+    /// directive.
+    #[arg(long, value_name = "DOMAIN")]
+    origin: Option<String>,
+
+    /// Source of the IPs of imported domains.
+    #[arg(long, default_value = "public")]
+    source: import::ImportSource,
+  },
+
+  /// Set or clean an ACME DNS-01 challenge TXT record and wait until Route 53
+  /// has propagated the change, for use as a certbot or lego hook.
+  #[command(name = "dns-01")]
+  Dns01 {
+    /// Whether to set or clean the challenge record.
+    action: acme::Action,
+
+    /// Domain being validated, optionally followed by a zone choice. The
+    /// `_acme-challenge.` prefix is added if it is missing.
+    #[arg(value_name = "DOMAIN[=ZONE]", env = "CERTBOT_DOMAIN")]
+    domain: String,
+
+    /// Validation token to put in the challenge record.
+    #[arg(env = "CERTBOT_VALIDATION")]
+    value: String,
+  },
+
+  /// Create, verify, and delete a temporary TXT record in the hosted zone of
+  /// each domain to prove that updates will work.
+  SelfTest {
+    /// Domain names whose hosted zones to test, in addition to those of the
+    /// inventory.
+    #[arg(value_name = "DOMAIN[=ZONE]")]
+    domains: Vec<String>,
+  },
+
+  /// Pause updates of a domain in the running daemon at `--control-addr`
+  /// until it is resumed, such as while the name is migrated to another host.
+  Pause {
+    /// Domain name to pause.
+    domain: String,
+  },
+
+  /// Resume updates of a domain paused with `pause`.
+  Resume {
+    /// Domain name to resume.
+    domain: String,
+  },
+
+  /// Replace this binary with the latest release from GitHub after verifying
+  /// its checksum.
+  SelfUpdate {
+    /// Only print whether a newer release is available.
+    #[arg(long)]
+    check: bool,
+  },
 }
 
 struct App {
-  current_ip: String,
+  /// Route 53 clients with the credentials of each distinct identity that
+  /// domains are updated with, the first having the global credentials.
+  clients: Vec<route53::Client>,
+  /// Identities of the clients, in the same order.
+  identities: Vec<aws::Identity>,
+  /// Hosted zones listed with each client.
+  zone_caches: Vec<ZoneCache>,
+  /// Number of hosted zones whose changes are submitted at the same time.
+  zone_concurrency: usize,
+  /// Updater of the domains hosted by Cloudflare, if there are any.
+  #[cfg(feature = "cloudflare")]
+  cloudflare: Option<ddns_route53::Updater<provider::Cloudflare>>,
+  consul: Option<Consul>,
+  controls: Controls,
+  current_ip: Option<IpAddr>,
+  /// Public IPv6, if any record follows it.
+  current_ipv6: Option<IpAddr>,
+  /// Whether to delete the record of the other address family when updating
+  /// a domain.
+  delete_stale_family: bool,
+  /// Whether to delete the records of domains when the daemon is stopped.
+  delete_on_exit: bool,
+  /// IP the records are pointed at on exit instead of being deleted, if any.
+  exit_ip: Option<IpAddr>,
   domains: Vec<Domain>,
-  route53: route53::Client,
+  /// Statistics of domains in the state file that are no longer configured,
+  /// which are kept until they are pruned.
+  retired: BTreeMap<String, DomainStats>,
+  /// Whether to delete the records of retired domains at startup.
+  prune: bool,
+  /// Whether changes are printed instead of submitted.
+  dry_run: bool,
+  /// Policy for adjusting TTLs, if they are adjusted.
+  dynamic_ttl: Option<DynamicTtl>,
+  /// Server of Prometheus metrics, if enabled.
+  exporter: Option<Exporter>,
+  failure_hook: Option<FailureHook>,
+  /// Detectors of the public IP flapping, one for each family so that a
+  /// change of the other family does not hide it.
+  flaps: FlapDetector,
+  flaps_v6: FlapDetector,
+  /// Changed public IPs that have not been detected often enough yet.
+  confirmations: Confirmations,
+  /// Whether public IPs in special-purpose ranges are accepted.
+  allow_bogons: bool,
+  /// Length of the delegated IPv6 prefix that suffixes are combined with.
+  prefix_length: u8,
+  /// Network interface the delegated IPv6 prefix is read from, if not from
+  /// the public IPv6.
+  prefix_interface: Option<String>,
+  /// Shortest time between two changes of the records.
+  min_change_interval: TimeDelta,
+  /// Graphite server the metrics of each pass are sent to, if any.
+  graphite: Option<Graphite>,
+  /// Server of health checks and status, if enabled.
+  health: Option<Health>,
+  http: Http,
+  /// Network interface the public IP is read from, if any.
+  interface: Option<String>,
+  /// Whether to ask IP providers when the interface has no public IP.
+  interface_fallback: bool,
+  /// Backoff between retries of requests to IP providers.
+  ip_backoff: Backoff,
+  ip_retries: u32,
+  ip_timeout: Duration,
+  /// Number of consecutive failures to determine the public IP.
+  ip_failures: u64,
+  /// Whether the current pass failed in a way that retrying soon may fix.
+  retry_soon: bool,
+  /// Whether the last pass determined the public IP and updated every record
+  /// that needed it.
+  healthy: bool,
+  notifier: Notifier,
+  ip_changed_at: Option<DateTime<Utc>>,
+  ip_changes: u64,
+  last_write_at: Option<DateTime<Utc>>,
+  /// Duration of the leases records are registered with, if they are.
+  lease: Option<TimeDelta>,
+  /// Host name written to the metadata TXT records of domains, if they are
+  /// written.
+  metadata_host: Option<String>,
+  providers: Vec<Provider>,
+  /// Providers of the public IPv6 in dual-stack mode.
+  providers_v6: Vec<Provider>,
+  /// Pushgateway the metrics of each pass are pushed to, if any.
+  pushgateway: Option<Pushgateway>,
+  reaper: Option<Reaper>,
+  /// Public IP restored from the state file, until the first detection.
+  restored_ip: Option<IpAddr>,
+  /// Public IPv6 restored from the state file, until the first detection.
+  restored_ipv6: Option<IpAddr>,
+  /// Multivalue answer or weighted routing of the records, if enabled.
+  routing: Option<aws::Routing>,
+  started_at: DateTime<Utc>,
+  /// Totals at startup for the summary printed on exit.
+  session: Baseline,
+  state_file: Option<PathBuf>,
+  /// Service manager to report readiness and status to, when running as a
+  /// systemd service.
+  systemd: Option<Systemd>,
+  #[cfg(feature = "tray")]
+  tray: Option<tray::TrayIcon>,
+  /// TTL of records, unless TTLs are adjusted.
+  ttl: i64,
+  /// Time the next check for a newer release is due, if checks are enabled.
+  update_check_due: Option<Instant>,
+  /// Checks that updates resolve, if enabled.
+  verifier: Option<Verifier>,
+  /// Zabbix server the metrics of each pass are sent to, if any.
+  zabbix: Option<Zabbix>,
 }
 
 struct Domain {
-  current_ip: String,
-  name: String,
+  /// Index of the Route 53 client whose credentials update the domain.
+  account: usize,
+  current_ttl: i64,
+  /// TTL of the domain's records, overriding the global TTL.
+  ttl: Option<i64>,
+  /// Time after which the lease of the record should be renewed, if it has
+  /// one.
+  lease_renews_at: Option<DateTime<Utc>>,
+  name: DomainName,
+  /// Whether updates of the domain are paused through the control API.
+  paused: bool,
+  /// The record of the domain's IP, which is its A record if it follows the
+  /// public IP.
+  record: Record,
+  /// The AAAA record of a domain that follows the public IP in dual-stack
+  /// mode.
+  record_v6: Record,
+  /// Types of the records that follow the public IP, once decided. Without
+  /// a type in the config file, these are A records, and AAAA records in
+  /// dual-stack mode.
+  record_types: Option<RecordTypes>,
+  source: Source,
+  stats: DomainStats,
+  zone: ZoneChoice,
   zone_id: String,
+  /// AWS credentials that update the domain, if they differ from the global
+  /// ones.
+  identity: aws::Identity,
+  /// Provider that hosts the zone of the domain.
+  provider: provider::Kind,
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-  let args = Args::parse();
-  let mut app = App::new(args).await?;
+/// A record of a domain that the daemon writes.
+#[derive(Default)]
+struct Record {
+  /// IP the record was last written with.
+  ip: Option<IpAddr>,
+  /// Change of the record that failed to apply and is retried.
+  pending: Option<PendingChange>,
+}
 
-  loop {
-    if let Err(err) = app
-      .refresh_public_ip()
-      .await
-      .with_context(|| "Failed to determine public IP.")
-    {
-      log_err!("{err:?}");
-      continue;
-    }
+/// Changes planned for the records of a domain in an update pass.
+struct Plan {
+  /// Records to write, by slot, with the IPs to write.
+  writes: Vec<(usize, IpAddr)>,
+  /// TTL the records are written with.
+  ttl: i64,
+  /// Record of the other address family that is deleted, if any.
+  stale: Option<ResourceRecordSet>,
+  changes: Vec<Change>,
+}
 
-    app.update_dns().await;
+/// Changes of the domains in a hosted zone, submitted as one batch.
+struct Batch {
+  zone_id: String,
+  /// Time the changes were planned at.
+  now: DateTime<Utc>,
+  /// Changes of each domain, by index, for the domains they could be planned
+  /// for.
+  plans: Vec<(usize, Plan)>,
+  /// Domains whose changes could not be planned, by index.
+  failures: Vec<(usize, anyhow::Error)>,
+  /// Result of submitting the changes, unless they were not submitted.
+  submitted: Option<Result<aws::Submitted>>,
+}
 
-    tokio::time::sleep(Duration::from_secs(300)).await;
-  }
+/// Public IPs of this machine, by address family.
+#[derive(Clone, Copy)]
+struct PublicIps {
+  v4: Option<IpAddr>,
+  v6: Option<IpAddr>,
 }
 
-impl App {
-  async fn new(args: Args) -> Result<Self> {
-    let mut domains = Vec::with_capacity(args.domains.len());
+impl Args {
+  /// Adds the domain names given to the `run` or `update` subcommand, which
+  /// need domains to update like the program without a subcommand.
+  fn require_domains(&mut self, domains: Vec<String>) -> Result<()> {
+    self.domains.extend(domains);
 
-    for name in args.domains {
-      if name.len() < 3 || !name.contains('.') {
-        bail!("Invalid domain name {name:?}.");
-      }
+    let given = !self.domains.is_empty() || self.use_hostname;
 
-      domains.push(Domain::new(name));
+    if !given && self.config.is_none() && self.inventory.is_none() {
+      bail!("No domains to update. Give domain names, `--config`, or `--inventory`.");
     }
 
-    let aws_config = aws_config::load_from_env().await;
-    let route53 = route53::Client::new(&aws_config);
+    Ok(())
+  }
 
-    Ok(Self {
-      domains,
-      current_ip: String::new(),
-      route53,
-    })
+  /// Loads the domains that the `status`, `check`, `self-test`, or `export`
+  /// subcommand looks up, failing if there are none.
+  fn load_domains(&mut self, domains: Vec<String>) -> Result<Vec<Domain>> {
+    let domains = self.configured_domains(domains)?;
+
+    if domains.is_empty() {
+      bail!("No domains to look up. Give domain names, `--config`, or `--inventory`.");
+    }
+
+    Ok(domains)
   }
 
-  async fn refresh_public_ip(&mut self) -> Result<()> {
-    let mut ip = reqwest::get("https://api.ipify.org").await?.text().await?;
+  /// Loads the domain names given to a subcommand or to the program, then
+  /// those of the config file and inventory.
+  fn configured_domains(&mut self, domains: Vec<String>) -> Result<Vec<Domain>> {
+    self.domains.extend(domains);
 
-    ip.truncate(16);
-    ip = ip.trim().to_string();
-    ip.parse::<Ipv4Addr>()?;
+    if let (true, Some(zone)) = (self.use_hostname, &self.zone) {
+      let domain = hostname_domain(&self.hostname_template, zone)?;
 
-    if ip != self.current_ip {
-      if self.current_ip.is_empty() {
-        log!("Public IP is {ip}.");
-      } else {
-        log!("Public IP has changed to {ip}.");
-      }
+      self.domains.push(domain);
+    }
 
-      self.current_ip = ip;
+    load_domains(
+      mem::take(&mut self.domains),
+      &self.config_domains,
+      self.inventory.as_deref(),
+    )
+  }
+
+  /// Returns the types of the records that follow the public IP of domains
+  /// without a type in the config file.
+  fn record_types(&self) -> RecordTypes {
+    match (self.dual_stack, self.ipv6_only) {
+      (true, _) => RecordTypes::Both,
+      (_, true) => RecordTypes::Aaaa,
+      _ => RecordTypes::A,
     }
+  }
+}
 
-    Ok(())
+/// Returns the providers the public IP of a family is asked for, in order.
+/// Connections are restricted to one family, so custom services are asked for
+/// both.
+fn ip_providers(ip_url: &[Url], source: ip::Source, family: Family) -> Vec<Provider> {
+  let custom = ip_url.iter().map(|url| Provider::new(url.to_string()));
+
+  let builtin = match (source, family) {
+    (ip::Source::Dns, _) => Provider::dns(family),
+    (ip::Source::Http, Family::V4) => Provider::builtin(ip::PROVIDERS_V4),
+    (ip::Source::Http, Family::V6) => Provider::builtin(ip::PROVIDERS_V6),
+  };
+
+  custom.chain(builtin).collect()
+}
+
+/// Returns the command line interface, in which each option without an
+/// environment variable of its own can also be set with one named after it,
+/// such as `DDNS_INTERVAL` for `--interval`, and the domain names with a
+/// comma-separated `DDNS_DOMAINS`. Variables of the options of subcommands
+/// are named after the subcommand as well, such as `DDNS_STATUS_FORMAT` for
+/// `status --format`.
+fn command() -> clap::Command {
+  let mut command = with_env(Args::command(), "DDNS_");
+  let names: Vec<String> = command
+    .get_subcommands()
+    .map(|sub| sub.get_name().to_string())
+    .collect();
+
+  for name in names {
+    let prefix = format!("DDNS_{}_", env_name(&name));
+
+    command = command.mut_subcommand(name, |sub| with_env(sub, &prefix));
   }
 
-  async fn update_dns(&mut self) {
-    if !self.domains.iter().any(|d| d.current_ip != self.current_ip) {
-      return;
+  command
+}
+
+/// Lets the options of a command be set with environment variables named
+/// after them with a prefix.
+fn with_env(command: clap::Command, prefix: &str) -> clap::Command {
+  command.mut_args(|arg| {
+    if arg.get_env().is_some() || matches!(arg.get_action(), ArgAction::Help | ArgAction::Version) {
+      return arg;
     }
 
-    // get list of hosted zones
+    let name = arg.get_long().unwrap_or(arg.get_id().as_str());
+    let var = format!("{prefix}{}", env_name(name));
 
-    let zones = match self
-      .route53
-      .list_hosted_zones()
-      .send()
-      .await
-      .with_context(|| "Failed to list Route 53 hosted zones.")
+    // values such as tokens and webhook URLs are secrets, so the help only
+    // names the variables
+    let arg = arg.hide_env_values(true);
+
+    match (arg.get_id() == "domains", arg.get_action()) {
+      (true, _) => arg.env(var).value_delimiter(','),
+      // flags are also set by values such as `1` or `yes`
+      (false, ArgAction::SetTrue) => arg.env(var).value_parser(BoolishValueParser::new()),
+      (false, _) => arg.env(var),
+    }
+  })
+}
+
+/// Returns the name of an option or subcommand as it appears in the names of
+/// environment variables.
+fn env_name(name: &str) -> String {
+  name.to_uppercase().replace('-', "_")
+}
+
+/// Parses the command line arguments, along with the environment and the
+/// config file if one is given.
+fn parse_args() -> Result<Args> {
+  let matches = command().get_matches();
+  let args = Args::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+
+  let Some(path) = &args.config else {
+    return Ok(args);
+  };
+
+  let config = config::load(path, &command(), &matches)?;
+
+  // settings from the file come first so that later arguments override them
+  let mut argv: Vec<_> = env::args_os().collect();
+
+  argv.splice(1..1, config.args);
+
+  let matches = command().get_matches_from(argv);
+  let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+
+  args.config_domains = config.domains;
+
+  Ok(args)
+}
+
+/// Parses the domain names given on the command line, in the config file, and
+/// in the inventory.
+fn load_domains(
+  args: Vec<String>,
+  config: &[DomainConfig],
+  inventory: Option<&Path>,
+) -> Result<Vec<Domain>> {
+  let mut domains = Vec::with_capacity(args.len() + config.len());
+
+  for arg in args {
+    domains.push(Domain::parse(arg)?);
+  }
+
+  for entry in config {
+    // domains given on the command line replace those in the file
+    if !entry.enabled
+      || domains
+        .iter()
+        .any(|d| d.name.eq_ignore_ascii_case(&entry.name))
     {
-      Ok(list) => list.hosted_zones,
+      continue;
+    }
 
-      Err(err) => {
-        log_err!("{err:?}");
-        return;
-      }
+    // private addresses are only reachable through private hosted zones,
+    // unless another zone is chosen
+    let arg = match (&entry.zone, entry.source) {
+      (Some(zone), _) => format!("{}={zone}", entry.name),
+      (None, Some(_)) => format!("{}=private", entry.name),
+      (None, None) => entry.name.clone(),
     };
 
-    // match domain names to hosted zones
+    let mut domain = Domain::parse(arg)?;
 
-    for domain in &mut self.domains {
-      if domain.current_ip == self.current_ip {
-        continue;
+    if let Some(ttl) = entry.ttl {
+      let secs = ttl.as_secs();
+
+      if secs == 0 || secs > i32::MAX as u64 {
+        bail!(
+          "Invalid TTL of `{}`. Expected between 1s and {}s.",
+          entry.name,
+          i32::MAX
+        );
       }
 
-      let Some(zone) = zones
-        .iter()
-        // find hosted zones that could contain this domain name
-        .filter(
-          |z| match domain.name.strip_suffix(z.name.trim_end_matches('.')) {
-            Some(rest) => rest.is_empty() || rest.ends_with('.'),
-            None => false,
-          },
-        )
-        // pick the hosted zone with the deepest subdomain match
-        .max_by_key(|zone| zone.name.len())
-      else {
-        log_err!("Cannot find a hosted zone for `{}`.", domain.name);
-        continue;
-      };
+      domain.ttl = Some(secs as i64);
+    }
 
-      domain.zone_id.replace_range(.., &zone.id);
+    domain.record_types = entry.record_types;
+
+    match (entry.source, entry.suffix) {
+      (Some(_), Some(_)) => bail!("`{}` has both a source and a suffix.", entry.name),
+      (Some(source), None) => domain.source = Source::Private { source, ip: None },
+      (None, Some(suffix)) => domain.source = Source::Prefix { suffix, ip: None },
+      (None, None) => {}
     }
 
-    // update DNS records
+    if let Source::Prefix { .. } = domain.source {
+      domain.record_types = Some(RecordTypes::Aaaa);
+    }
 
-    for domain in &mut self.domains {
-      if domain.zone_id.is_empty() || domain.current_ip == self.current_ip {
-        continue;
+    domain.identity = aws::Identity {
+      profile: entry.profile.clone(),
+      role_arn: entry.role_arn.clone(),
+    };
+
+    // a zone ID of another provider is not a hosted zone to update
+    if entry.provider != provider::Kind::Route53 {
+      domain.provider = entry.provider;
+      domain.zone_id.clear();
+    }
+
+    domains.push(domain);
+  }
+
+  if let Some(path) = inventory {
+    for (arg, source) in inventory::load(path)? {
+      let mut domain = Domain::parse(arg)?;
+
+      domain.source = source.into();
+
+      // only AAAA records are addressed by a suffix
+      if let Source::Prefix { .. } = domain.source {
+        domain.record_types = Some(RecordTypes::Aaaa);
       }
 
-      match upsert(
-        &self.route53,
-        &domain.zone_id,
-        &domain.name,
-        &self.current_ip,
-      )
-      .await
-      .with_context(|| format!("Failed to update `{}`.", domain.name))
-      {
-        Ok(()) => {
-          domain.current_ip.replace_range(.., &self.current_ip);
-          log!("Updated `{}` to {}.", domain.name, self.current_ip);
-        }
+      domains.push(domain);
+    }
+  }
 
-        Err(err) => {
-          log_err!("{err:?}");
-        }
-      }
-    }
-
-    async fn upsert(route53: &route53::Client, zone_id: &str, name: &str, ip: &str) -> Result<()> {
-      route53
-        .change_resource_record_sets()
-        .hosted_zone_id(zone_id)
-        .change_batch(
-          ChangeBatch::builder()
-            .changes(
-              Change::builder()
-                .action(Upsert)
-                .resource_record_set(
-                  ResourceRecordSet::builder()
-                    .r#type(RrType::A)
-                    .name(name)
-                    .resource_records(ResourceRecord::builder().value(ip).build()?)
-                    .ttl(300)
-                    .build()?,
-                )
-                .build()?,
-            )
-            .build()?,
-        )
-        .send()
-        .await?;
+  for (i, domain) in domains.iter().enumerate() {
+    if domains[..i].iter().any(|d| d.name == domain.name) {
+      bail!("Domain `{}` is listed more than once.", domain.name);
+    }
+  }
+
+  Ok(domains)
+}
+
+/// Returns the identity that the records of a domain that is no longer
+/// configured were written with.
+fn retired_identity(stats: &DomainStats) -> aws::Identity {
+  aws::Identity {
+    profile: stats.profile.clone(),
+    role_arn: stats.role_arn.clone(),
+  }
+}
+
+/// Builds the changes that delete the records of the given types of a name,
+/// along with its lease and metadata records, except that a record of the
+/// family of `exit_ip` is pointed at it instead.
+async fn removal(
+  route53: &route53::Client,
+  zone_id: &str,
+  name: &str,
+  types: &[RrType],
+  exit_ip: Option<IpAddr>,
+  routing: Option<&aws::Routing>,
+) -> Result<Vec<Change>> {
+  let mut changes = Vec::new();
+
+  for rr_type in types {
+    let Some(set) = aws::find_record(route53, zone_id, name, rr_type.clone(), routing).await?
+    else {
+      continue;
+    };
 
-      Ok(())
+    let points = match exit_ip {
+      Some(IpAddr::V4(_)) => *rr_type == RrType::A,
+      Some(IpAddr::V6(_)) => *rr_type == RrType::Aaaa,
+      None => false,
+    };
+
+    match (points, exit_ip) {
+      (true, Some(ip)) => changes.push(aws::upsert(name, ip, set.ttl().unwrap_or(60), routing)?),
+      _ => changes.push(aws::delete(set)?),
+    }
+  }
+
+  let txt = [
+    (lease::record_name(name), None),
+    (metadata::record_name(name), routing),
+  ];
+
+  for (txt_name, routing) in txt {
+    if let Some(set) = aws::find_record(route53, zone_id, &txt_name, RrType::Txt, routing).await? {
+      changes.push(aws::delete(set)?);
     }
   }
+
+  Ok(changes)
 }
 
-impl Domain {
-  const fn new(name: String) -> Self {
-    Self {
-      name,
-      zone_id: String::new(),
-      current_ip: String::new(),
+/// Derives the domain name of this machine from its host name, formatted with
+/// a template and followed by the domain given with `--zone`.
+fn hostname_domain(template: &str, zone: &str) -> Result<String> {
+  let host = host::name()?;
+
+  Ok(format!("{}.{zone}", template.replace("{host}", &host)))
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+  let mut args = parse_args()?;
+
+  args.aws.http_proxy.clone_from(&args.http.http_proxy);
+
+  trace::init(args.log_level, args.log_format, args.log_target)?;
+
+  match args.command.take() {
+    Some(Command::Run { domains }) => args.require_domains(domains)?,
+    Some(Command::Update { domains }) => {
+      args.require_domains(domains)?;
+      args.once = true;
+    }
+
+    Some(Command::Status {
+      domains,
+      live,
+      format,
+    }) => {
+      if !live && domains.is_empty() && args.state_file.is_some() {
+        return print_status(args.state_file.as_deref());
+      }
+
+      let domains = args.load_domains(domains)?;
+
+      if !live::run(&args, &domains, format).await? {
+        process::exit(1);
+      }
+
+      return Ok(());
+    }
+
+    Some(Command::Check {
+      setup: true,
+      domains,
+      format,
+      ..
+    }) => {
+      let domains = args.load_domains(domains);
+
+      process::exit(check::setup(domains, format, &args.aws).await);
+    }
+
+    Some(Command::Check {
+      format,
+      warning,
+      critical,
+      ..
+    }) => {
+      let state_file = args.state_file.as_deref();
+
+      process::exit(check::run(state_file, format, warning, critical));
+    }
+
+    Some(Command::Explain { domain }) => {
+      let domain = Domain::parse(domain)?;
+
+      // a configured domain is looked up with its own credentials
+      let identity = args
+        .configured_domains(Vec::new())?
+        .into_iter()
+        .find(|d| d.name.eq_ignore_ascii_case(&domain.name))
+        .map(|d| d.identity)
+        .unwrap_or_default();
+
+      return explain(domain, &identity, &args.aws).await;
+    }
+    Some(Command::Zones { domains }) => {
+      let domains = args.configured_domains(domains)?;
+
+      return print_zones(&domains, &args.aws).await;
+    }
+    Some(Command::Export { domains, format }) => {
+      let domains = args.load_domains(domains)?;
+
+      return export::run(&domains, format, &args.aws).await;
     }
+
+    Some(Command::Import {
+      file,
+      format,
+      origin,
+      source,
+    }) => {
+      return import::run(
+        &file,
+        format,
+        origin.as_deref(),
+        source,
+        args.inventory.as_deref(),
+      )
+    }
+
+    Some(Command::Dns01 {
+      action,
+      domain,
+      value,
+    }) => return acme::run(action, domain, &value, &args.aws).await,
+    Some(Command::SelfTest { domains }) => {
+      let domains = args.load_domains(domains)?;
+
+      return selftest::run(&domains, &args.aws).await;
+    }
+
+    Some(Command::Pause { domain }) => {
+      return api::set_paused(args.control_addr, &domain, true).await
+    }
+    Some(Command::Resume { domain }) => {
+      return api::set_paused(args.control_addr, &domain, false).await
+    }
+
+    Some(Command::SelfUpdate { check }) => return update::run(check, &args.http).await,
+    None => {}
+  }
+
+  info!("Starting ddns-route53 {VERSION}.");
+
+  let network_wait = args.network_wait;
+  let interval = args.interval;
+  let max_backoff = args.aws.max_backoff;
+  let once = args.once;
+  let dry_run = args.dry_run;
+  let mut shutdown = Shutdown::listen()?;
+  let mut app = App::new(args).await?;
+
+  signal::refresh_on_signal(app.controls.clone())?;
+
+  // the network may never come up, so exiting cannot wait for it
+  let mut detected_at_startup = tokio::select! {
+    detected = app.wait_for_network(network_wait) => detected,
+
+    _ = shutdown.requested() => {
+      app.stop().await;
+      return Ok(());
+    }
+  };
+
+  app.seed_records().await;
+  app.prune_retired().await;
+
+  // failed passes are retried sooner than the interval while the failures are
+  // transient
+  let backoff = Backoff {
+    base: Duration::from_secs(10),
+    max: max_backoff.min(interval),
+  };
+
+  let mut retries = 0;
+  let mut failed = false;
+
+  loop {
+    let failures = app.update_failures();
+
+    app.retry_soon = false;
+
+    // the first pass uses the IP detected while waiting for the network, so
+    // that it is not counted twice towards `--confirmations`
+    let detected = match mem::take(&mut detected_at_startup) {
+      true => Ok(()),
+
+      false => app
+        .refresh_public_ip()
+        .await
+        .with_context(|| "Failed to determine public IP."),
+    };
+
+    match &detected {
+      Ok(()) => app.ip_failures = 0,
+
+      Err(err) => {
+        // changes queued while Route 53 was unreachable can still be applied
+        error!("{err:?}");
+        app.ip_failures += 1;
+        app.retry_soon = true;
+
+        if let Some(hook) = &app.failure_hook {
+          hook.observe(Failure {
+            kind: "ip",
+            domain: None,
+            count: app.ip_failures,
+            error: err,
+          });
+        }
+      }
+    }
+
+    app.refresh_hosts().await;
+    app.refresh_private_ips().await;
+    app.refresh_prefixes();
+    app.apply_paused_domains();
+
+    if dry_run {
+      if !app.is_held_down() {
+        app.update_dns().await;
+      }
+
+      failed = detected.is_err() || app.update_failures() > failures;
+      break;
+    }
+
+    if !app.controls.is_paused() {
+      app.reap_hosts().await;
+      app.expire_leases().await;
+    }
+
+    if !app.is_held_down() && !app.controls.is_paused() {
+      app.update_dns().await;
+      app.register_with_consul().await;
+    }
+
+    app.report_unverified();
+    app.healthy = detected.is_ok() && app.update_failures() == failures;
+    app.save_state();
+    app.publish_status();
+    app.notify_systemd();
+    app.report_metrics().await;
+    app.notifier.send_digests();
+    app.check_for_updates();
+
+    #[cfg(feature = "tray")]
+    if let Some(tray) = &app.tray {
+      let healthy = detected.is_ok() && app.domains.iter().all(|d| d.stats.last_error.is_none());
+
+      tray.show(healthy, app.current_ip).await;
+    }
+
+    if once {
+      failed = detected.is_err() || app.update_failures() > failures;
+      break;
+    }
+
+    let delay = match app.retry_soon {
+      true => {
+        let delay = backoff.delay(retries);
+
+        retries += 1;
+        info!("Retrying in {}s.", delay.as_secs());
+        delay
+      }
+
+      false => {
+        retries = 0;
+        interval
+      }
+    };
+
+    let sleep = async {
+      tokio::select! {
+        _ = app.controls.sleep(delay) => false,
+        _ = shutdown.requested() => true,
+      }
+    };
+
+    let stop = match &app.systemd {
+      Some(systemd) => systemd.pinging(sleep).await,
+      None => sleep.await,
+    };
+
+    if stop {
+      app.remove_records().await;
+      break;
+    }
+  }
+
+  // a single pass waits for its updates to be verified, since nothing would
+  // report a failure after it exits
+  if once {
+    failed |= app.finish_verifying().await;
+  }
+
+  app.stop().await;
+
+  if failed {
+    process::exit(1);
+  }
+
+  Ok(())
+}
+
+impl App {
+  async fn new(mut args: Args) -> Result<Self> {
+    let started_at = Utc::now();
+
+    if let (true, Some(zone)) = (args.use_hostname, &args.zone) {
+      let domain = hostname_domain(&args.hostname_template, zone)?;
+
+      info!("Updating `{domain}` for the host name of this machine.");
+      args.domains.push(domain);
+    }
+
+    let record_types = args.record_types();
+    let mut domains = load_domains(
+      args.domains,
+      &args.config_domains,
+      args.inventory.as_deref(),
+    )?;
+
+    // restore statistics from a previous run
+
+    let mut state = match &args.state_file {
+      Some(path) if path.exists() => State::load(path)?,
+      _ => State::default(),
+    };
+
+    if args.reset_stats {
+      state = State {
+        pending: state.pending,
+        pending_v6: state.pending_v6,
+        paused: state.paused,
+        ..State::default()
+      };
+    }
+
+    for domain in &mut domains {
+      domain.record_types.get_or_insert(record_types);
+
+      if let Some(stats) = state.domains.get(domain.name.as_str()) {
+        domain.stats.clone_from(stats);
+      }
+
+      domain.record.pending = state.pending.get(domain.name.as_str()).cloned();
+      domain.record_v6.pending = state.pending_v6.get(domain.name.as_str()).cloned();
+    }
+
+    let mut retired = mem::take(&mut state.domains);
+
+    retired.retain(|name, _| !domains.iter().any(|d| d.name.as_str() == name));
+
+    let flap_window = TimeDelta::from_std(args.flap_window).context("Invalid flap window.")?;
+    let hold_down = TimeDelta::from_std(args.hold_down).context("Invalid hold-down duration.")?;
+    let mut flaps = FlapDetector::new(args.flap_threshold, flap_window, hold_down);
+    let mut flaps_v6 = FlapDetector::new(args.flap_threshold, flap_window, hold_down);
+
+    // a hold-down survives restarts until it expires; the state does not
+    // record which family was flapping, so hold both
+    flaps.held_until = state.held_until.filter(|&until| until > Utc::now());
+    flaps_v6.held_until = flaps.held_until;
+
+    if let Some(until) = flaps.held_until {
+      info!(
+        "Public IP was flapping. Holding updates until {}.",
+        until.format("%F %T")
+      );
+    }
+
+    let mut providers = ip_providers(&args.ip_url, args.ip_source, Family::V4);
+    let mut providers_v6 = ip_providers(&args.ip_url, args.ip_source, Family::V6);
+
+    for provider in providers.iter_mut().chain(&mut providers_v6) {
+      provider.garbage = state.garbage.get(&provider.url).copied().unwrap_or(0);
+    }
+
+    let http = Http::with_options(&args.http)?;
+    let notifier = match args.dry_run {
+      true => Notifier::silent(&http),
+      false => Notifier::new(args.notify, &http)?,
+    };
+
+    let dynamic_ttl = match args.dynamic_ttl {
+      true => {
+        let (min, max) = (args.min_ttl.as_secs(), args.max_ttl.as_secs());
+
+        if min == 0 || min > max || max > i32::MAX as u64 {
+          bail!("Invalid TTL bounds. Expected `--min-ttl` between 1s and `--max-ttl`.");
+        }
+
+        Some(DynamicTtl::new(min as i64, max as i64))
+      }
+
+      false => None,
+    };
+
+    if args.interval.is_zero() {
+      bail!("Invalid interval. Expected at least 1s.");
+    }
+
+    let ttl = args.ttl.as_secs();
+
+    if ttl == 0 || ttl > i32::MAX as u64 {
+      bail!("Invalid TTL. Expected between 1s and {}s.", i32::MAX);
+    }
+
+    let ttl = ttl as i64;
+
+    let lease = match args.lease {
+      Some(lease) => Some(TimeDelta::from_std(lease).context("Invalid lease duration.")?),
+      None => None,
+    };
+
+    let metadata_host = match args.write_txt_metadata {
+      true => Some(host::name()?),
+      false => None,
+    };
+
+    let reaper = match args.reap_after {
+      Some(after) => Some(Reaper {
+        after: TimeDelta::from_std(after).context("Invalid reap period.")?,
+        action: args.reap_action,
+      }),
+
+      None => None,
+    };
+
+    let failure_hook = args.on_failure.filter(|_| !args.dry_run);
+    let failure_hook = failure_hook.map(|command| FailureHook {
+      command,
+      threshold: args.failure_threshold,
+    });
+
+    let controls = Controls::default();
+
+    for name in &state.paused {
+      if domains.iter().any(|d| d.name.as_str() == name) {
+        controls.set_domain_paused(name, true);
+      }
+    }
+
+    if let Some(addr) = args.control_addr {
+      let names = domains.iter().map(|d| d.name.to_string()).collect();
+
+      api::listen(addr, controls.clone(), names).await?;
+    }
+
+    let exporter = match args.metrics_addr {
+      Some(addr) => Some(Exporter::listen(addr).await?),
+      None => None,
+    };
+
+    let health = Health::listen(args.health, args.interval).await?;
+
+    #[cfg(not(feature = "tray"))]
+    if args.tray {
+      bail!("This build does not support the tray icon. Rebuild it with `--features tray`.");
+    }
+
+    #[cfg(feature = "tray")]
+    let tray = match args.tray {
+      true => Some(tray::TrayIcon::spawn(controls.clone()).await?),
+      false => None,
+    };
+
+    let on_cloudflare = domains
+      .iter()
+      .any(|d| d.provider == provider::Kind::Cloudflare);
+
+    #[cfg(not(feature = "cloudflare"))]
+    if on_cloudflare {
+      bail!("This build does not support Cloudflare. Rebuild it with `--features cloudflare`.");
+    }
+
+    #[cfg(feature = "cloudflare")]
+    let cloudflare = match (on_cloudflare, args.cloudflare_token) {
+      (false, _) => None,
+      (true, None) => bail!("Domains hosted by Cloudflare need `--cloudflare-token`."),
+
+      (true, Some(token)) => Some(ddns_route53::Updater::new(provider::Cloudflare::new(
+        &http, token,
+      ))),
+    };
+
+    // domains with the same profile and role share a client
+    let mut identities = vec![aws::Identity::default()];
+
+    for domain in &mut domains {
+      domain.account = match identities.iter().position(|i| *i == domain.identity) {
+        Some(account) => account,
+
+        None => {
+          identities.push(domain.identity.clone());
+          identities.len() - 1
+        }
+      };
+    }
+
+    // retired domains are pruned with the credentials that wrote them
+    if args.prune {
+      for identity in retired.values().map(retired_identity) {
+        if !identities.contains(&identity) {
+          identities.push(identity);
+        }
+      }
+    }
+
+    let mut clients = Vec::with_capacity(identities.len());
+
+    for identity in &identities {
+      clients.push(aws::connect_as(&args.aws, identity).await);
+    }
+
+    pacer::restore_throttled_count(state.throttled);
+
+    let session = Baseline::new(started_at, state.ip_changes, &domains);
+    let verifier = Verifier::new(args.verify, &http);
+    let pushgateway = Pushgateway::new(args.pushgateway, &http)?;
+
+    Ok(Self {
+      zone_caches: clients
+        .iter()
+        .map(|_| ZoneCache::new(args.zone_cache_ttl))
+        .collect(),
+      zone_concurrency: args.zone_concurrency as usize,
+      clients,
+      identities,
+      #[cfg(feature = "cloudflare")]
+      cloudflare,
+      consul: Consul::new(args.consul, &http),
+      controls,
+      domains,
+      current_ip: None,
+      current_ipv6: None,
+      delete_stale_family: args.delete_stale_family,
+      delete_on_exit: args.delete_on_exit,
+      exit_ip: args.exit_ip,
+      retired,
+      prune: args.prune,
+      dry_run: args.dry_run,
+      dynamic_ttl,
+      exporter,
+      failure_hook,
+      flaps,
+      flaps_v6,
+      confirmations: Confirmations::new(args.confirmations),
+      allow_bogons: args.allow_bogons,
+      prefix_length: args.prefix_length,
+      prefix_interface: args.prefix_interface,
+      min_change_interval: TimeDelta::from_std(args.min_change_interval)
+        .context("Invalid minimum change interval.")?,
+      graphite: Graphite::new(args.graphite),
+      health,
+      http,
+      interface: args.interface,
+      interface_fallback: args.interface_fallback,
+      ip_backoff: Backoff {
+        base: Duration::from_secs(1),
+        max: args.aws.max_backoff,
+      },
+      ip_retries: args.ip_retries,
+      ip_timeout: args.ip_timeout,
+      ip_failures: 0,
+      retry_soon: false,
+      healthy: false,
+      notifier,
+      ip_changed_at: state.ip_changed_at,
+      ip_changes: state.ip_changes,
+      last_write_at: state.last_write_at,
+      lease,
+      metadata_host,
+      providers,
+      providers_v6,
+      pushgateway,
+      reaper,
+      restored_ip: state.public_ip,
+      restored_ipv6: state.public_ipv6,
+      routing: args.set_identifier.map(|set_identifier| aws::Routing {
+        set_identifier,
+        health_check_id: args.health_check_id,
+        weight: args.weight,
+      }),
+      session,
+      started_at,
+      state_file: args.state_file,
+      systemd: Systemd::from_env(),
+      #[cfg(feature = "tray")]
+      tray,
+      ttl,
+      update_check_due: args.check_updates.then(Instant::now),
+      verifier,
+      zabbix: Zabbix::new(args.zabbix),
+    })
+  }
+
+  /// Returns the metrics of the pass that just completed.
+  fn snapshot(&self) -> Snapshot {
+    Snapshot {
+      time: Utc::now(),
+      ip_changes: self.ip_changes,
+      ip_failures: self.ip_failures,
+      throttled: pacer::throttled_count(),
+      route53_errors: pacer::failed_count(),
+      healthy: self.healthy,
+      domains: self
+        .domains
+        .iter()
+        .map(|d| DomainMetrics {
+          name: d.name.to_string(),
+          updates: d.stats.updates,
+          failures: d.stats.failures,
+          consecutive_failures: d.stats.consecutive_failures,
+          last_change: d.stats.last_change,
+          paused: d.paused,
+        })
+        .collect(),
+    }
+  }
+
+  /// Reports the outcome of the pass to systemd, if running as a service:
+  /// readiness after the first healthy pass, the public IP as the status, and
+  /// a ping of the watchdog.
+  fn notify_systemd(&mut self) {
+    let Some(systemd) = &mut self.systemd else {
+      return;
+    };
+
+    if self.healthy {
+      systemd.ready();
+    }
+
+    let ip = match (self.current_ip, self.current_ipv6) {
+      (Some(v4), Some(v6)) => format!("Public IP is {v4} and {v6}"),
+      (Some(ip), None) | (None, Some(ip)) => format!("Public IP is {ip}"),
+      (None, None) => "Public IP is unknown".into(),
+    };
+
+    let failing = self
+      .domains
+      .iter()
+      .filter(|d| d.stats.last_error.is_some())
+      .count();
+
+    let status = match failing {
+      0 => format!("{ip}. Every domain is up to date."),
+      n => format!("{ip}. {n} of {} domains are failing.", self.domains.len()),
+    };
+
+    systemd.status(&status);
+    systemd.ping();
+  }
+
+  /// Publishes the status of the pass to health checks, if they are served.
+  fn publish_status(&self) {
+    let Some(health) = &self.health else {
+      return;
+    };
+
+    health.publish(Status {
+      time: Utc::now(),
+      healthy: self.healthy,
+      last_success: None,
+      public_ip: self.current_ip,
+      public_ipv6: self.current_ipv6,
+      domains: self
+        .domains
+        .iter()
+        .map(|d| DomainStatus {
+          name: d.name.to_string(),
+          zone_id: Some(d.zone_id.clone()).filter(|id| !id.is_empty()),
+          records: [d.record.ip, d.record_v6.ip]
+            .into_iter()
+            .flatten()
+            .collect(),
+          ttl: d.current_ttl,
+          pending: d.record.pending.is_some() || d.record_v6.pending.is_some(),
+          paused: d.paused,
+          last_change: d.stats.last_change,
+          last_error: d.stats.last_error.clone(),
+        })
+        .collect(),
+    });
+  }
+
+  /// Reports the metrics of the pass to the monitoring systems that are
+  /// configured.
+  async fn report_metrics(&self) {
+    if self.exporter.is_none()
+      && self.pushgateway.is_none()
+      && self.graphite.is_none()
+      && self.zabbix.is_none()
+    {
+      return;
+    }
+
+    let snapshot = self.snapshot();
+
+    if let Some(exporter) = &self.exporter {
+      exporter.publish(&snapshot);
+    }
+
+    if let Some(pushgateway) = &self.pushgateway {
+      if let Err(err) = pushgateway.push(&snapshot).await {
+        error!("{err:?}");
+      }
+    }
+
+    if let Some(graphite) = &self.graphite {
+      if let Err(err) = graphite.send(&snapshot).await {
+        error!("{err:?}");
+      }
+    }
+
+    if let Some(zabbix) = &self.zabbix {
+      if let Err(err) = zabbix.send(&snapshot).await {
+        error!("{err:?}");
+      }
+    }
+  }
+
+  /// Applies pauses and resumptions of domains requested through the control
+  /// API since the last pass.
+  fn apply_paused_domains(&mut self) {
+    for domain in &mut self.domains {
+      domain.paused = self.controls.is_domain_paused(&domain.name);
+    }
+  }
+
+  /// Checks for a newer release in the background if a check is due.
+  fn check_for_updates(&mut self) {
+    const INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+    let now = Instant::now();
+
+    if self.update_check_due.is_none_or(|due| due > now) {
+      return;
+    }
+
+    self.update_check_due = Some(now + INTERVAL);
+
+    let http = self.http.clone();
+
+    tokio::spawn(async move { update::check(&http).await });
+  }
+
+  /// Notifies of the updates that failed verification since the last pass.
+  fn report_unverified(&mut self) {
+    let Some(verifier) = &mut self.verifier else {
+      return;
+    };
+
+    for Unverified { domain, ip, error } in verifier.failures() {
+      self
+        .notifier
+        .notify(Event::Unverified { domain, ip, error });
+    }
+  }
+
+  /// Waits for updates being verified and notifies of those that fail,
+  /// returning `true` if any do.
+  async fn finish_verifying(&mut self) -> bool {
+    let Some(verifier) = &mut self.verifier else {
+      return false;
+    };
+
+    let failures = verifier.finish().await;
+    let failed = !failures.is_empty();
+
+    for Unverified { domain, ip, error } in failures {
+      self
+        .notifier
+        .notify(Event::Unverified { domain, ip, error });
+    }
+
+    failed
+  }
+
+  /// Prints a summary of the session, and sends it to notification channels
+  /// if enabled, before exiting.
+  async fn stop(&mut self) {
+    let summary = self.session.report(self.ip_changes, &self.domains);
+
+    if let Some(systemd) = &self.systemd {
+      systemd.stopping();
+    }
+
+    info!("Exiting. Session summary:\n{summary}");
+
+    if self.notifier.sends_summary() {
+      self.notifier.notify(Event::Stopped { summary });
+    }
+
+    self.notifier.flush(Duration::from_secs(10)).await;
+  }
+
+  async fn refresh_public_ip(&mut self) -> Result<()> {
+    let mut result = Ok(());
+
+    // an instance that only manages other hosts does not need its own IP,
+    // unless the delegated prefix is taken from its IPv6
+    let prefixed = self.prefix_interface.is_none()
+      && self
+        .domains
+        .iter()
+        .any(|d| matches!(d.source, Source::Prefix { .. }));
+
+    for family in [Family::V4, Family::V6] {
+      let needed = family == Family::V6 && prefixed;
+
+      if !needed && !self.domains.iter().any(|d| d.follows(family)) {
+        continue;
+      }
+
+      match self.detect_public_ip(family).await {
+        Ok((ip, url)) => {
+          if self.confirm_public_ip(ip, url.as_deref()).await {
+            self.observe_public_ip(ip);
+          }
+        }
+
+        // only one error is returned, so log the other
+        Err(err) if result.is_err() => error!("{err:?}"),
+        Err(err) => result = Err(err),
+      }
+    }
+
+    result
+  }
+
+  /// Determines the public IP of a family, from the network interface if one
+  /// is given, or trying each provider in turn. Returns the URL of the provider
+  /// that answered along with the IP.
+  async fn detect_public_ip(&mut self, family: Family) -> Result<(IpAddr, Option<String>)> {
+    if let Some(interface) = &self.interface {
+      match ip::interface_address(interface, family) {
+        Ok(ip) => return Ok((ip, None)),
+        Err(err) if !self.interface_fallback => return Err(err),
+
+        Err(err) => debug!(
+          error = format!("{err:#}"),
+          "Falling back to external IP providers."
+        ),
+      }
+    }
+
+    // try providers that have returned the fewest garbage responses first
+
+    let providers = match family {
+      Family::V4 => &mut self.providers,
+      Family::V6 => &mut self.providers_v6,
+    };
+
+    providers.sort_by_key(|p| p.garbage);
+
+    let mut last_err = None;
+    let mut detected = None;
+
+    for provider in providers {
+      let mut attempt = 0;
+
+      let result = loop {
+        let span = info_span!("ip_fetch", url = provider.url, family = %family, attempt);
+
+        let garbage = provider.garbage;
+        let result = match traced(span, provider.fetch(&self.http, family, self.ip_timeout)).await {
+          // a public IP in a special-purpose range is most likely garbage
+          Ok(ip) if !self.allow_bogons && !ip::is_global(ip) => {
+            provider.garbage += 1;
+            Err(anyhow!("Response {ip} is not a globally routable address."))
+          }
+
+          result => result,
+        };
+
+        match result {
+          // only requests that failed are retried, since a provider that
+          // responded with garbage is likely to do so again
+          Err(err) if attempt < self.ip_retries && provider.garbage == garbage => {
+            let delay = self.ip_backoff.delay(attempt);
+
+            debug!(
+              url = provider.url,
+              error = format!("{err:#}"),
+              ?delay,
+              "Retrying IP provider."
+            );
+
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+          }
+
+          result => break result,
+        }
+      };
+
+      match result {
+        Ok(ip) => {
+          if let Some(err) = last_err.take() {
+            error!("{err:?}");
+          }
+
+          detected = Some((ip, Some(provider.url.clone())));
+          break;
+        }
+
+        Err(err) => {
+          let err = err.context(format!("Failed to get public IP from {}.", provider.url));
+
+          // log failures of all but the last provider tried, which is returned
+          // if no provider succeeds
+          if let Some(err) = last_err.replace(err) {
+            error!("{err:?}");
+          }
+        }
+      }
+    }
+
+    detected.ok_or_else(|| last_err.unwrap_or_else(|| anyhow!("No IP providers are configured.")))
+  }
+
+  /// Returns `true` if a detected public IP can be trusted: if it has not
+  /// changed, has been detected on `--confirmations` checks in a row, or is
+  /// also reported by another provider than the one that answered.
+  async fn confirm_public_ip(&mut self, ip: IpAddr, answered: Option<&str>) -> bool {
+    let known = match Family::of(ip) {
+      Family::V4 => self.current_ip.or(self.restored_ip),
+      Family::V6 => self.current_ipv6.or(self.restored_ipv6),
+    };
+
+    let remaining = self.confirmations.observe(known, ip);
+
+    if remaining == 0 {
+      return true;
+    }
+
+    if let Some(answered) = answered {
+      if self.second_opinion(ip, answered).await {
+        self.confirmations.confirm(ip);
+        return true;
+      }
+    }
+
+    info!(
+      new_ip = %ip,
+      "Public IP appears to have changed to {ip}. Waiting for {remaining} more checks to confirm \
+       it."
+    );
+
+    false
+  }
+
+  /// Asks the providers other than the one that answered for the public IP
+  /// until one answers, returning `true` if it reports the same IP.
+  async fn second_opinion(&mut self, ip: IpAddr, answered: &str) -> bool {
+    let family = Family::of(ip);
+    let providers = match family {
+      Family::V4 => &mut self.providers,
+      Family::V6 => &mut self.providers_v6,
+    };
+
+    for provider in providers.iter_mut().filter(|p| p.url != answered) {
+      let span = info_span!("ip_fetch", url = provider.url, family = %family);
+
+      match traced(span, provider.fetch(&self.http, family, self.ip_timeout)).await {
+        Ok(reported) => return reported == ip,
+
+        Err(err) => debug!(
+          url = provider.url,
+          error = format!("{err:#}"),
+          "Failed to ask another IP provider to confirm the public IP."
+        ),
+      }
+    }
+
+    false
+  }
+
+  /// Records the public IP of a family, noting whether it changed.
+  fn observe_public_ip(&mut self, ip: IpAddr) {
+    let (current, restored) = match Family::of(ip) {
+      Family::V4 => (&mut self.current_ip, &mut self.restored_ip),
+      Family::V6 => (&mut self.current_ipv6, &mut self.restored_ipv6),
+    };
+
+    if *current == Some(ip) {
+      return;
+    }
+
+    match current {
+      None => info!(new_ip = %ip, "Public IP is {ip}."),
+      Some(old) => info!(old_ip = %old, new_ip = %ip, "Public IP has changed to {ip}."),
+    }
+
+    let previous = restored.take().or(*current);
+
+    *current = Some(ip);
+
+    if previous != Some(ip) {
+      let now = Utc::now();
+
+      if let Some(previous) = previous {
+        self.ip_changes += 1;
+
+        self.notifier.notify(Event::IpChanged {
+          from: previous,
+          to: ip,
+        });
+
+        let flaps = match ip {
+          IpAddr::V4(_) => &mut self.flaps,
+          IpAddr::V6(_) => &mut self.flaps_v6,
+        };
+
+        if let Some(values) = flaps.observe(ip, now) {
+          let between: Vec<String> = values.iter().map(IpAddr::to_string).collect();
+
+          error!(
+            "Public IP is flapping between {}. Holding updates until {}.",
+            between.join(" and "),
+            flaps.held_until.unwrap_or(now).format("%F %T"),
+          );
+
+          self.notifier.notify(Event::Flapping { between: values });
+        }
+      }
+
+      self.ip_changed_at = Some(now);
+    }
+  }
+
+  /// Fetches the IPs of inventory hosts that report them through an HTTP
+  /// endpoint.
+  async fn refresh_hosts(&mut self) {
+    for domain in &mut self.domains {
+      let Source::Remote { provider, ip } = &mut domain.source else {
+        continue;
+      };
+
+      // the record is the AAAA record only if the domain has no A record
+      let family = match domain.record_types {
+        Some(RecordTypes::Aaaa) => Family::V6,
+        _ => Family::V4,
+      };
+
+      let span = info_span!("ip_fetch", url = provider.url, family = %family);
+
+      match traced(span, provider.fetch(&self.http, family, self.ip_timeout)).await {
+        Ok(fetched) => {
+          domain.stats.last_seen = Some(Utc::now());
+
+          if domain.stats.reaped_at.take().is_some() {
+            info!(
+              domain = domain.name.as_str(),
+              "`{}` is reporting its IP again.", domain.name
+            );
+          }
+
+          if *ip != Some(fetched) {
+            info!(
+              domain = domain.name.as_str(),
+              old_ip = ip.map(field::display),
+              new_ip = %fetched,
+              "`{}` is at {fetched}.",
+              domain.name
+            );
+            *ip = Some(fetched);
+          }
+        }
+
+        Err(err) => {
+          let context = format!(
+            "Failed to get the IP of `{}` from {}.",
+            domain.name, provider.url
+          );
+
+          error!(domain = domain.name.as_str(), "{:?}", err.context(context));
+        }
+      }
+    }
+  }
+
+  /// Detects the private addresses of this machine that domains point at.
+  async fn refresh_private_ips(&mut self) {
+    for domain in &mut self.domains {
+      let Source::Private { source, ip } = &mut domain.source else {
+        continue;
+      };
+
+      // the record is the AAAA record only if the domain has no A record
+      let family = match domain.record_types {
+        Some(RecordTypes::Aaaa) => Family::V6,
+        _ => Family::V4,
+      };
+
+      match source.detect(&self.http, family, self.ip_timeout).await {
+        Ok(detected) if *ip != Some(detected) => {
+          info!(
+            domain = domain.name.as_str(),
+            old_ip = ip.map(field::display),
+            new_ip = %detected,
+            "Private IP of `{}` is {detected}.",
+            domain.name
+          );
+          *ip = Some(detected);
+        }
+
+        Ok(_) => {}
+
+        Err(err) => {
+          let context = format!("Failed to get the private IP of `{}`.", domain.name);
+
+          error!(domain = domain.name.as_str(), "{:?}", err.context(context));
+        }
+      }
+    }
+  }
+
+  /// Combines the current delegated IPv6 prefix with the suffixes of hosts in
+  /// it.
+  fn refresh_prefixes(&mut self) {
+    if !self
+      .domains
+      .iter()
+      .any(|d| matches!(d.source, Source::Prefix { .. }))
+    {
+      return;
+    }
+
+    let prefix = match &self.prefix_interface {
+      Some(interface) => match ip::interface_address(interface, Family::V6) {
+        Ok(ip) => Some(ip),
+
+        Err(err) => {
+          error!(
+            "{:?}",
+            err.context("Failed to get the delegated IPv6 prefix.")
+          );
+          None
+        }
+      },
+
+      None => self.current_ipv6,
+    };
+
+    // records keep their addresses until the prefix is known
+    let Some(IpAddr::V6(prefix)) = prefix else {
+      return;
+    };
+
+    for domain in &mut self.domains {
+      let Source::Prefix { suffix, ip } = &mut domain.source else {
+        continue;
+      };
+
+      let address = IpAddr::V6(ip::with_suffix(prefix, self.prefix_length, *suffix));
+
+      if *ip != Some(address) {
+        info!(
+          domain = domain.name.as_str(),
+          old_ip = ip.map(field::display),
+          new_ip = %address,
+          "Address of `{}` in the delegated prefix is {address}.",
+          domain.name
+        );
+        *ip = Some(address);
+      }
+    }
+  }
+
+  /// Registers the public IP with Consul along with the domains that point at
+  /// it.
+  async fn register_with_consul(&mut self) {
+    let Some(consul) = &mut self.consul else {
+      return;
+    };
+
+    let domains: Vec<&str> = self
+      .domains
+      .iter()
+      .filter(|d| matches!(d.source, Source::Public))
+      .map(|d| d.name.as_str())
+      .collect();
+
+    let Some(ip) = self.current_ip else {
+      return;
+    };
+
+    if let Err(err) = consul.register(ip, &domains).await {
+      error!("{err:?}");
+    }
+  }
+
+  /// Deletes the records of domains in the state file that are no longer
+  /// configured. Domains whose records fail to be deleted are kept in the
+  /// state file and pruned at the next start.
+  async fn prune_retired(&mut self) {
+    if !self.prune || self.retired.is_empty() {
+      return;
+    }
+
+    let names: Vec<String> = self.retired.keys().cloned().collect();
+
+    for name in names {
+      let stats = &self.retired[&name];
+      let identity = retired_identity(stats);
+      let account = self
+        .identities
+        .iter()
+        .position(|i| *i == identity)
+        .unwrap_or_default();
+
+      let zone_id = match stats.zone_id.clone() {
+        Some(zone_id) => Ok(zone_id),
+        // domains saved before their zones were recorded are looked up
+        None => self.find_zone(account, &name).await,
+      };
+
+      let prune = async {
+        let domain: DomainName = name.parse()?;
+        let zone_id = zone_id?;
+
+        let route53 = &self.clients[account];
+        let types = [RrType::A, RrType::Aaaa];
+        let changes = removal(
+          route53,
+          &zone_id,
+          &domain,
+          &types,
+          None,
+          self.routing.as_ref(),
+        )
+        .await?;
+
+        if changes.is_empty() {
+          return Ok(zone_id);
+        }
+
+        if self.dry_run {
+          println!(
+            "Changes to hosted zone {}:",
+            zone_id.trim_start_matches("/hostedzone/")
+          );
+
+          for change in &changes {
+            println!("  {}", aws::describe(change));
+          }
+        } else {
+          aws::change_records(route53, &zone_id, changes).await?;
+        }
+
+        anyhow::Ok(zone_id)
+      };
+
+      match prune.await {
+        Ok(_) if self.dry_run => {}
+
+        Ok(zone_id) => {
+          info!(
+            domain = name.as_str(),
+            zone_id, "Pruned `{name}`, which is no longer configured."
+          );
+
+          self.retired.remove(&name);
+        }
+
+        Err(err) => {
+          let context = format!("Failed to prune `{name}`.");
+
+          error!(domain = name.as_str(), "{:?}", err.context(context));
+        }
+      }
+    }
+  }
+
+  /// Finds the hosted zone of a retired domain among those visible to the
+  /// client of an account.
+  async fn find_zone(&mut self, account: usize, name: &str) -> Result<String> {
+    let zones = match self.zone_caches[account].get() {
+      Some(cached) => cached.to_vec(),
+
+      None => {
+        let listed = list_zones(&self.clients[account]).await?;
+
+        self.zone_caches[account].store(listed.clone());
+        listed
+      }
+    };
+
+    Ok(
+      zone::find(&zones, &name.parse()?, &ZoneChoice::Any)?
+        .id
+        .clone(),
+    )
+  }
+
+  /// Deletes the records this instance wrote, or points them at the exit IP,
+  /// as it is stopped. Paused domains are left alone, since another host may
+  /// have taken them over.
+  async fn remove_records(&self) {
+    if !self.delete_on_exit {
+      return;
+    }
+
+    for domain in &self.domains {
+      if !domain.on_route53() || domain.paused || matches!(domain.source, Source::Lease) {
+        continue;
+      }
+
+      let types: Vec<RrType> = [&domain.record, &domain.record_v6]
+        .into_iter()
+        .filter_map(|record| record.ip)
+        .map(|ip| match ip {
+          IpAddr::V4(_) => RrType::A,
+          IpAddr::V6(_) => RrType::Aaaa,
+        })
+        .collect();
+
+      if types.is_empty() || domain.zone_id.is_empty() {
+        continue;
+      }
+
+      let route53 = &self.clients[domain.account];
+      let routing = self.routing.as_ref();
+
+      let remove = async {
+        let changes = removal(
+          route53,
+          &domain.zone_id,
+          &domain.name,
+          &types,
+          self.exit_ip,
+          routing,
+        )
+        .await?;
+
+        if !changes.is_empty() {
+          aws::change_records(route53, &domain.zone_id, changes).await?;
+        }
+
+        anyhow::Ok(())
+      };
+
+      match (remove.await, self.exit_ip) {
+        (Ok(()), Some(ip)) => info!(
+          domain = domain.name.as_str(),
+          zone_id = domain.zone_id,
+          "Pointed `{}` at {ip} on exit.",
+          domain.name
+        ),
+
+        (Ok(()), None) => info!(
+          domain = domain.name.as_str(),
+          zone_id = domain.zone_id,
+          "Deleted the records of `{}` on exit.",
+          domain.name
+        ),
+
+        (Err(err), _) => {
+          let context = format!("Failed to remove the records of `{}` on exit.", domain.name);
+
+          error!(domain = domain.name.as_str(), "{:?}", err.context(context));
+        }
+      }
+    }
+  }
+
+  /// Deletes the records of inventory hosts whose leases have lapsed.
+  async fn expire_leases(&mut self) {
+    let mut zones = vec![None; self.clients.len()];
+
+    for domain in &mut self.domains {
+      if !matches!(domain.source, Source::Lease) || domain.paused || !domain.on_route53() {
+        continue;
+      }
+
+      let route53 = &self.clients[domain.account];
+
+      match domain
+        .expire_lease(route53, &mut zones[domain.account])
+        .await
+      {
+        Ok(false) => {}
+
+        Ok(true) => {
+          info!(
+            domain = domain.name.as_str(),
+            zone_id = domain.zone_id,
+            "Deleted `{}` because its lease lapsed.",
+            domain.name
+          );
+
+          self.notifier.notify(Event::LeaseExpired {
+            domain: domain.name.to_string(),
+          });
+        }
+
+        Err(err) => error!(domain = domain.name.as_str(), "{err:?}"),
+      }
+    }
+  }
+
+  /// Alerts on or deletes the records of inventory hosts that have not
+  /// reported their IP for the reap period.
+  async fn reap_hosts(&mut self) {
+    let Some(reaper) = &self.reaper else {
+      return;
+    };
+
+    let now = Utc::now();
+    let mut zones = vec![None; self.clients.len()];
+
+    for domain in &mut self.domains {
+      if !matches!(domain.source, Source::Remote { .. }) || domain.stats.reaped_at.is_some() {
+        continue;
+      }
+
+      let last_seen = domain.stats.last_seen;
+
+      if now - last_seen.unwrap_or(self.started_at) < reaper.after {
+        continue;
+      }
+
+      let deleted = reaper.action == ReapAction::Delete && domain.on_route53();
+
+      if deleted {
+        let result = domain
+          .delete_records(
+            &self.clients[domain.account],
+            &mut zones[domain.account],
+            self.routing.as_ref(),
+          )
+          .await;
+
+        if let Err(err) = result {
+          error!(domain = domain.name.as_str(), "{err:?}");
+          continue;
+        }
+
+        info!(
+          domain = domain.name.as_str(),
+          zone_id = domain.zone_id,
+          "Deleted the records of `{}`, which stopped reporting its IP.",
+          domain.name
+        );
+      } else {
+        error!(
+          domain = domain.name.as_str(),
+          "`{}` has stopped reporting its IP.", domain.name
+        );
+      }
+
+      domain.stats.reaped_at = Some(now);
+
+      self.notifier.notify(Event::HostMissing {
+        domain: domain.name.to_string(),
+        last_seen,
+        deleted,
+      });
+    }
+  }
+
+  /// Lists the hosted zones visible to each client that `needed` returns
+  /// `true` for a domain of, leaving `None` for the others.
+  async fn list_zones(
+    &mut self,
+    needed: impl Fn(&Domain) -> bool,
+  ) -> Vec<Option<Result<Vec<HostedZone>>>> {
+    let mut zones = Vec::with_capacity(self.clients.len());
+
+    for (account, route53) in self.clients.iter().enumerate() {
+      let needed = self
+        .domains
+        .iter()
+        .any(|d| d.account == account && needed(d));
+
+      if !needed {
+        zones.push(None);
+        continue;
+      }
+
+      let cache = &mut self.zone_caches[account];
+
+      if let Some(cached) = cache.get() {
+        zones.push(Some(Ok(cached.to_vec())));
+        continue;
+      }
+
+      let listed = list_zones(route53).await;
+
+      if let Ok(listed) = &listed {
+        cache.store(listed.clone());
+      }
+
+      zones.push(Some(listed));
+    }
+
+    zones
+  }
+
+  /// Initializes the records of domains from those that already exist in
+  /// Route 53, so that records that are up to date are not written again at
+  /// startup.
+  ///
+  /// Only records whose IPs are already known are looked up. Failures are
+  /// logged and leave the records to be written by the first pass.
+  async fn seed_records(&mut self) {
+    let zones: Vec<_> = self
+      .list_zones(|d| d.on_route53() && d.zone.zone_id().is_none())
+      .await
+      .into_iter()
+      .map(|zones| {
+        zones?
+          .map_err(|err| error!("{:?}", err.context("Failed to look up existing records.")))
+          .ok()
+      })
+      .collect();
+
+    let public = self.public_ips();
+    let routing = self.routing.as_ref();
+
+    for domain in &mut self.domains {
+      // records of other providers are checked when they are first written
+      if !domain.on_route53() {
+        continue;
+      }
+
+      if domain.zone.zone_id().is_none() {
+        let Some(zones) = &zones[domain.account] else {
+          continue;
+        };
+
+        // domains without a zone are reported by the first pass
+        let Ok(zone) = zone::find(zones, &domain.name, &domain.zone) else {
+          continue;
+        };
+
+        domain.zone_id.replace_range(.., &zone.id);
+      }
+
+      let mut ttls = Vec::with_capacity(2);
+
+      for (slot, ip) in domain.desired_ips(public).into_iter().enumerate() {
+        let Some(ip) = ip else {
+          continue;
+        };
+
+        let rr_type = match ip {
+          IpAddr::V4(_) => RrType::A,
+          IpAddr::V6(_) => RrType::Aaaa,
+        };
+
+        let route53 = &self.clients[domain.account];
+        let zone_id = &domain.zone_id;
+
+        let set = match aws::find_record(route53, zone_id, &domain.name, rr_type, routing).await {
+          Ok(set) => set,
+
+          Err(err) => {
+            error!("{err:?}");
+            continue;
+          }
+        };
+
+        // a record set with several values is rewritten to hold only the IP
+        let existing = set.as_ref().and_then(|set| match set.resource_records() {
+          [record] => record.value().parse::<IpAddr>().ok(),
+          _ => None,
+        });
+
+        let (Some(set), Some(existing)) = (set, existing) else {
+          continue;
+        };
+
+        let record = domain.record_mut(slot);
+
+        record.ip = Some(existing);
+
+        if record.pending.as_ref().is_some_and(|p| p.ip == existing) {
+          record.pending = None;
+        }
+
+        ttls.push(set.ttl.unwrap_or_default());
+
+        debug!(
+          domain = domain.name.as_str(),
+          ip = %existing,
+          "Found existing record."
+        );
+      }
+
+      // records with different TTLs are all written again
+      domain.current_ttl = match ttls.as_slice() {
+        [ttl] => *ttl,
+        [a, b] if a == b => *a,
+        _ => 0,
+      };
+
+      // the lease record is not looked up, so it is renewed right away
+      if self.lease.is_some() && !ttls.is_empty() {
+        domain.lease_renews_at = Some(Utc::now());
+      }
+    }
+  }
+
+  /// Retries detecting the public IP with exponential backoff until it
+  /// succeeds or the timeout passes, for when the network is still coming up.
+  /// Returns `true` if it succeeded.
+  async fn wait_for_network(&mut self, timeout: Duration) -> bool {
+    const MAX_DELAY: Duration = Duration::from_secs(60);
+
+    let started = Instant::now();
+    let mut delay = Duration::from_secs(1);
+    let mut waiting = false;
+
+    loop {
+      let err = match self.refresh_public_ip().await {
+        Ok(()) => {
+          if waiting {
+            info!("Network is up.");
+          }
+
+          return true;
+        }
+
+        Err(err) => err,
+      };
+
+      if started.elapsed() + delay > timeout {
+        if waiting {
+          info!(
+            "Network is still unavailable after {}. Continuing anyway.",
+            humantime::format_duration(Duration::from_secs(started.elapsed().as_secs()))
+          );
+        }
+
+        return false;
+      }
+
+      if !waiting {
+        info!("Waiting for the network to come up.");
+        waiting = true;
+      }
+
+      debug!(
+        error = format!("{err:#}"),
+        ?delay,
+        "Public IP is not available yet."
+      );
+
+      self.controls.sleep(delay).await;
+      delay = (delay * 2).min(MAX_DELAY);
+    }
+  }
+
+  /// Returns `true` if updates are held down because the public IP is
+  /// flapping.
+  fn is_held_down(&mut self) -> bool {
+    let now = Utc::now();
+
+    for flaps in [&mut self.flaps, &mut self.flaps_v6] {
+      if flaps.release(now) {
+        info!("Public IP has stopped flapping. Resuming updates.");
+      }
+    }
+
+    self.flaps.is_holding(now) || self.flaps_v6.is_holding(now)
+  }
+
+  fn save_state(&self) {
+    let Some(path) = &self.state_file else {
+      return;
+    };
+
+    let state = State {
+      started_at: Some(self.started_at),
+      checked_at: Some(Utc::now()),
+      public_ip: self.current_ip,
+      public_ipv6: self.current_ipv6,
+      ip_changed_at: self.ip_changed_at,
+      ip_changes: self.ip_changes,
+      last_write_at: self.last_write_at,
+      held_until: self.flaps.held_until.max(self.flaps_v6.held_until),
+      throttled: pacer::throttled_count(),
+      garbage: self
+        .providers
+        .iter()
+        .chain(&self.providers_v6)
+        .map(|p| (p.url.clone(), p.garbage))
+        .collect(),
+      domains: self
+        .domains
+        .iter()
+        .map(|d| (d.name.to_string(), d.saved_stats()))
+        .chain(self.retired.clone())
+        .collect(),
+      pending: self
+        .domains
+        .iter()
+        .filter_map(|d| Some((d.name.to_string(), d.record.pending.clone()?)))
+        .collect(),
+      pending_v6: self
+        .domains
+        .iter()
+        .filter_map(|d| Some((d.name.to_string(), d.record_v6.pending.clone()?)))
+        .collect(),
+      paused: self
+        .domains
+        .iter()
+        .filter(|d| d.paused)
+        .map(|d| d.name.to_string())
+        .collect(),
+    };
+
+    if let Err(err) = state.save(path) {
+      error!("{err:?}");
+    }
+  }
+
+  /// Returns the TTL records should have.
+  fn desired_ttl(&self) -> i64 {
+    match (&self.dynamic_ttl, self.ip_changed_at) {
+      (Some(policy), Some(changed_at)) => policy.ttl(Utc::now() - changed_at),
+      (Some(policy), None) => policy.ttl(TimeDelta::zero()),
+      (None, _) => self.ttl,
+    }
+  }
+
+  async fn update_dns(&mut self) {
+    let ttl = self.desired_ttl();
+    let public = self.public_ips();
+
+    if !self.domains.iter().any(|d| d.is_stale(public, ttl)) {
+      if self.dry_run {
+        info!("All records are up to date.");
+      }
+
+      return;
+    }
+
+    let allowed_at = self
+      .last_write_at
+      .map(|time| time + self.min_change_interval);
+
+    if let Some(allowed_at) = allowed_at.filter(|&time| Utc::now() < time) {
+      info!(
+        "Holding record changes until {}, since the records were changed less than \
+         --min-change-interval ago.",
+        allowed_at.format("%F %T")
+      );
+
+      return;
+    }
+
+    // get list of hosted zones, unless every domain to update is given the ID
+    // of its zone, which also works without permission to list them
+
+    let unresolved =
+      |d: &Domain| d.on_route53() && d.is_stale(public, ttl) && d.zone.zone_id().is_none();
+
+    let cached: Vec<bool> = self.zone_caches.iter().map(|c| c.get().is_some()).collect();
+    let mut zones = self.list_zones(unresolved).await;
+
+    // a domain that matches none of the kept zones may be in a zone that was
+    // created since they were listed
+    let mut outdated = false;
+
+    for domain in self.domains.iter().filter(|d| unresolved(d)) {
+      let Some(Ok(listed)) = &zones[domain.account] else {
+        continue;
+      };
+
+      let matched = zone::judge(listed, &domain.name, &domain.zone)
+        .iter()
+        .any(|(_, verdict)| *verdict == ZoneVerdict::Deepest);
+
+      if cached[domain.account] && !matched {
+        self.zone_caches[domain.account].clear();
+        outdated = true;
+      }
+    }
+
+    if outdated {
+      zones = self.list_zones(unresolved).await;
+    }
+
+    for err in zones
+      .iter()
+      .flatten()
+      .filter_map(|zones| zones.as_ref().err())
+    {
+      error!("{err:?}");
+      self.retry_soon |= aws::is_transient(err);
+    }
+
+    // match domain names to hosted zones
+
+    for domain in &mut self.domains {
+      if !unresolved(domain) {
+        continue;
+      }
+
+      let zones = match &zones[domain.account] {
+        Some(Ok(zones)) => zones,
+
+        Some(Err(err)) => {
+          domain.record_failure(err, &mut self.notifier, self.failure_hook.as_ref());
+          domain.queue(public);
+          domain.zone_id.clear();
+          continue;
+        }
+
+        // zones are listed for the account of every unresolved domain
+        None => continue,
+      };
+
+      let zone = match zone::find(zones, &domain.name, &domain.zone) {
+        Ok(zone) => zone,
+
+        Err(err) => {
+          error!(domain = domain.name.as_str(), "{err:?}");
+          domain.record_failure(&err, &mut self.notifier, self.failure_hook.as_ref());
+          domain.zone_id.clear();
+          continue;
+        }
+      };
+
+      domain.zone_id.replace_range(.., &zone.id);
+    }
+
+    #[cfg(feature = "cloudflare")]
+    self.update_cloudflare(public, ttl).await;
+
+    // update DNS records, submitting the changes of all domains in a hosted
+    // zone as one batch so that they apply atomically, with the credentials
+    // the domains share
+
+    let mut batches: BTreeMap<(usize, String), Vec<usize>> = BTreeMap::new();
+
+    for (index, domain) in self.domains.iter().enumerate() {
+      if domain.on_route53() && !domain.zone_id.is_empty() && domain.is_stale(public, ttl) {
+        batches
+          .entry((domain.account, domain.zone_id.clone()))
+          .or_default()
+          .push(index);
+      }
+    }
+
+    // the batches of different zones are planned and submitted concurrently,
+    // and their outcomes are then recorded one at a time
+    let batches: Vec<Batch> = stream::iter(batches)
+      .map(|((account, zone_id), indices)| {
+        self.submit_batch(account, zone_id, indices, public, ttl)
+      })
+      .buffered(self.zone_concurrency)
+      .collect()
+      .await;
+
+    for batch in batches {
+      let Batch {
+        zone_id,
+        now,
+        plans,
+        failures,
+        submitted,
+      } = batch;
+
+      for (index, err) in failures {
+        error!(
+          domain = self.domains[index].name.as_str(),
+          zone_id, "{err:?}"
+        );
+        self.fail_update(index, &err, public);
+      }
+
+      if plans.is_empty() {
+        continue;
+      }
+
+      let Some(submitted) = submitted else {
+        println!(
+          "Changes to hosted zone {}:",
+          zone_id.trim_start_matches("/hostedzone/")
+        );
+
+        for change in plans.iter().flat_map(|(_, plan)| &plan.changes) {
+          println!("  {}", aws::describe(change));
+        }
+
+        continue;
+      };
+
+      match submitted {
+        Ok(submitted) => {
+          for (index, plan) in plans {
+            self.finish_update(index, plan, Some(&submitted), now);
+          }
+        }
+
+        Err(err) => {
+          let names: Vec<_> = plans
+            .iter()
+            .map(|&(index, _)| format!("`{}`", self.domains[index].name))
+            .collect();
+
+          let err = err.context(format!("Failed to update {}.", names.join(", ")));
+
+          error!(zone_id, "{err:?}");
+
+          for (index, _) in plans {
+            self.fail_update(index, &err, public);
+          }
+        }
+      }
+    }
+  }
+
+  /// Updates the records of the domains hosted by Cloudflare, one domain at a
+  /// time since Cloudflare has no batches of changes.
+  ///
+  /// Records that already have their IP are left alone, and counted as
+  /// written without an update.
+  #[cfg(feature = "cloudflare")]
+  async fn update_cloudflare(&mut self, public: PublicIps, ttl: i64) {
+    let Some(cloudflare) = &mut self.cloudflare else {
+      return;
+    };
+
+    let now = Utc::now();
+    let mut results = Vec::new();
+
+    for (index, domain) in self.domains.iter().enumerate() {
+      if domain.provider != provider::Kind::Cloudflare || !domain.is_stale(public, ttl) {
+        continue;
+      }
+
+      let target = ddns_route53::RecordTarget {
+        name: domain.name.clone(),
+        zone: domain.zone.clone(),
+        ttl: domain.ttl.unwrap_or(ttl),
+      };
+
+      let writes = domain.writes(public, ttl, now);
+
+      if self.dry_run {
+        println!("Changes to the Cloudflare zone of `{}`:", domain.name);
+
+        for (_, ip) in &writes {
+          let rr_type = match ip {
+            IpAddr::V4(_) => "A",
+            IpAddr::V6(_) => "AAAA",
+          };
+
+          println!(
+            "  UPSERT {rr_type} `{}` → {ip} (TTL {}s)",
+            domain.name, target.ttl
+          );
+        }
+
+        continue;
+      }
+
+      let mut written = Vec::with_capacity(writes.len());
+      let mut current = Vec::new();
+      let mut result = Ok(());
+
+      for (slot, ip) in writes {
+        match cloudflare.apply(&target, ip).await {
+          Ok(true) => written.push((slot, ip)),
+          Ok(false) => current.push((slot, ip)),
+
+          Err(err) => {
+            result = Err(err.context(format!("Failed to update `{}`.", domain.name)));
+            break;
+          }
+        }
+      }
+
+      results.push((index, target.ttl, written, current, result));
+    }
+
+    for (index, ttl, written, current, result) in results {
+      if let Err(err) = result {
+        error!(domain = self.domains[index].name.as_str(), "{err:?}");
+        self.fail_update(index, &err, public);
+        continue;
+      }
+
+      let domain = &mut self.domains[index];
+
+      for (slot, ip) in current {
+        let record = domain.record_mut(slot);
+
+        record.ip = Some(ip);
+        record.pending = None;
+      }
+
+      if written.is_empty() {
+        domain.current_ttl = ttl;
+        domain.stats.consecutive_failures = 0;
+        domain.stats.last_error = None;
+        continue;
+      }
+
+      let plan = Plan {
+        writes: written,
+        ttl,
+        stale: None,
+        changes: Vec::new(),
+      };
+
+      self.finish_update(index, plan, None, now);
+    }
+  }
+
+  /// Plans the changes of the domains of a batch and submits them, unless
+  /// this is a dry run or none of them could be planned.
+  async fn submit_batch(
+    &self,
+    account: usize,
+    zone_id: String,
+    indices: Vec<usize>,
+    public: PublicIps,
+    ttl: i64,
+  ) -> Batch {
+    let now = Utc::now();
+    let mut plans = Vec::with_capacity(indices.len());
+    let mut failures = Vec::new();
+
+    for index in indices {
+      let domain = &self.domains[index];
+
+      match self.plan(domain, public, ttl, now).await {
+        Ok(plan) => plans.push((index, plan)),
+        Err(err) => failures.push((
+          index,
+          err.context(format!("Failed to update `{}`.", domain.name)),
+        )),
+      }
+    }
+
+    let submitted = match self.dry_run || plans.is_empty() {
+      true => None,
+
+      false => {
+        let changes = plans
+          .iter()
+          .flat_map(|(_, plan)| plan.changes.iter().cloned())
+          .collect();
+
+        Some(aws::change_records(&self.clients[account], &zone_id, changes).await)
+      }
+    };
+
+    Batch {
+      zone_id,
+      now,
+      plans,
+      failures,
+      submitted,
+    }
+  }
+
+  /// Plans the changes that update the records of a domain.
+  async fn plan(
+    &self,
+    domain: &Domain,
+    public: PublicIps,
+    ttl: i64,
+    now: DateTime<Utc>,
+  ) -> Result<Plan> {
+    let writes = domain.writes(public, ttl, now);
+    let ttl = domain.ttl.unwrap_or(ttl);
+    let routing = self.routing.as_ref();
+    let mut changes = Vec::with_capacity(writes.len() + 3);
+    let mut stale = None;
+
+    for &(_, ip) in &writes {
+      changes.push(aws::upsert(&domain.name, ip, ttl, routing)?);
+    }
+
+    if let Some(lease) = self.lease {
+      changes.push(lease::renew(&domain.name, now + lease, ttl)?);
+    }
+
+    if let Some(host) = &self.metadata_host {
+      changes.push(metadata::write(&domain.name, host, now, ttl, routing)?);
+    }
+
+    let stale_type = domain.stale_type(public).filter(|_| !writes.is_empty());
+
+    if let (true, Some(rr_type)) = (self.delete_stale_family, stale_type) {
+      let route53 = &self.clients[domain.account];
+      let zone_id = &domain.zone_id;
+
+      stale = aws::find_record(route53, zone_id, &domain.name, rr_type, routing).await?;
+
+      if let Some(set) = &stale {
+        changes.push(aws::delete(set.clone())?);
+      }
+    }
+
+    Ok(Plan {
+      writes,
+      ttl,
+      stale,
+      changes,
+    })
+  }
+
+  /// Records that the planned changes of a domain were applied.
+  fn finish_update(
+    &mut self,
+    index: usize,
+    plan: Plan,
+    submitted: Option<&aws::Submitted>,
+    now: DateTime<Utc>,
+  ) {
+    let domain = &mut self.domains[index];
+    let ttl = plan.ttl;
+    let change_id = submitted.map(|s| s.change_id.as_str());
+    let request_id = submitted.and_then(|s| s.request_id.as_deref());
+
+    if let Some(set) = plan.stale {
+      info!(
+        domain = domain.name.as_str(),
+        zone_id = domain.zone_id,
+        change_id,
+        aws_request_id = request_id,
+        "Deleted the stale {} record of `{}`.",
+        set.r#type.as_str(),
+        domain.name
+      );
+    }
+
+    let ttl_changed = ttl != domain.current_ttl;
+    let previous_ttl = domain.current_ttl;
+    let mut ip_changed = false;
+
+    domain.current_ttl = ttl;
+    domain.lease_renews_at = self
+      .lease
+      .filter(|_| domain.on_route53())
+      .map(|lease| now + lease / 2);
+
+    domain.stats.record_success();
+    self.last_write_at = domain.stats.last_change;
+
+    for (slot, ip) in plan.writes {
+      let record = domain.record_mut(slot);
+
+      record.pending = None;
+
+      let old = record.ip.replace(ip);
+
+      if old == Some(ip) {
+        continue;
+      }
+
+      ip_changed = true;
+
+      info!(
+        domain = domain.name.as_str(),
+        zone_id = domain.zone_id,
+        old_ip = old.map(field::display),
+        new_ip = %ip,
+        change_id,
+        aws_request_id = request_id,
+        "Updated `{}` to {ip}.",
+        domain.name
+      );
+
+      // only changes to Route 53 can be waited on to be in sync
+      if let (Some(verifier), Some(change_id)) = (&mut self.verifier, change_id) {
+        verifier.spawn(
+          &self.clients[domain.account],
+          domain.name.to_string(),
+          domain.zone_id.clone(),
+          change_id.into(),
+          ip,
+          Duration::from_secs(previous_ttl.max(ttl) as u64),
+        );
+      }
+
+      self.notifier.notify(Event::Updated {
+        domain: domain.name.to_string(),
+        ip,
+      });
+    }
+
+    if !ip_changed {
+      match ttl_changed {
+        true => info!(
+          domain = domain.name.as_str(),
+          zone_id = domain.zone_id,
+          ttl,
+          change_id,
+          aws_request_id = request_id,
+          "Changed the TTL of `{}` to {ttl}s.",
+          domain.name
+        ),
+        false => debug!(domain = domain.name.as_str(), "Renewed lease."),
+      }
+    }
+  }
+
+  /// Records that the records of a domain failed to update, queueing the
+  /// changes to retry them.
+  fn fail_update(&mut self, index: usize, err: &anyhow::Error, public: PublicIps) {
+    let domain = &mut self.domains[index];
+
+    domain.record_failure(err, &mut self.notifier, self.failure_hook.as_ref());
+    domain.queue(public);
+    self.retry_soon |= aws::is_transient(err);
+  }
+
+  /// Returns the total number of failed update attempts of all domains.
+  fn update_failures(&self) -> u64 {
+    self.domains.iter().map(|d| d.stats.failures).sum()
+  }
+
+  /// Returns the public IPs of this machine last detected.
+  const fn public_ips(&self) -> PublicIps {
+    PublicIps {
+      v4: self.current_ip,
+      v6: self.current_ipv6,
+    }
+  }
+}
+
+impl Domain {
+  /// Returns `true` if the zone of the domain is hosted by Route 53.
+  fn on_route53(&self) -> bool {
+    self.provider == provider::Kind::Route53
+  }
+
+  /// Returns the statistics of the domain to save to the state file, along
+  /// with where its records are so that they can be pruned once the domain
+  /// is no longer configured.
+  fn saved_stats(&self) -> DomainStats {
+    let zone_id = match self.zone_id.is_empty() || !self.on_route53() {
+      true => self.stats.zone_id.clone(),
+      false => Some(self.zone_id.clone()),
+    };
+
+    DomainStats {
+      zone_id,
+      profile: self.identity.profile.clone(),
+      role_arn: self.identity.role_arn.clone(),
+      ..self.stats.clone()
+    }
+  }
+
+  /// Parses a domain name optionally followed by `=` and a zone choice.
+  fn parse(arg: String) -> Result<Self> {
+    let (name, zone) = match arg.split_once('=') {
+      Some((name, zone)) => (name.parse()?, zone.parse()?),
+      None => (arg.parse()?, ZoneChoice::Any),
+    };
+
+    Ok(Self {
+      account: 0,
+      identity: aws::Identity::default(),
+      provider: provider::Kind::Route53,
+      name,
+      paused: false,
+      record: Record::default(),
+      record_v6: Record::default(),
+      record_types: None,
+      source: Source::Public,
+      stats: DomainStats::default(),
+      zone_id: zone.zone_id().unwrap_or_default(),
+      zone,
+      current_ttl: 0,
+      ttl: None,
+      lease_renews_at: None,
+    })
+  }
+
+  /// Records a failed update, notifying the user once the domain has failed
+  /// enough times in a row and running the failure hook if it keeps failing.
+  fn record_failure(
+    &mut self,
+    err: &anyhow::Error,
+    notifier: &mut Notifier,
+    hook: Option<&FailureHook>,
+  ) {
+    self.stats.record_failure(err);
+
+    if self.stats.consecutive_failures == notifier.failure_threshold() {
+      notifier.notify(Event::UpdateFailed {
+        domain: self.name.to_string(),
+        error: format!("{err:#}"),
+        failures: self.stats.consecutive_failures,
+      });
+    }
+
+    if let Some(hook) = hook {
+      hook.observe(Failure {
+        kind: "update",
+        domain: Some(&self.name),
+        count: self.stats.consecutive_failures,
+        error: err,
+      });
+    }
+  }
+
+  /// Deletes the domain's A and AAAA records so that they are recreated only
+  /// once its host reports an IP again.
+  async fn delete_records(
+    &mut self,
+    route53: &route53::Client,
+    zones: &mut Option<Vec<HostedZone>>,
+    routing: Option<&aws::Routing>,
+  ) -> Result<()> {
+    self.resolve_zone(route53, zones).await?;
+
+    let mut changes = Vec::new();
+
+    for rr_type in [RrType::A, RrType::Aaaa] {
+      if let Some(record) =
+        aws::find_record(route53, &self.zone_id, &self.name, rr_type, routing).await?
+      {
+        changes.push(aws::delete(record)?);
+      }
+    }
+
+    if !changes.is_empty() {
+      aws::change_records(route53, &self.zone_id, changes)
+        .await
+        .with_context(|| format!("Failed to delete the records of `{}`.", self.name))?;
+    }
+
+    if let Source::Remote { ip, .. } = &mut self.source {
+      *ip = None;
+    }
+
+    self.record = Record::default();
+    self.record_v6 = Record::default();
+
+    Ok(())
+  }
+
+  /// Deletes the domain's A and AAAA records along with its lease if the lease
+  /// has lapsed.
+  ///
+  /// Returns `true` if the lease had lapsed.
+  async fn expire_lease(
+    &mut self,
+    route53: &route53::Client,
+    zones: &mut Option<Vec<HostedZone>>,
+  ) -> Result<bool> {
+    self.resolve_zone(route53, zones).await?;
+
+    let lease_name = lease::record_name(&self.name);
+    let Some(lease) =
+      aws::find_record(route53, &self.zone_id, &lease_name, RrType::Txt, None).await?
+    else {
+      return Ok(false);
+    };
+
+    if lease::expiry(&lease).is_some_and(|expires| expires > Utc::now()) {
+      return Ok(false);
+    }
+
+    let mut changes = vec![aws::delete(lease)?];
+
+    for rr_type in [RrType::A, RrType::Aaaa] {
+      if let Some(record) =
+        aws::find_record(route53, &self.zone_id, &self.name, rr_type, None).await?
+      {
+        changes.push(aws::delete(record)?);
+      }
+    }
+
+    aws::change_records(route53, &self.zone_id, changes)
+      .await
+      .with_context(|| format!("Failed to delete the lapsed lease of `{}`.", self.name))?;
+
+    Ok(true)
+  }
+
+  /// Finds the hosted zone of the domain if it has not been found yet.
+  ///
+  /// The hosted zones are listed at most once for all domains sharing the
+  /// same list.
+  async fn resolve_zone(
+    &mut self,
+    route53: &route53::Client,
+    zones: &mut Option<Vec<HostedZone>>,
+  ) -> Result<()> {
+    if self.zone_id.is_empty() {
+      let zones = match zones {
+        Some(zones) => zones,
+        None => zones.insert(list_zones(route53).await?),
+      };
+
+      self
+        .zone_id
+        .clone_from(&zone::find(zones, &self.name, &self.zone)?.id);
+    }
+
+    Ok(())
+  }
+
+  /// Returns the IPs the records of the domain should have, by slot: its
+  /// record, and its AAAA record in dual-stack mode.
+  ///
+  /// A change that failed to apply is still retried when the IP it changes
+  /// to is not known anymore.
+  fn desired_ips(&self, public: PublicIps) -> [Option<IpAddr>; 2] {
+    let ip = match &self.source {
+      Source::Public => public.v4.filter(|_| self.follows(Family::V4)),
+      Source::Static(ip) => Some(*ip),
+      Source::Remote { ip, .. } | Source::Private { ip, .. } | Source::Prefix { ip, .. } => *ip,
+      // the record is managed by the instance that holds the lease
+      Source::Lease => None,
+    };
+
+    let ipv6 = public.v6.filter(|_| self.follows(Family::V6));
+
+    let pending = |record: &Record| record.pending.as_ref().map(|p| p.ip);
+
+    [
+      ip.or(pending(&self.record)),
+      ipv6.or(pending(&self.record_v6)),
+    ]
+  }
+
+  /// Returns `true` if a record of the domain follows the public IP of a
+  /// family.
+  fn follows(&self, family: Family) -> bool {
+    matches!(self.source, Source::Public)
+      && self
+        .record_types
+        .is_some_and(|types| types.includes(family))
+  }
+
+  /// Returns the type of the record of the other address family, which is
+  /// stale if the domain should only have an address of a single family.
+  fn stale_type(&self, public: PublicIps) -> Option<RrType> {
+    let mut families = self
+      .desired_ips(public)
+      .into_iter()
+      .flatten()
+      .map(Family::of);
+
+    match (families.next(), families.next()) {
+      (Some(Family::V4), None) => Some(RrType::Aaaa),
+      (Some(Family::V6), None) => Some(RrType::A),
+      _ => None,
+    }
+  }
+
+  /// Returns a record of the domain by slot.
+  fn record_mut(&mut self, slot: usize) -> &mut Record {
+    match slot {
+      0 => &mut self.record,
+      _ => &mut self.record_v6,
+    }
+  }
+
+  /// Returns the records that need to be written, by slot, with the IPs to
+  /// write. Every record is written again when the TTL changes or a lease
+  /// is renewed.
+  fn writes(&self, public: PublicIps, ttl: i64, now: DateTime<Utc>) -> Vec<(usize, IpAddr)> {
+    let ttl = self.ttl.unwrap_or(ttl);
+    let renew = ttl != self.current_ttl || self.lease_renews_at.is_some_and(|time| now >= time);
+    let records = [&self.record, &self.record_v6];
+
+    self
+      .desired_ips(public)
+      .into_iter()
+      .zip(records)
+      .enumerate()
+      .filter_map(|(slot, (ip, record))| {
+        let ip = ip?;
+
+        (renew || record.ip != Some(ip)).then_some((slot, ip))
+      })
+      .collect()
+  }
+
+  /// Returns `true` if any record of the domain needs to be written.
+  fn is_stale(&self, public: PublicIps, ttl: i64) -> bool {
+    !self.paused && !self.writes(public, ttl, Utc::now()).is_empty()
+  }
+
+  /// Queues changes of the records that need to be written, to retry them
+  /// even if their IPs cannot be determined later.
+  fn queue(&mut self, public: PublicIps) {
+    let desired = self.desired_ips(public);
+
+    for (slot, ip) in desired.into_iter().enumerate() {
+      let Some(ip) = ip else {
+        continue;
+      };
+
+      let record = self.record_mut(slot);
+
+      if record.pending.as_ref().is_some_and(|p| p.ip == ip) {
+        continue;
+      }
+
+      record.pending = Some(PendingChange {
+        ip,
+        queued_at: Utc::now(),
+      });
+    }
+  }
+}
+
+/// Prints every hosted zone considered for a domain and which one is chosen.
+async fn explain(domain: Domain, identity: &aws::Identity, aws: &AwsOptions) -> Result<()> {
+  let zones = list_zones(&connect_as(aws, identity).await).await?;
+
+  let verdicts = zone::judge(&zones, &domain.name, &domain.zone);
+  let id_width = zones.iter().map(|z| z.id.len()).max().unwrap_or(0);
+  let name_width = zones.iter().map(|z| z.name.len()).max().unwrap_or(0);
+
+  println!("Hosted zones considered for `{}`:", domain.name);
+  println!();
+
+  for (zone, verdict) in verdicts {
+    let reason = match verdict {
+      ZoneVerdict::Unrelated => "rejected: does not contain the domain".into(),
+      ZoneVerdict::Excluded => format!("rejected: excluded by `={}`", domain.zone),
+      ZoneVerdict::Shallower => "rejected: a deeper zone contains the domain".into(),
+      ZoneVerdict::Deepest => "accepted: deepest zone containing the domain".into(),
+    };
+
+    println!(
+      "  {:id_width$}  {:name_width$}  {:7}  {reason}",
+      zone.id,
+      zone.name,
+      visibility(zone),
+    );
+  }
+
+  println!();
+
+  match zone::find(&zones, &domain.name, &domain.zone) {
+    Ok(zone) => println!("Chosen: {} ({})", zone.id, zone.name),
+    Err(err) => println!("No zone chosen. {err}"),
+  }
+
+  Ok(())
+}
+
+/// Prints the visible hosted zones and the zone each domain is matched to.
+async fn print_zones(domains: &[Domain], aws: &AwsOptions) -> Result<()> {
+  let mut accounts = Accounts::new(aws);
+  let mut zones: Vec<HostedZone> = Vec::new();
+
+  // the zones visible to each identity that a domain without a zone ID is
+  // matched with are listed together
+  let global = aws::Identity::default();
+  let mut identities = Vec::new();
+
+  for domain in domains.iter().filter(|d| d.zone.zone_id().is_none()) {
+    if !identities.contains(&&domain.identity) {
+      identities.push(&domain.identity);
+    }
+  }
+
+  if domains.is_empty() {
+    identities.push(&global);
+  }
+
+  for identity in identities {
+    for zone in accounts.zones(identity).await? {
+      if !zones.iter().any(|z| z.id == zone.id) {
+        zones.push(zone.clone());
+      }
+    }
+  }
+
+  let id_width = zones.iter().map(|z| z.id.len()).max().unwrap_or(0).max(7);
+  let name_width = zones.iter().map(|z| z.name.len()).max().unwrap_or(0).max(4);
+
+  println!(
+    "{:id_width$}  {:name_width$}  VISIBILITY  RECORDS",
+    "ZONE ID", "NAME"
+  );
+
+  for zone in &zones {
+    println!(
+      "{:id_width$}  {:name_width$}  {:10}  {:>7}",
+      zone.id,
+      zone.name,
+      visibility(zone),
+      zone.resource_record_set_count.unwrap_or(0),
+    );
+  }
+
+  if domains.is_empty() {
+    return Ok(());
+  }
+
+  let width = domains
+    .iter()
+    .map(|d| d.name.len())
+    .max()
+    .unwrap_or(0)
+    .max(6);
+
+  println!();
+  println!("{:width$}  ZONE", "DOMAIN");
+
+  for domain in domains {
+    let zone = accounts
+      .zone(&domain.identity, &domain.name, &domain.zone)
+      .await;
+
+    match zone {
+      Ok(aws::Zone {
+        id,
+        name: Some(name),
+      }) => println!("{:width$}  {id} ({name})", domain.name.as_str()),
+      Ok(zone) => println!("{:width$}  {}", domain.name.as_str(), zone.id),
+      Err(err) => println!("{:width$}  {err}", domain.name.as_str()),
+    }
+  }
+
+  Ok(())
+}
+
+/// Prints the state saved by a running daemon.
+fn print_status(state_file: Option<&Path>) -> Result<()> {
+  let Some(path) = state_file else {
+    bail!("The status command requires --state-file.");
+  };
+
+  let state = State::load(path)?;
+  let width = state
+    .domains
+    .keys()
+    .map(String::len)
+    .max()
+    .unwrap_or(0)
+    .max(6);
+
+  let now = Utc::now();
+
+  let since = |time: Option<DateTime<Utc>>| match time {
+    Some(time) => format!(
+      "{} ({} ago)",
+      time.format("%F %T"),
+      format_duration(now - time)
+    ),
+    None => "never".into(),
+  };
+
+  match state.public_ip {
+    Some(ip) => println!("Public IP:  {ip}"),
+    None => println!("Public IP:  unknown"),
+  }
+  if let Some(ip) = state.public_ipv6 {
+    println!("IPv6:       {ip}");
+  }
+  println!("IP since:   {}", since(state.ip_changed_at));
+  println!("Started:    {}", since(state.started_at));
+  println!("Last write: {}", since(state.last_write_at));
+
+  if let Some(until) = state.held_until.filter(|&until| until > now) {
+    println!(
+      "Hold-down:  until {} because the public IP is flapping",
+      until.format("%F %T")
+    );
+  }
+  println!();
+  println!("IP changes: {}", state.ip_changes);
+  println!(
+    "Updates:    {}",
+    state.domains.values().map(|d| d.updates).sum::<u64>()
+  );
+  println!(
+    "Failures:   {}",
+    state.domains.values().map(|d| d.failures).sum::<u64>()
+  );
+  println!("Throttled:  {}", state.throttled);
+  println!();
+  println!(
+    "{:width$}  UPDATES  FAILURES  LAST CHANGE          LAST ERROR",
+    "DOMAIN"
+  );
+
+  for (name, stats) in &state.domains {
+    let last_change = match stats.last_change {
+      Some(time) => time.format("%F %T").to_string(),
+      None => "never".into(),
+    };
+
+    println!(
+      "{name:width$}  {:>7}  {:>8}  {last_change:19}  {}",
+      stats.updates,
+      stats.failures,
+      stats.last_error.as_deref().unwrap_or("-"),
+    );
+  }
+
+  if !state.pending.is_empty() || !state.pending_v6.is_empty() {
+    println!();
+    println!("Pending changes:");
+
+    for (name, pending) in state.pending.iter().chain(&state.pending_v6) {
+      println!(
+        "  {name} → {} (queued {})",
+        pending.ip,
+        pending.queued_at.format("%F %T")
+      );
+    }
+  }
+
+  if !state.paused.is_empty() {
+    println!();
+    println!("Paused domains:");
+
+    for name in &state.paused {
+      println!("  {name}");
+    }
+  }
+
+  if state.domains.values().any(|d| d.reaped_at.is_some()) {
+    println!();
+    println!("Hosts that stopped reporting their IP:");
+
+    for (name, stats) in &state.domains {
+      if stats.reaped_at.is_some() {
+        println!("  {name} (last seen {})", since(stats.last_seen));
+      }
+    }
+  }
+
+  if state.garbage.values().any(|&count| count > 0) {
+    println!();
+    println!("Garbage responses from IP providers:");
+
+    for (url, count) in &state.garbage {
+      println!("  {url}: {count}");
+    }
+  }
+
+  Ok(())
+}
+
+/// Formats a duration as a short human-readable string such as `3d 4h 12m`.
+fn format_duration(duration: chrono::TimeDelta) -> String {
+  let minutes = duration.num_minutes().max(0);
+  let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+
+  match (days, hours) {
+    (0, 0) => format!("{minutes}m"),
+    (0, _) => format!("{hours}h {minutes}m"),
+    _ => format!("{days}d {hours}h {minutes}m"),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn domain(types: RecordTypes) -> Domain {
+    let mut domain = Domain::parse("home.example.com".into()).unwrap();
+
+    domain.record_types = Some(types);
+    domain
+  }
+
+  #[test]
+  fn stale_type_is_the_family_without_a_desired_address() {
+    let v4 = Some("203.0.113.7".parse().unwrap());
+    let v6 = Some("2001:db8::7".parse().unwrap());
+
+    let both = PublicIps { v4, v6 };
+    let v4_only = PublicIps { v4, v6: None };
+    let v6_only = PublicIps { v4: None, v6 };
+
+    assert_eq!(domain(RecordTypes::A).stale_type(both), Some(RrType::Aaaa));
+    assert_eq!(domain(RecordTypes::Aaaa).stale_type(both), Some(RrType::A));
+
+    // in dual-stack mode, neither record is stale while both are detected
+    let dual = domain(RecordTypes::Both);
+
+    assert_eq!(dual.stale_type(both), None);
+    assert_eq!(dual.stale_type(v4_only), Some(RrType::Aaaa));
+    assert_eq!(dual.stale_type(v6_only), Some(RrType::A));
+    assert_eq!(dual.stale_type(PublicIps { v4: None, v6: None }), None);
+  }
+
+  #[test]
+  fn stale_type_keeps_the_record_of_a_pending_change() {
+    let mut dual = domain(RecordTypes::Both);
+    let v4 = Some("203.0.113.7".parse().unwrap());
+
+    dual.record_v6.pending = Some(PendingChange {
+      ip: "2001:db8::7".parse().unwrap(),
+      queued_at: Utc::now(),
+    });
+
+    assert_eq!(dual.stale_type(PublicIps { v4, v6: None }), None);
   }
 }
@@ -0,0 +1,36 @@
+use anyhow::Result;
+use aws_sdk_route53::types::Change;
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::aws;
+
+/// Returns the name of the TXT record that describes the last update of a
+/// domain's records.
+pub fn record_name(domain: &str) -> String {
+  format!("_ddns.{domain}")
+}
+
+/// Builds a change that records when and by which host and version of the
+/// daemon a domain's records were written.
+pub fn write(
+  domain: &str,
+  host: &str,
+  now: DateTime<Utc>,
+  ttl: i64,
+  routing: Option<&aws::Routing>,
+) -> Result<Change> {
+  let values = [
+    format!("updated={}", now.to_rfc3339_opts(SecondsFormat::Secs, true)),
+    format!("host={host}"),
+    format!("version={}", env!("CARGO_PKG_VERSION")),
+  ];
+
+  // each value is a separate string of the same record
+  let value = values
+    .iter()
+    .map(|value| format!("\"{value}\""))
+    .collect::<Vec<_>>()
+    .join(" ");
+
+  aws::upsert_txt(&record_name(domain), &value, ttl, routing)
+}
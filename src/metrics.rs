@@ -0,0 +1,273 @@
+use std::{
+  fmt::Write,
+  net::SocketAddr,
+  sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+
+use crate::server::{self, Response};
+
+/// Metrics recorded at the end of an update pass, for the sinks that report
+/// them to monitoring systems.
+pub struct Snapshot {
+  /// Time the pass completed.
+  pub time: DateTime<Utc>,
+  /// Number of times the public IP has changed.
+  pub ip_changes: u64,
+  /// Number of consecutive failures to determine the public IP.
+  pub ip_failures: u64,
+  /// Number of Route 53 requests that were throttled.
+  pub throttled: u64,
+  /// Number of Route 53 requests that failed, including throttled ones.
+  pub route53_errors: u64,
+  /// Whether the public IP was determined and every update succeeded in the
+  /// pass.
+  pub healthy: bool,
+  pub domains: Vec<DomainMetrics>,
+}
+
+/// Metrics of a single domain.
+pub struct DomainMetrics {
+  pub name: String,
+  /// Number of successful record updates.
+  pub updates: u64,
+  /// Number of failed update attempts.
+  pub failures: u64,
+  /// Number of failed update attempts since the last successful update.
+  pub consecutive_failures: u64,
+  /// Time of the most recent successful record update.
+  pub last_change: Option<DateTime<Utc>>,
+  /// Whether updates of the domain are paused.
+  pub paused: bool,
+}
+
+impl Snapshot {
+  /// Renders the metrics in the Prometheus text exposition format.
+  pub fn prometheus(&self) -> String {
+    let mut out = String::new();
+
+    let mut metric = |name: &str, kind: &str, help: &str, samples: &[(Option<&str>, f64)]| {
+      let _ = writeln!(out, "# HELP ddns_route53_{name} {help}");
+      let _ = writeln!(out, "# TYPE ddns_route53_{name} {kind}");
+
+      // domain names are validated, so they never need escaping in labels
+      for (domain, value) in samples {
+        let _ = match domain {
+          Some(domain) => writeln!(out, "ddns_route53_{name}{{domain=\"{domain}\"}} {value}"),
+          None => writeln!(out, "ddns_route53_{name} {value}"),
+        };
+      }
+    };
+
+    let per_domain = |value: fn(&DomainMetrics) -> Option<f64>| -> Vec<(Option<&str>, f64)> {
+      self
+        .domains
+        .iter()
+        .filter_map(|d| Some((Some(d.name.as_str()), value(d)?)))
+        .collect()
+    };
+
+    metric(
+      "last_pass_timestamp_seconds",
+      "gauge",
+      "Time the last update pass completed.",
+      &[(None, self.time.timestamp() as f64)],
+    );
+
+    metric(
+      "ip_changes_total",
+      "counter",
+      "Number of times the public IP has changed.",
+      &[(None, self.ip_changes as f64)],
+    );
+
+    metric(
+      "ip_failures",
+      "gauge",
+      "Number of consecutive failures to determine the public IP.",
+      &[(None, self.ip_failures as f64)],
+    );
+
+    metric(
+      "route53_throttled_total",
+      "counter",
+      "Number of Route 53 requests that were throttled.",
+      &[(None, self.throttled as f64)],
+    );
+
+    metric(
+      "route53_errors_total",
+      "counter",
+      "Number of Route 53 requests that failed, including throttled ones.",
+      &[(None, self.route53_errors as f64)],
+    );
+
+    metric(
+      "healthy",
+      "gauge",
+      "Whether the public IP was determined and every update succeeded in the last pass.",
+      &[(None, self.healthy as u8 as f64)],
+    );
+
+    metric(
+      "updates_total",
+      "counter",
+      "Number of successful record updates.",
+      &per_domain(|d| Some(d.updates as f64)),
+    );
+
+    metric(
+      "update_failures_total",
+      "counter",
+      "Number of failed update attempts.",
+      &per_domain(|d| Some(d.failures as f64)),
+    );
+
+    metric(
+      "consecutive_failures",
+      "gauge",
+      "Number of failed update attempts since the last successful update.",
+      &per_domain(|d| Some(d.consecutive_failures as f64)),
+    );
+
+    metric(
+      "last_change_timestamp_seconds",
+      "gauge",
+      "Time of the most recent successful record update.",
+      &per_domain(|d| Some(d.last_change?.timestamp() as f64)),
+    );
+
+    metric(
+      "paused",
+      "gauge",
+      "Whether updates of the domain are paused.",
+      &per_domain(|d| Some(d.paused as u8 as f64)),
+    );
+
+    out
+  }
+
+  /// Returns every value of the snapshot, for sinks that report values one
+  /// by one.
+  pub fn samples(&self) -> Vec<Sample<'_>> {
+    let mut samples = vec![
+      Sample::new("last_pass", None, self.time.timestamp() as u64),
+      Sample::new("ip_changes", None, self.ip_changes),
+      Sample::new("ip_failures", None, self.ip_failures),
+      Sample::new("route53_throttled", None, self.throttled),
+      Sample::new("route53_errors", None, self.route53_errors),
+      Sample::new("healthy", None, self.healthy as u64),
+    ];
+
+    for domain in &self.domains {
+      let name = Some(domain.name.as_str());
+
+      samples.extend([
+        Sample::new("updates", name, domain.updates),
+        Sample::new("failures", name, domain.failures),
+        Sample::new("consecutive_failures", name, domain.consecutive_failures),
+        Sample::new("paused", name, domain.paused as u64),
+      ]);
+
+      if let Some(time) = domain.last_change {
+        samples.push(Sample::new("last_change", name, time.timestamp() as u64));
+      }
+    }
+
+    samples
+  }
+
+  /// Renders the metrics in the Graphite plaintext format, with each path
+  /// starting with a prefix.
+  ///
+  /// The dots of domain names separate path components in Graphite, so they
+  /// are replaced with underscores.
+  pub fn graphite(&self, prefix: &str) -> String {
+    let mut out = String::new();
+    let time = self.time.timestamp();
+
+    for sample in self.samples() {
+      let _ = match sample.domain {
+        Some(domain) => writeln!(
+          out,
+          "{prefix}.domains.{}.{} {} {time}",
+          domain.replace('.', "_"),
+          sample.metric,
+          sample.value
+        ),
+
+        None => writeln!(out, "{prefix}.{} {} {time}", sample.metric, sample.value),
+      };
+    }
+
+    out
+  }
+}
+
+/// Serves the metrics of the latest update pass for Prometheus to scrape.
+pub struct Exporter {
+  /// Metrics of the latest pass in the Prometheus text format, once a pass
+  /// has completed.
+  latest: Arc<Mutex<Option<String>>>,
+}
+
+impl Exporter {
+  /// Serves the metrics at `/metrics` on an address.
+  pub async fn listen(addr: SocketAddr) -> Result<Self> {
+    let latest = Arc::new(Mutex::new(None::<String>));
+    let metrics = latest.clone();
+
+    server::listen(addr, "Prometheus metrics", move |method, path| {
+      let text = metrics.lock().unwrap().clone();
+
+      match (method, path, text) {
+        ("GET", "/metrics", Some(text)) => Response {
+          status: "200 OK",
+          content_type: "text/plain; version=0.0.4",
+          body: text,
+        },
+
+        // scrapes fail until the first pass so that a daemon that never
+        // completes one is noticed
+        ("GET", "/metrics", None) => Response {
+          status: "503 Service Unavailable",
+          content_type: "text/plain",
+          body: "No update pass has completed yet.\n".into(),
+        },
+
+        _ => Response {
+          status: "404 Not Found",
+          content_type: "text/plain",
+          body: "Not found.\n".into(),
+        },
+      }
+    })
+    .await?;
+
+    Ok(Self { latest })
+  }
+
+  /// Replaces the served metrics with those of a pass.
+  pub fn publish(&self, snapshot: &Snapshot) {
+    *self.latest.lock().unwrap() = Some(snapshot.prometheus());
+  }
+}
+
+/// A single value of a metric, optionally of a domain.
+pub struct Sample<'a> {
+  pub metric: &'static str,
+  pub domain: Option<&'a str>,
+  pub value: u64,
+}
+
+impl<'a> Sample<'a> {
+  const fn new(metric: &'static str, domain: Option<&'a str>, value: u64) -> Self {
+    Self {
+      metric,
+      domain,
+      value,
+    }
+  }
+}
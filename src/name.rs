@@ -0,0 +1,229 @@
+use std::{fmt, ops::Deref, str::FromStr};
+
+use anyhow::{bail, Result};
+
+/// Maximum length of a domain name in presentation format, without the
+/// trailing dot.
+const MAX_LEN: usize = 253;
+
+/// Maximum length of a single label.
+const MAX_LABEL_LEN: usize = 63;
+
+/// A validated domain name in normalized form: lowercase and without a
+/// trailing dot.
+///
+/// Route 53 writes names with a trailing dot and escapes some characters as
+/// octal codes, so names it returns should be converted with
+/// [`DomainName::from_route53`] before they are compared.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DomainName(String);
+
+impl DomainName {
+  /// Converts a name returned by Route 53, such as a hosted zone or record
+  /// name, decoding escapes like `\052` for `*`.
+  ///
+  /// Unlike names parsed from user input, names from Route 53 may have a
+  /// single label, since private hosted zones can be named like `internal`.
+  pub fn from_route53(name: &str) -> Result<Self> {
+    let mut decoded = String::with_capacity(name.len());
+    let mut chars = name.chars();
+
+    while let Some(c) = chars.next() {
+      if c != '\\' {
+        decoded.push(c);
+        continue;
+      }
+
+      let code: String = chars.by_ref().take(3).collect();
+
+      match u8::from_str_radix(&code, 8) {
+        Ok(byte) if code.len() == 3 => decoded.push(byte as char),
+        _ => bail!("Invalid escape in name {name:?}."),
+      }
+    }
+
+    Self::parse(&decoded, 1)
+  }
+
+  /// Returns the name as a string.
+  pub fn as_str(&self) -> &str {
+    &self.0
+  }
+
+  /// Returns `true` if this name is the given zone or one of its subdomains.
+  pub fn is_within(&self, zone: &DomainName) -> bool {
+    match self.0.strip_suffix(&zone.0) {
+      Some(rest) => rest.is_empty() || rest.ends_with('.'),
+      None => false,
+    }
+  }
+
+  /// Returns the number of labels in the name.
+  pub fn depth(&self) -> usize {
+    self.0.split('.').count()
+  }
+
+  /// Parses and normalizes a domain name with at least the given number of
+  /// labels.
+  ///
+  /// Names are case-insensitive and may have a trailing dot. Labels may
+  /// contain ASCII letters, digits, hyphens not at either end, and
+  /// underscores for service names like `_acme-challenge`. The leftmost label
+  /// may be `*` for a wildcard record. Internationalized names must be given
+  /// in their `xn--` form.
+  fn parse(s: &str, min_labels: usize) -> Result<Self> {
+    let name = s.strip_suffix('.').unwrap_or(s).to_ascii_lowercase();
+
+    if name.is_empty() {
+      bail!("Domain name is empty.");
+    }
+
+    if name.len() > MAX_LEN {
+      bail!("Domain name {s:?} is longer than {MAX_LEN} characters.");
+    }
+
+    let labels: Vec<&str> = name.split('.').collect();
+
+    if labels.len() < min_labels {
+      bail!("Domain name {s:?} has no parent domain.");
+    }
+
+    for (i, label) in labels.iter().enumerate() {
+      if label.is_empty() {
+        bail!("Domain name {s:?} has an empty label.");
+      }
+
+      if *label == "*" && i == 0 {
+        continue;
+      }
+
+      if label.len() > MAX_LABEL_LEN {
+        bail!("Label `{label}` of {s:?} is longer than {MAX_LABEL_LEN} characters.");
+      }
+
+      if let Some(c) = label
+        .chars()
+        .find(|&c| !c.is_ascii_alphanumeric() && c != '-' && c != '_')
+      {
+        bail!("Domain name {s:?} contains invalid character {c:?}.");
+      }
+
+      if label.starts_with('-') || label.ends_with('-') {
+        bail!("Label `{label}` of {s:?} starts or ends with a hyphen.");
+      }
+    }
+
+    if labels[labels.len() - 1].chars().all(|c| c.is_ascii_digit()) {
+      bail!("Domain name {s:?} has a numeric top-level domain.");
+    }
+
+    Ok(Self(name))
+  }
+}
+
+impl FromStr for DomainName {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    Self::parse(s, 2)
+  }
+}
+
+impl Deref for DomainName {
+  type Target = str;
+
+  fn deref(&self) -> &str {
+    &self.0
+  }
+}
+
+impl fmt::Display for DomainName {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(&self.0)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn accepts_valid_names() {
+    let label = "a".repeat(MAX_LABEL_LEN);
+    let longest = format!("{label}.{label}.{label}.{}", "b".repeat(61));
+
+    let names = [
+      ("home.example.com", "home.example.com"),
+      // case-insensitive, with an optional trailing dot
+      ("Home.Example.COM.", "home.example.com"),
+      // hyphens inside labels and underscores for service names
+      ("my-host.example.com", "my-host.example.com"),
+      ("_acme-challenge.example.com", "_acme-challenge.example.com"),
+      // numeric labels other than the top-level domain
+      ("123.example.com", "123.example.com"),
+      ("xn--bcher-kva.example", "xn--bcher-kva.example"),
+      // a leftmost wildcard
+      ("*.example.com", "*.example.com"),
+      // the longest label and name
+      (&format!("{label}.com"), &format!("{label}.com")),
+      (&longest, &longest),
+    ];
+
+    for (name, normalized) in names {
+      assert_eq!(
+        name.parse::<DomainName>().unwrap().as_str(),
+        normalized,
+        "{name}"
+      );
+    }
+  }
+
+  #[test]
+  fn rejects_invalid_names() {
+    let label = "a".repeat(MAX_LABEL_LEN + 1);
+    let longest = format!("{0}.{0}.{0}.{1}", "a".repeat(MAX_LABEL_LEN), "b".repeat(62));
+
+    let names = [
+      "",
+      ".",
+      // a single label
+      "localhost",
+      // empty labels
+      "home..example.com",
+      ".example.com",
+      // a label or name that is too long
+      &format!("{label}.com"),
+      &longest,
+      // invalid characters
+      "home example.com",
+      "home!.example.com",
+      "bücher.example",
+      // hyphens at either end of a label
+      "-home.example.com",
+      "home-.example.com",
+      "home.example.-com",
+      // a numeric top-level domain
+      "example.123",
+      "192.0.2.1",
+      // a wildcard anywhere but leftmost, or within a label
+      "home.*.example.com",
+      "*home.example.com",
+    ];
+
+    for name in names {
+      assert!(name.parse::<DomainName>().is_err(), "{name:?}");
+    }
+  }
+
+  #[test]
+  fn converts_names_from_route53() {
+    let name = DomainName::from_route53("\\052.example.com.").unwrap();
+
+    assert_eq!(name.as_str(), "*.example.com");
+    assert_eq!(
+      DomainName::from_route53("internal.").unwrap().as_str(),
+      "internal"
+    );
+    assert!(DomainName::from_route53("bad\\05.example.com.").is_err());
+  }
+}
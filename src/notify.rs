@@ -0,0 +1,620 @@
+mod apprise;
+mod desktop;
+mod discord;
+mod gotify;
+mod matrix;
+mod pushover;
+mod slack;
+mod webhook;
+
+use std::{net::IpAddr, sync::Arc, time::Duration};
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, TimeDelta, Utc};
+use clap::ValueEnum;
+use reqwest::Url;
+use serde_json::json;
+use tokio::task::JoinSet;
+use tracing::error;
+
+use crate::http::Http;
+
+/// Command line options for notifications.
+#[derive(clap::Args)]
+#[command(next_help_heading = "Notifications")]
+pub struct NotifyOptions {
+  /// Show a desktop notification when the public IP changes or a domain starts
+  /// failing to update.
+  #[arg(long)]
+  desktop_notifications: bool,
+
+  /// Collect routine desktop notifications into a digest sent once per period.
+  /// Failures are still shown immediately.
+  #[arg(long, value_name = "PERIOD", requires = "desktop_notifications")]
+  desktop_digest: Option<Period>,
+
+  /// Base URL of the Matrix homeserver to post notifications through.
+  #[arg(long, value_name = "URL", requires_all = ["matrix_access_token", "matrix_room"])]
+  matrix_homeserver: Option<Url>,
+
+  /// Access token of the Matrix account that posts notifications.
+  #[arg(long, value_name = "TOKEN", requires = "matrix_homeserver")]
+  matrix_access_token: Option<String>,
+
+  /// ID of the Matrix room to post notifications to.
+  #[arg(long, value_name = "ROOM_ID", requires = "matrix_homeserver")]
+  matrix_room: Option<String>,
+
+  /// Collect routine Matrix notifications into a digest sent once per period.
+  #[arg(long, value_name = "PERIOD", requires = "matrix_homeserver")]
+  matrix_digest: Option<Period>,
+
+  /// API token of the Pushover application to push notifications through.
+  #[arg(long, value_name = "TOKEN", requires = "pushover_user_key")]
+  pushover_app_token: Option<String>,
+
+  /// Key of the Pushover user or group to push notifications to.
+  #[arg(long, value_name = "KEY", requires = "pushover_app_token")]
+  pushover_user_key: Option<String>,
+
+  /// Priority of Pushover notifications, from -2 (lowest) to 1 (high).
+  #[arg(
+    long,
+    value_name = "PRIORITY",
+    default_value_t = 0,
+    allow_negative_numbers = true,
+    value_parser = clap::value_parser!(i8).range(-2..=1),
+  )]
+  pushover_priority: i8,
+
+  /// Collect routine Pushover notifications into a digest sent once per period.
+  #[arg(long, value_name = "PERIOD", requires = "pushover_app_token")]
+  pushover_digest: Option<Period>,
+
+  /// Base URL of the Gotify server to push notifications to.
+  #[arg(long, value_name = "URL", requires = "gotify_app_token")]
+  gotify_url: Option<Url>,
+
+  /// Token of the Gotify application to push notifications as.
+  #[arg(long, value_name = "TOKEN", requires = "gotify_url")]
+  gotify_app_token: Option<String>,
+
+  /// Collect routine Gotify notifications into a digest sent once per period.
+  #[arg(long, value_name = "PERIOD", requires = "gotify_url")]
+  gotify_digest: Option<Period>,
+
+  /// Apprise URL of a service to send notifications to, such as
+  /// `pover://USER@TOKEN` or `discord://WEBHOOK_ID/WEBHOOK_TOKEN`. May be
+  /// repeated.
+  #[arg(long = "notify", value_name = "URL")]
+  notify_urls: Vec<Url>,
+
+  /// Collect routine notifications for `--notify` services into a digest sent
+  /// once per period.
+  #[arg(long, value_name = "PERIOD", requires = "notify_urls")]
+  notify_digest: Option<Period>,
+
+  /// Base URL of an Apprise API server to send notifications through for
+  /// services that are not supported natively.
+  #[arg(long, value_name = "URL")]
+  apprise_api: Option<Url>,
+
+  /// URL to POST notifications to as JSON. May be repeated.
+  #[arg(long, value_name = "URL")]
+  webhook_url: Vec<Url>,
+
+  /// Payload to POST to webhooks: generic `json` with the details of each
+  /// event, or the message format of `slack` or `discord` webhooks.
+  #[arg(long, value_enum, value_name = "FORMAT", default_value_t)]
+  webhook_format: WebhookFormat,
+
+  /// Collect routine webhook notifications into a digest sent once per
+  /// period.
+  #[arg(long, value_name = "PERIOD", requires = "webhook_url")]
+  webhook_digest: Option<Period>,
+
+  /// Number of consecutive failures of a domain after which every channel is
+  /// notified that it fails to update.
+  #[arg(
+    long,
+    value_name = "N",
+    default_value_t = 1,
+    value_parser = clap::value_parser!(u64).range(1..)
+  )]
+  notify_after_failures: u64,
+
+  /// Send a summary of the session to every notification channel when the
+  /// daemon exits.
+  #[arg(long)]
+  notify_summary: bool,
+}
+
+/// Something that happened that the user may want to be told about.
+#[derive(Clone)]
+pub enum Event {
+  /// The public IP changed.
+  IpChanged { from: IpAddr, to: IpAddr },
+  /// A domain's record was updated.
+  Updated { domain: String, ip: IpAddr },
+  /// Updates are held down because the public IP is flapping.
+  Flapping { between: Vec<IpAddr> },
+  /// A domain failed to update a number of times in a row after previously
+  /// succeeding.
+  UpdateFailed {
+    domain: String,
+    error: String,
+    failures: u64,
+  },
+  /// An inventory host stopped reporting its IP.
+  HostMissing {
+    domain: String,
+    last_seen: Option<DateTime<Utc>>,
+    deleted: bool,
+  },
+  /// The record of an inventory host was deleted because its lease lapsed.
+  LeaseExpired { domain: String },
+  /// Resolvers did not answer with the new IP of an updated domain in time.
+  Unverified {
+    domain: String,
+    ip: IpAddr,
+    error: String,
+  },
+  /// The daemon is exiting.
+  Stopped { summary: String },
+}
+
+/// How often a channel receives a digest of routine events.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Period {
+  Daily,
+  Weekly,
+}
+
+/// Payload of notifications posted to webhooks.
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum WebhookFormat {
+  /// JSON with the kind and details of the event.
+  #[default]
+  Json,
+  /// Message of a Slack incoming webhook.
+  Slack,
+  /// Message of a Discord webhook.
+  Discord,
+}
+
+/// A notification to send.
+pub struct Message {
+  /// Kind of event the message is about, such as `ip_changed`, or `digest`
+  /// for a digest of events.
+  pub kind: &'static str,
+  pub time: DateTime<Utc>,
+  pub summary: String,
+  pub body: String,
+  /// Details of the event as JSON, for webhooks.
+  pub details: serde_json::Value,
+}
+
+/// A destination for notifications.
+pub enum Channel {
+  /// Native desktop notifications.
+  Desktop,
+  /// Messages in a Matrix room.
+  Matrix(matrix::Room),
+  /// Pushover messages.
+  Pushover(pushover::Recipient),
+  /// Messages on a Gotify server.
+  Gotify(gotify::Server),
+  /// Messages posted by a Discord webhook.
+  Discord(discord::Webhook),
+  /// Messages posted by a Slack webhook.
+  Slack(slack::Webhook),
+  /// Events posted to a webhook as JSON.
+  Webhook(webhook::Webhook),
+  /// Messages forwarded by an Apprise API server.
+  Apprise(apprise::Api),
+}
+
+/// Sends notifications of events to subscribed channels.
+pub struct Notifier {
+  http: reqwest::Client,
+  /// Whether to send a summary of the session on exit.
+  summary: bool,
+  subscribers: Vec<Subscriber>,
+  /// Number of consecutive failures of a domain after which it is reported.
+  failure_threshold: u64,
+  /// Notifications being sent in the background.
+  tasks: JoinSet<()>,
+}
+
+struct Subscriber {
+  channel: Arc<Channel>,
+  digest: Option<Digest>,
+}
+
+/// Routine events collected for a channel until its digest is due.
+struct Digest {
+  due: DateTime<Utc>,
+  events: Vec<(DateTime<Utc>, Event)>,
+  period: Period,
+}
+
+impl Event {
+  /// Returns the name of the kind of event, for webhooks.
+  pub const fn kind(&self) -> &'static str {
+    match self {
+      Self::IpChanged { .. } => "ip_changed",
+      Self::Updated { .. } => "updated",
+      Self::Flapping { .. } => "flapping",
+      Self::UpdateFailed { .. } => "update_failed",
+      Self::HostMissing { .. } => "host_missing",
+      Self::LeaseExpired { .. } => "lease_expired",
+      Self::Unverified { .. } => "unverified",
+      Self::Stopped { .. } => "stopped",
+    }
+  }
+
+  /// Returns a short summary of the event.
+  pub const fn summary(&self) -> &'static str {
+    match self {
+      Self::IpChanged { .. } => "Public IP changed",
+      Self::Updated { .. } => "DNS record updated",
+      Self::Flapping { .. } => "Public IP is flapping",
+      Self::UpdateFailed { .. } => "DNS update failed",
+      Self::HostMissing { .. } => "Host stopped reporting",
+      Self::LeaseExpired { .. } => "DNS lease expired",
+      Self::Unverified { .. } => "DNS update not verified",
+      Self::Stopped { .. } => "DNS updates stopped",
+    }
+  }
+
+  /// Returns a description of the event.
+  pub fn body(&self) -> String {
+    match self {
+      Self::IpChanged { from, to } => format!("The public IP changed from {from} to {to}."),
+      Self::Updated { domain, ip } => format!("Updated `{domain}` to {ip}."),
+      Self::Flapping { between } => format!(
+        "The public IP is flapping between {}. Updates are on hold.",
+        between
+          .iter()
+          .map(IpAddr::to_string)
+          .collect::<Vec<_>>()
+          .join(" and ")
+      ),
+      Self::UpdateFailed {
+        domain,
+        error,
+        failures: 1,
+      } => format!("Failed to update `{domain}`: {error}"),
+
+      Self::UpdateFailed {
+        domain,
+        error,
+        failures,
+      } => format!("Failed to update `{domain}` {failures} times in a row: {error}"),
+
+      Self::LeaseExpired { domain } => format!("Deleted `{domain}` because its lease lapsed."),
+      Self::Stopped { summary } => format!("The daemon is exiting.\n{summary}"),
+
+      Self::Unverified { domain, ip, error } => {
+        format!("Failed to verify that `{domain}` resolves to {ip}: {error}")
+      }
+
+      Self::HostMissing {
+        domain,
+        last_seen,
+        deleted,
+      } => {
+        let since = match last_seen {
+          Some(time) => format!("since {}", time.format("%F %T")),
+          None => "at all".into(),
+        };
+
+        match deleted {
+          true => format!("`{domain}` has not reported its IP {since}. Its record was deleted."),
+          false => format!("`{domain}` has not reported its IP {since}."),
+        }
+      }
+    }
+  }
+
+  /// Returns the details of the event as JSON.
+  pub fn details(&self) -> serde_json::Value {
+    match self {
+      Self::IpChanged { from, to } => json!({ "from": from, "to": to }),
+      Self::Updated { domain, ip } => json!({ "domain": domain, "ip": ip }),
+      Self::Flapping { between } => json!({ "between": between }),
+      Self::LeaseExpired { domain } => json!({ "domain": domain }),
+      Self::Stopped { summary } => json!({ "summary": summary }),
+
+      Self::Unverified { domain, ip, error } => {
+        json!({ "domain": domain, "ip": ip, "error": error })
+      }
+
+      Self::UpdateFailed {
+        domain,
+        error,
+        failures,
+      } => json!({ "domain": domain, "error": error, "failures": failures }),
+
+      Self::HostMissing {
+        domain,
+        last_seen,
+        deleted,
+      } => json!({ "domain": domain, "last_seen": last_seen, "deleted": deleted }),
+    }
+  }
+
+  /// Returns a message about the event.
+  fn message(&self, time: DateTime<Utc>) -> Message {
+    Message {
+      kind: self.kind(),
+      time,
+      summary: self.summary().into(),
+      body: self.body(),
+      details: self.details(),
+    }
+  }
+
+  /// Returns `true` if the event should be sent immediately, even to channels
+  /// that receive digests, because it is a problem or because there will be
+  /// no later digest.
+  pub const fn is_urgent(&self) -> bool {
+    matches!(
+      self,
+      Self::Flapping { .. }
+        | Self::UpdateFailed { .. }
+        | Self::Unverified { .. }
+        | Self::HostMissing { .. }
+        | Self::Stopped { .. }
+    )
+  }
+}
+
+impl Period {
+  const fn duration(self) -> TimeDelta {
+    match self {
+      Self::Daily => TimeDelta::days(1),
+      Self::Weekly => TimeDelta::weeks(1),
+    }
+  }
+}
+
+impl Channel {
+  /// Returns a short name for the channel for use in logs.
+  const fn name(&self) -> &'static str {
+    match self {
+      Self::Desktop => "desktop",
+      Self::Matrix(_) => "Matrix",
+      Self::Pushover(_) => "Pushover",
+      Self::Gotify(_) => "Gotify",
+      Self::Discord(_) => "Discord",
+      Self::Slack(_) => "Slack",
+      Self::Webhook(_) => "webhook",
+      Self::Apprise(_) => "Apprise",
+    }
+  }
+
+  /// Sends a notification through the channel.
+  async fn send(&self, http: &reqwest::Client, message: &Message) -> Result<()> {
+    match self {
+      Self::Desktop => desktop::show(message).await,
+      Self::Matrix(room) => room.send(http, message).await,
+      Self::Pushover(recipient) => recipient.send(http, message).await,
+      Self::Gotify(server) => server.send(http, message).await,
+      Self::Discord(webhook) => webhook.send(http, message).await,
+      Self::Slack(webhook) => webhook.send(http, message).await,
+      Self::Webhook(webhook) => webhook.send(http, message).await,
+      Self::Apprise(api) => api.send(http, message).await,
+    }
+  }
+}
+
+impl Notifier {
+  /// Creates a notifier with the channels enabled by command line options.
+  pub fn new(options: NotifyOptions, http: &Http) -> Result<Self> {
+    let mut notifier = Self {
+      http: http.any.clone(),
+      summary: options.notify_summary,
+      subscribers: Vec::new(),
+      failure_threshold: options.notify_after_failures,
+      tasks: JoinSet::new(),
+    };
+
+    if options.desktop_notifications {
+      if !cfg!(feature = "desktop-notifications") {
+        bail!(
+          "This build does not support desktop notifications. Rebuild it with \
+           `--features desktop-notifications`."
+        );
+      }
+
+      notifier.subscribe(Channel::Desktop, options.desktop_digest);
+    }
+
+    if let (Some(homeserver), Some(access_token), Some(room_id)) = (
+      options.matrix_homeserver,
+      options.matrix_access_token,
+      options.matrix_room,
+    ) {
+      let room = matrix::Room {
+        homeserver,
+        access_token,
+        room_id,
+      };
+
+      notifier.subscribe(Channel::Matrix(room), options.matrix_digest);
+    }
+
+    if let (Some(app_token), Some(user_key)) =
+      (options.pushover_app_token, options.pushover_user_key)
+    {
+      let recipient = pushover::Recipient {
+        app_token,
+        user_key,
+        priority: options.pushover_priority,
+      };
+
+      notifier.subscribe(Channel::Pushover(recipient), options.pushover_digest);
+    }
+
+    if let (Some(url), Some(app_token)) = (options.gotify_url, options.gotify_app_token) {
+      let server = gotify::Server { url, app_token };
+
+      notifier.subscribe(Channel::Gotify(server), options.gotify_digest);
+    }
+
+    for url in &options.notify_urls {
+      let channel = apprise::parse(url, options.apprise_api.as_ref())
+        .with_context(|| format!("Invalid `{}://` notification URL.", url.scheme()))?;
+
+      notifier.subscribe(channel, options.notify_digest);
+    }
+
+    for url in options.webhook_url {
+      let channel = match options.webhook_format {
+        WebhookFormat::Json => Channel::Webhook(webhook::Webhook { url }),
+        WebhookFormat::Slack => Channel::Slack(slack::Webhook { url }),
+        WebhookFormat::Discord => Channel::Discord(discord::Webhook { url }),
+      };
+
+      notifier.subscribe(channel, options.webhook_digest);
+    }
+
+    Ok(notifier)
+  }
+
+  /// Subscribes a channel to events, optionally collecting routine events
+  /// into a digest sent once per period.
+  fn subscribe(&mut self, channel: Channel, digest: Option<Period>) {
+    self.subscribers.push(Subscriber {
+      channel: Arc::new(channel),
+      digest: digest.map(|period| Digest {
+        due: Utc::now() + period.duration(),
+        events: Vec::new(),
+        period,
+      }),
+    });
+  }
+
+  /// Creates a notifier without any channels, for runs that must not send
+  /// notifications.
+  pub fn silent(http: &Http) -> Self {
+    Self {
+      http: http.any.clone(),
+      summary: false,
+      subscribers: Vec::new(),
+      failure_threshold: 1,
+      tasks: JoinSet::new(),
+    }
+  }
+
+  /// Returns `true` if a summary of the session should be sent on exit.
+  pub const fn sends_summary(&self) -> bool {
+    self.summary
+  }
+
+  /// Returns the number of consecutive failures of a domain after which
+  /// channels are notified.
+  pub const fn failure_threshold(&self) -> u64 {
+    self.failure_threshold
+  }
+
+  /// Sends a notification of an event, or adds it to the digests of channels
+  /// that receive them if it is routine.
+  pub fn notify(&mut self, event: Event) {
+    let now = Utc::now();
+
+    // forget notifications that have already been sent
+    while self.tasks.try_join_next().is_some() {}
+
+    for subscriber in &mut self.subscribers {
+      match &mut subscriber.digest {
+        Some(digest) if !event.is_urgent() => digest.events.push((now, event.clone())),
+
+        _ => send(
+          &mut self.tasks,
+          &self.http,
+          &subscriber.channel,
+          event.message(now),
+        ),
+      }
+    }
+  }
+
+  /// Sends the digests that are due.
+  pub fn send_digests(&mut self) {
+    let now = Utc::now();
+
+    for subscriber in &mut self.subscribers {
+      let Some(digest) = &mut subscriber.digest else {
+        continue;
+      };
+
+      if now < digest.due {
+        continue;
+      }
+
+      let since = digest.due - digest.period.duration();
+
+      digest.due = now + digest.period.duration();
+
+      if digest.events.is_empty() {
+        continue;
+      }
+
+      let mut body = format!("Since {}:", since.format("%F %T"));
+      let mut events = Vec::new();
+
+      for (time, event) in digest.events.drain(..) {
+        body.push_str(&format!("\n• [{}] {}", time.format("%F %T"), event.body()));
+
+        events.push(json!({
+          "event": event.kind(),
+          "time": time,
+          "message": event.body(),
+          "details": event.details(),
+        }));
+      }
+
+      let summary = match digest.period {
+        Period::Daily => "Daily DNS update summary",
+        Period::Weekly => "Weekly DNS update summary",
+      };
+
+      send(
+        &mut self.tasks,
+        &self.http,
+        &subscriber.channel,
+        Message {
+          kind: "digest",
+          time: now,
+          summary: summary.into(),
+          body,
+          details: json!({ "since": since, "events": events }),
+        },
+      );
+    }
+  }
+
+  /// Waits up to a timeout for notifications being sent in the background,
+  /// so that they are not lost when the daemon exits.
+  pub async fn flush(&mut self, timeout: Duration) {
+    let all = async { while self.tasks.join_next().await.is_some() {} };
+
+    if tokio::time::timeout(timeout, all).await.is_err() {
+      error!("Gave up waiting for notifications to be sent.");
+    }
+  }
+}
+
+/// Sends a message through a channel in the background.
+fn send(tasks: &mut JoinSet<()>, http: &reqwest::Client, channel: &Arc<Channel>, message: Message) {
+  let http = http.clone();
+  let channel = channel.clone();
+
+  tasks.spawn(async move {
+    if let Err(err) = channel.send(&http, &message).await {
+      error!("Failed to send {} notification. {err:#}", channel.name());
+    }
+  });
+}
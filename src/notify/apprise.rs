@@ -0,0 +1,181 @@
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::Url;
+use serde_json::json;
+
+use super::{discord, gotify, matrix, pushover, Channel, Message};
+
+/// An Apprise API server that forwards notifications to services that are not
+/// supported natively.
+pub struct Api {
+  /// Base URL of the Apprise API server.
+  pub server: Url,
+  /// Apprise URL of the service to notify.
+  pub target: Url,
+}
+
+impl Api {
+  /// Asks the server to send a message to the target service.
+  pub async fn send(&self, http: &reqwest::Client, message: &Message) -> Result<()> {
+    let mut url = self.server.clone();
+
+    url
+      .path_segments_mut()
+      .map_err(|()| anyhow!("Invalid Apprise API server URL."))?
+      .pop_if_empty()
+      .extend(["notify", ""]);
+
+    http
+      .post(url)
+      .json(&json!({
+        "urls": self.target.as_str(),
+        "title": message.summary,
+        "body": message.body,
+      }))
+      .send()
+      .await?
+      .error_for_status()
+      .context("Apprise API server failed to send the message.")?;
+
+    Ok(())
+  }
+}
+
+/// Parses an Apprise URL into a channel.
+///
+/// Pushover, Gotify, Matrix, and Discord URLs are handled natively. Any other
+/// service is reached through the Apprise API server, if one is given.
+pub fn parse(url: &Url, server: Option<&Url>) -> Result<Channel> {
+  let channel = match url.scheme() {
+    "pover" => Channel::Pushover(pushover::Recipient {
+      app_token: host(url)?.into(),
+      user_key: user(url)?.into(),
+      priority: match query(url, "priority") {
+        None => 0,
+        Some(priority) => pushover_priority(&priority)?,
+      },
+    }),
+
+    scheme @ ("gotify" | "gotifys") => {
+      let mut segments = segments(url);
+
+      let Some(app_token) = segments.pop() else {
+        bail!("Missing Gotify application token.");
+      };
+
+      Channel::Gotify(gotify::Server {
+        url: http_url(url, scheme == "gotifys", &segments)?,
+        app_token,
+      })
+    }
+
+    scheme @ ("matrix" | "matrixs") => {
+      let segments = segments(url);
+
+      let [room_id] = &segments[..] else {
+        bail!("Expected exactly one Matrix room ID.");
+      };
+
+      Channel::Matrix(matrix::Room {
+        homeserver: http_url(url, scheme == "matrixs", &[])?,
+        access_token: user(url)?.into(),
+        room_id: room_id.clone(),
+      })
+    }
+
+    "discord" => {
+      let segments = segments(url);
+
+      let [token] = &segments[..] else {
+        bail!("Expected a Discord webhook token after the webhook ID.");
+      };
+
+      let mut webhook = Url::parse("https://discord.com/api/webhooks")?;
+
+      webhook
+        .path_segments_mut()
+        .map_err(|()| anyhow!("Invalid Discord webhook URL."))?
+        .extend([host(url)?, token]);
+
+      Channel::Discord(discord::Webhook { url: webhook })
+    }
+
+    scheme => {
+      let Some(server) = server else {
+        bail!("Unsupported service `{scheme}`. Pass `--apprise-api` to send it through Apprise.");
+      };
+
+      Channel::Apprise(Api {
+        server: server.clone(),
+        target: url.clone(),
+      })
+    }
+  };
+
+  Ok(channel)
+}
+
+/// Converts a Pushover priority by name or number.
+fn pushover_priority(priority: &str) -> Result<i8> {
+  Ok(match priority {
+    "lowest" | "-2" => -2,
+    "low" | "-1" => -1,
+    "normal" | "0" => 0,
+    "high" | "1" => 1,
+    _ => bail!("Unsupported Pushover priority `{priority}`."),
+  })
+}
+
+/// Returns the host of a URL, which Apprise uses for tokens and IDs as well as
+/// host names.
+fn host(url: &Url) -> Result<&str> {
+  url
+    .host_str()
+    .filter(|host| !host.is_empty())
+    .ok_or_else(|| anyhow!("Missing host."))
+}
+
+/// Returns the user name of a URL, which Apprise uses for keys and tokens.
+fn user(url: &Url) -> Result<&str> {
+  match url.username() {
+    "" => bail!("Missing user."),
+    user => Ok(user),
+  }
+}
+
+/// Returns the non-empty path segments of a URL.
+fn segments(url: &Url) -> Vec<String> {
+  url
+    .path_segments()
+    .into_iter()
+    .flatten()
+    .filter(|segment| !segment.is_empty())
+    .map(String::from)
+    .collect()
+}
+
+/// Returns the value of a query parameter of a URL.
+fn query(url: &Url, name: &str) -> Option<String> {
+  url
+    .query_pairs()
+    .find(|(key, _)| key == name)
+    .map(|(_, value)| value.into_owned())
+}
+
+/// Converts the host, port, and the given path of an Apprise URL into an HTTP
+/// URL.
+fn http_url(url: &Url, secure: bool, path: &[String]) -> Result<Url> {
+  let scheme = if secure { "https" } else { "http" };
+  let mut http = Url::parse(&format!("{scheme}://{}", host(url)?))?;
+
+  http
+    .set_port(url.port())
+    .map_err(|()| anyhow!("Invalid port."))?;
+
+  http
+    .path_segments_mut()
+    .map_err(|()| anyhow!("Invalid URL."))?
+    .clear()
+    .extend(path);
+
+  Ok(http)
+}
@@ -0,0 +1,29 @@
+use anyhow::Result;
+
+use super::Message;
+
+/// Shows a desktop notification.
+#[cfg(feature = "desktop-notifications")]
+pub async fn show(message: &Message) -> Result<()> {
+  use notify_rust::Notification;
+
+  let summary = message.summary.clone();
+  let body = message.body.clone();
+
+  tokio::task::spawn_blocking(move || {
+    Notification::new()
+      .appname(env!("CARGO_PKG_NAME"))
+      .summary(&summary)
+      .body(&body)
+      .show()
+  })
+  .await??;
+
+  Ok(())
+}
+
+/// Desktop notifications are not supported in this build.
+#[cfg(not(feature = "desktop-notifications"))]
+pub async fn show(_message: &Message) -> Result<()> {
+  anyhow::bail!("This build does not support desktop notifications.");
+}
@@ -0,0 +1,28 @@
+use anyhow::{Context, Result};
+use reqwest::Url;
+use serde_json::json;
+
+use super::Message;
+
+/// A Discord webhook that notifications are posted to.
+pub struct Webhook {
+  /// URL of the webhook, including its ID and token.
+  pub url: Url,
+}
+
+impl Webhook {
+  /// Posts a message through the webhook.
+  pub async fn send(&self, http: &reqwest::Client, message: &Message) -> Result<()> {
+    http
+      .post(self.url.clone())
+      .json(&json!({
+        "content": format!("**{}**\n{}", message.summary, message.body),
+      }))
+      .send()
+      .await?
+      .error_for_status()
+      .context("Discord rejected the message.")?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,40 @@
+use anyhow::{anyhow, Context, Result};
+use reqwest::Url;
+use serde_json::json;
+
+use super::Message;
+
+/// A Gotify server that notifications are pushed to.
+pub struct Server {
+  /// Base URL of the server, such as `https://gotify.example.com`.
+  pub url: Url,
+  /// Token of the Gotify application that sends messages.
+  pub app_token: String,
+}
+
+impl Server {
+  /// Pushes a message to the server.
+  pub async fn send(&self, http: &reqwest::Client, message: &Message) -> Result<()> {
+    let mut url = self.url.clone();
+
+    url
+      .path_segments_mut()
+      .map_err(|()| anyhow!("Invalid Gotify server URL."))?
+      .pop_if_empty()
+      .push("message");
+
+    http
+      .post(url)
+      .header("X-Gotify-Key", &self.app_token)
+      .json(&json!({
+        "title": message.summary,
+        "message": message.body,
+      }))
+      .send()
+      .await?
+      .error_for_status()
+      .context("Gotify server rejected the message.")?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,55 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use anyhow::{Context, Result};
+use reqwest::Url;
+use serde_json::json;
+
+use super::Message;
+
+/// A Matrix room that notifications are posted to as messages.
+pub struct Room {
+  /// Base URL of the homeserver, such as `https://matrix.org`.
+  pub homeserver: Url,
+  /// Access token of the account that posts messages.
+  pub access_token: String,
+  /// ID of the room, such as `!abc123:matrix.org`.
+  pub room_id: String,
+}
+
+impl Room {
+  /// Posts a message to the room.
+  pub async fn send(&self, http: &reqwest::Client, message: &Message) -> Result<()> {
+    // transaction IDs only need to be unique per access token, so a timestamp
+    // and a counter are sufficient
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let txn_id = format!(
+      "{}.{}",
+      chrono::Utc::now().timestamp_millis(),
+      COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+
+    let mut url = self.homeserver.clone();
+
+    url
+      .path_segments_mut()
+      .map_err(|()| anyhow::anyhow!("Invalid Matrix homeserver URL."))?
+      .pop_if_empty()
+      .extend(["_matrix", "client", "v3", "rooms", &self.room_id])
+      .extend(["send", "m.room.message", &txn_id]);
+
+    http
+      .put(url)
+      .bearer_auth(&self.access_token)
+      .json(&json!({
+        "msgtype": "m.text",
+        "body": format!("{}\n{}", message.summary, message.body),
+      }))
+      .send()
+      .await?
+      .error_for_status()
+      .context("Matrix homeserver rejected the message.")?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,39 @@
+use anyhow::{Context, Result};
+
+use super::Message;
+
+/// Endpoint of the Pushover message API.
+const API_URL: &str = "https://api.pushover.net/1/messages.json";
+
+/// A Pushover user or group that notifications are pushed to.
+pub struct Recipient {
+  /// API token of the Pushover application that sends messages.
+  pub app_token: String,
+  /// Key of the user or group that receives messages.
+  pub user_key: String,
+  /// Priority of messages, from -2 (lowest) to 1 (high).
+  pub priority: i8,
+}
+
+impl Recipient {
+  /// Pushes a message to the recipient.
+  pub async fn send(&self, http: &reqwest::Client, message: &Message) -> Result<()> {
+    let priority = self.priority.to_string();
+
+    http
+      .post(API_URL)
+      .form(&[
+        ("token", self.app_token.as_str()),
+        ("user", self.user_key.as_str()),
+        ("title", message.summary.as_str()),
+        ("message", message.body.as_str()),
+        ("priority", priority.as_str()),
+      ])
+      .send()
+      .await?
+      .error_for_status()
+      .context("Pushover rejected the message.")?;
+
+    Ok(())
+  }
+}
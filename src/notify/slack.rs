@@ -0,0 +1,28 @@
+use anyhow::{Context, Result};
+use reqwest::Url;
+use serde_json::json;
+
+use super::Message;
+
+/// A Slack incoming webhook that notifications are posted to.
+pub struct Webhook {
+  /// URL of the webhook, such as `https://hooks.slack.com/services/...`.
+  pub url: Url,
+}
+
+impl Webhook {
+  /// Posts a message through the webhook.
+  pub async fn send(&self, http: &reqwest::Client, message: &Message) -> Result<()> {
+    http
+      .post(self.url.clone())
+      .json(&json!({
+        "text": format!("*{}*\n{}", message.summary, message.body),
+      }))
+      .send()
+      .await?
+      .error_for_status()
+      .context("Slack rejected the message.")?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,34 @@
+use anyhow::{Context, Result};
+use reqwest::Url;
+use serde_json::json;
+
+use super::Message;
+
+/// A webhook that notifications are posted to as generic JSON.
+pub struct Webhook {
+  pub url: Url,
+}
+
+impl Webhook {
+  /// Posts a message to the webhook.
+  ///
+  /// Along with the text of the message, the body has the kind of event it is
+  /// about and the event's details, for receivers that act on them.
+  pub async fn send(&self, http: &reqwest::Client, message: &Message) -> Result<()> {
+    http
+      .post(self.url.clone())
+      .json(&json!({
+        "event": message.kind,
+        "summary": message.summary,
+        "message": message.body,
+        "time": message.time,
+        "details": message.details,
+      }))
+      .send()
+      .await?
+      .error_for_status()
+      .context("Webhook rejected the message.")?;
+
+    Ok(())
+  }
+}
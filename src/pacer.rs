@@ -0,0 +1,123 @@
+use std::{
+  sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Mutex,
+  },
+  time::Duration,
+};
+
+use tokio::time::Instant;
+use tracing::{error, info};
+
+/// Longest interval between requests after repeated throttling.
+const MAX_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long a request may wait for its turn before the pacing is logged.
+const NOTICEABLE_WAIT: Duration = Duration::from_secs(5);
+
+/// Spaces out Route 53 API requests so that the daemon stays well under the
+/// limit of five requests per second that Route 53 enforces for each AWS
+/// account, leaving room for other automation in the same account.
+///
+/// When Route 53 throttles a request anyway, the interval between requests is
+/// doubled, and it recovers gradually as requests succeed again.
+struct Pacer {
+  /// Interval between requests chosen with `--route53-rate`.
+  base: Duration,
+  /// Current interval between requests.
+  interval: Duration,
+  /// Time the next request may be sent.
+  next: Option<Instant>,
+}
+
+static PACER: Mutex<Pacer> = Mutex::new(Pacer {
+  base: Duration::from_millis(500),
+  interval: Duration::from_millis(500),
+  next: None,
+});
+
+/// Number of requests Route 53 has throttled.
+static THROTTLED: AtomicU64 = AtomicU64::new(0);
+
+/// Number of requests that failed for any reason, including throttling.
+static FAILED: AtomicU64 = AtomicU64::new(0);
+
+/// Whether a noticeable wait has been logged, so that it is only logged once
+/// until the pace recovers.
+static WARNED: AtomicBool = AtomicBool::new(false);
+
+/// Sets the number of requests per second to send at most.
+pub fn configure(rate: f64) {
+  let mut pacer = PACER.lock().unwrap();
+
+  pacer.base = Duration::from_secs_f64(1.0 / rate);
+  pacer.interval = pacer.base;
+}
+
+/// Waits until the next request may be sent.
+pub async fn wait() {
+  let now = Instant::now();
+
+  let slot = {
+    let mut pacer = PACER.lock().unwrap();
+    let slot = pacer.next.map_or(now, |next| next.max(now));
+
+    pacer.next = Some(slot + pacer.interval);
+    slot
+  };
+
+  if slot - now >= NOTICEABLE_WAIT && !WARNED.swap(true, Ordering::Relaxed) {
+    info!(
+      "Pacing Route 53 requests to stay under the account's request limit. Requests are waiting \
+       up to {}s.",
+      (slot - now).as_secs()
+    );
+  }
+
+  tokio::time::sleep_until(slot).await;
+}
+
+/// Records that Route 53 throttled a request and slows down.
+pub fn throttled() {
+  THROTTLED.fetch_add(1, Ordering::Relaxed);
+
+  let mut pacer = PACER.lock().unwrap();
+
+  pacer.interval = (pacer.interval * 2).min(MAX_INTERVAL);
+
+  error!(
+    "Route 53 throttled a request. Slowing down to one request every {}ms.",
+    pacer.interval.as_millis()
+  );
+}
+
+/// Records that a request succeeded, recovering the pace after throttling.
+pub fn succeeded() {
+  let mut pacer = PACER.lock().unwrap();
+
+  if pacer.interval > pacer.base {
+    pacer.interval = (pacer.interval * 9 / 10).max(pacer.base);
+  } else {
+    WARNED.store(false, Ordering::Relaxed);
+  }
+}
+
+/// Records that a request failed.
+pub fn failed() {
+  FAILED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Returns the number of requests that have failed.
+pub fn failed_count() -> u64 {
+  FAILED.load(Ordering::Relaxed)
+}
+
+/// Restores the number of throttled requests from a previous run.
+pub fn restore_throttled_count(count: u64) {
+  THROTTLED.store(count, Ordering::Relaxed);
+}
+
+/// Returns the number of requests Route 53 has throttled.
+pub fn throttled_count() -> u64 {
+  THROTTLED.load(Ordering::Relaxed)
+}
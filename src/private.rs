@@ -0,0 +1,120 @@
+use std::{
+  net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket},
+  time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+use crate::{http::Http, ip::Family};
+
+/// Endpoint of the EC2 instance metadata service, unless another is given
+/// with `AWS_EC2_METADATA_SERVICE_ENDPOINT` like for the AWS SDK.
+const METADATA_ENDPOINT: &str = "http://169.254.169.254";
+
+/// How long a metadata session token is valid, in seconds. A token is only
+/// used for the request right after it.
+const TOKEN_TTL: &str = "60";
+
+/// Where the private address of this machine comes from, for records in
+/// private hosted zones.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PrivateSource {
+  /// The private address of the EC2 instance, from the instance metadata
+  /// service with IMDSv2.
+  Ec2Metadata,
+  /// The local address this machine sends traffic to the internet from.
+  Local,
+}
+
+impl PrivateSource {
+  /// Detects the private address of a family.
+  pub async fn detect(self, http: &Http, family: Family, timeout: Duration) -> Result<IpAddr> {
+    match self {
+      Self::Ec2Metadata => ec2_address(http, family, timeout)
+        .await
+        .context("Failed to get the private address from the EC2 instance metadata service."),
+
+      Self::Local => local_address(family),
+    }
+  }
+}
+
+/// Asks the instance metadata service for the private address of the
+/// instance's primary network interface.
+async fn ec2_address(http: &Http, family: Family, timeout: Duration) -> Result<IpAddr> {
+  let endpoint = std::env::var("AWS_EC2_METADATA_SERVICE_ENDPOINT");
+  let endpoint = endpoint.as_deref().unwrap_or(METADATA_ENDPOINT);
+  let base = format!("{}/latest", endpoint.trim_end_matches('/'));
+
+  let token = http
+    .direct
+    .put(format!("{base}/api/token"))
+    .header("X-aws-ec2-metadata-token-ttl-seconds", TOKEN_TTL)
+    .timeout(timeout)
+    .send()
+    .await?
+    .error_for_status()?
+    .text()
+    .await?;
+
+  let path = match family {
+    Family::V4 => "local-ipv4",
+    Family::V6 => "ipv6",
+  };
+
+  let response = http
+    .direct
+    .get(format!("{base}/meta-data/{path}"))
+    .header("X-aws-ec2-metadata-token", token)
+    .timeout(timeout)
+    .send()
+    .await?;
+
+  // the instance has no such address
+  if response.status() == reqwest::StatusCode::NOT_FOUND {
+    bail!("The instance has no private {family} address.");
+  }
+
+  let body = response.error_for_status()?.text().await?;
+  let ip: IpAddr = body
+    .trim()
+    .parse()
+    .with_context(|| format!("Invalid address {body:?}."))?;
+
+  match Family::of(ip) == family {
+    true => Ok(ip),
+    false => bail!("Expected an {family} address, got {ip}."),
+  }
+}
+
+/// Returns the address the system would send a packet to the internet from.
+///
+/// Connecting a UDP socket only chooses the route, so no packet is sent.
+fn local_address(family: Family) -> Result<IpAddr> {
+  let (bind, remote) = match family {
+    Family::V4 => (
+      IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+      IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)),
+    ),
+    Family::V6 => (
+      IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+      IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)),
+    ),
+  };
+
+  let socket = UdpSocket::bind(SocketAddr::new(bind, 0))?;
+
+  socket
+    .connect(SocketAddr::new(remote, 53))
+    .with_context(|| format!("There is no route to the internet over {family}."))?;
+
+  let ip = socket.local_addr()?.ip();
+
+  if ip.is_loopback() || ip.is_unspecified() {
+    bail!("This machine has no local {family} address.");
+  }
+
+  Ok(ip)
+}
@@ -0,0 +1,63 @@
+#[cfg(feature = "cloudflare")]
+mod cloudflare;
+mod route53;
+
+use std::{future::Future, net::IpAddr};
+
+use anyhow::Result;
+use serde::Deserialize;
+
+use crate::{name::DomainName, zone::ZoneChoice};
+
+#[cfg(feature = "cloudflare")]
+pub use self::cloudflare::Cloudflare;
+pub use self::route53::Route53;
+
+/// A DNS service that hosts the zones of domains.
+///
+/// Providers only find and upsert single A and AAAA records. Deleting
+/// records, which deleting the record of a stale family, reaping hosts, and
+/// expiring leases rely on, is only supported in Route 53, whose records the
+/// daemon changes through [`aws`](crate::aws) rather than this trait.
+pub trait DnsProvider {
+  /// Returns the ID of the zone that holds the records of a name.
+  fn resolve_zone(
+    &mut self,
+    name: &DomainName,
+    choice: &ZoneChoice,
+  ) -> impl Future<Output = Result<String>> + Send;
+
+  /// Returns the A or AAAA record of a name in a zone, depending on the
+  /// family of `ip`, if it exists.
+  fn find_record(
+    &self,
+    zone_id: &str,
+    name: &DomainName,
+    ip: IpAddr,
+  ) -> impl Future<Output = Result<Option<Record>>> + Send;
+
+  /// Sets the A or AAAA record of a name in a zone to a single IP.
+  fn upsert_record(
+    &self,
+    zone_id: &str,
+    name: &DomainName,
+    ip: IpAddr,
+    ttl: i64,
+  ) -> impl Future<Output = Result<()>> + Send;
+}
+
+/// A record as a provider serves it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Record {
+  pub values: Vec<String>,
+  pub ttl: i64,
+}
+
+/// Which provider hosts the zone of a domain, as chosen in the config file.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+  #[default]
+  Route53,
+  Cloudflare,
+}
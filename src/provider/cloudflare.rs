@@ -0,0 +1,419 @@
+use std::net::IpAddr;
+
+use anyhow::{bail, Context, Result};
+use reqwest::{Client, Method};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+
+use super::{DnsProvider, Record};
+use crate::{http::Http, name::DomainName, zone::ZoneChoice};
+
+/// Base URL of the Cloudflare API.
+const API_URL: &str = "https://api.cloudflare.com/client/v4";
+
+/// Zones in Cloudflare, managed with an API token that may edit their DNS
+/// records.
+pub struct Cloudflare {
+  http: Client,
+  token: String,
+  api_url: String,
+}
+
+/// Envelope of every API response.
+#[derive(Deserialize)]
+struct Response<T> {
+  success: bool,
+  #[serde(default)]
+  errors: Vec<ApiError>,
+  result: Option<T>,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+  code: i64,
+  message: String,
+}
+
+#[derive(Deserialize)]
+struct Zone {
+  id: String,
+}
+
+#[derive(Deserialize)]
+struct DnsRecord {
+  id: String,
+  content: String,
+  ttl: i64,
+}
+
+impl Cloudflare {
+  pub fn new(http: &Http, token: String) -> Self {
+    Self {
+      http: http.any.clone(),
+      token,
+      api_url: API_URL.into(),
+    }
+  }
+
+  /// Sends requests to another base URL than the public API, such as a proxy.
+  pub fn with_api_url(self, api_url: String) -> Self {
+    Self { api_url, ..self }
+  }
+
+  /// Sends a request to the API and returns the result.
+  async fn request<T: DeserializeOwned>(
+    &self,
+    method: Method,
+    path: &str,
+    query: &[(&str, &str)],
+    body: Option<Value>,
+  ) -> Result<T> {
+    let mut request = self
+      .http
+      .request(method, format!("{}{path}", self.api_url))
+      .bearer_auth(&self.token)
+      .query(query);
+
+    if let Some(body) = body {
+      request = request.json(&body);
+    }
+
+    let response: Response<T> = request
+      .send()
+      .await?
+      .json()
+      .await
+      .context("Invalid response from Cloudflare.")?;
+
+    if !response.success {
+      let errors: Vec<_> = response
+        .errors
+        .iter()
+        .map(|err| format!("{} (code {})", err.message, err.code))
+        .collect();
+
+      bail!("Cloudflare responded with an error. {}", errors.join("; "));
+    }
+
+    response
+      .result
+      .context("Cloudflare responded without a result.")
+  }
+
+  /// Sets the A or AAAA record of a name. An existing record is patched so
+  /// that settings such as whether it is proxied are kept, and any other
+  /// records of the name and type are deleted.
+  async fn upsert(&self, zone_id: &str, name: &str, ip: IpAddr, ttl: i64) -> Result<()> {
+    let records = self.records(zone_id, name, ip).await?;
+    let path = format!("/zones/{zone_id}/dns_records");
+
+    let Some((first, rest)) = records.split_first() else {
+      let body = json!({ "type": rr_type(ip), "name": name, "content": ip, "ttl": ttl });
+
+      self
+        .request::<Value>(Method::POST, &path, &[], Some(body))
+        .await?;
+
+      return Ok(());
+    };
+
+    let body = json!({ "content": ip, "ttl": ttl });
+    let path_of = |record: &DnsRecord| format!("{path}/{}", record.id);
+
+    self
+      .request::<Value>(Method::PATCH, &path_of(first), &[], Some(body))
+      .await?;
+
+    for record in rest {
+      self
+        .request::<Value>(Method::DELETE, &path_of(record), &[], None)
+        .await?;
+    }
+
+    Ok(())
+  }
+
+  /// Lists the A or AAAA records of a name.
+  async fn records(&self, zone_id: &str, name: &str, ip: IpAddr) -> Result<Vec<DnsRecord>> {
+    let path = format!("/zones/{zone_id}/dns_records");
+    let query = [("type", rr_type(ip)), ("name", name)];
+
+    self.request(Method::GET, &path, &query, None).await
+  }
+}
+
+impl DnsProvider for Cloudflare {
+  /// The zone is the one with the deepest name that contains the name, found
+  /// by looking up each of its parent domains in turn.
+  async fn resolve_zone(&mut self, name: &DomainName, choice: &ZoneChoice) -> Result<String> {
+    match choice {
+      ZoneChoice::Any | ZoneChoice::Public => {}
+      ZoneChoice::Id(id) => return Ok(id.clone()),
+      ZoneChoice::Private => bail!("Cloudflare has no private zones for `{name}`."),
+    }
+
+    let mut candidate = name.as_str();
+
+    loop {
+      let zones: Vec<Zone> = self
+        .request(Method::GET, "/zones", &[("name", candidate)], None)
+        .await
+        .with_context(|| format!("Failed to look up the Cloudflare zone of `{name}`."))?;
+
+      if let Some(zone) = zones.into_iter().next() {
+        return Ok(zone.id);
+      }
+
+      match candidate.split_once('.') {
+        Some((_, parent)) if parent.contains('.') => candidate = parent,
+        _ => bail!("Cannot find a Cloudflare zone for `{name}`."),
+      }
+    }
+  }
+
+  async fn find_record(
+    &self,
+    zone_id: &str,
+    name: &DomainName,
+    ip: IpAddr,
+  ) -> Result<Option<Record>> {
+    let records = self
+      .records(zone_id, name, ip)
+      .await
+      .with_context(|| format!("Failed to get the {} records of `{name}`.", rr_type(ip)))?;
+
+    Ok(records.first().map(|first| Record {
+      values: records.iter().map(|r| r.content.clone()).collect(),
+      ttl: first.ttl,
+    }))
+  }
+
+  async fn upsert_record(
+    &self,
+    zone_id: &str,
+    name: &DomainName,
+    ip: IpAddr,
+    ttl: i64,
+  ) -> Result<()> {
+    self
+      .upsert(zone_id, name, ip, ttl)
+      .await
+      .with_context(|| format!("Failed to update the {} record of `{name}`.", rr_type(ip)))
+  }
+}
+
+/// Returns the type of the record that holds an IP.
+const fn rr_type(ip: IpAddr) -> &'static str {
+  match ip {
+    IpAddr::V4(_) => "A",
+    IpAddr::V6(_) => "AAAA",
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::{Arc, Mutex};
+
+  use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::TcpListener,
+  };
+
+  use super::*;
+
+  /// A request received by the fake API.
+  #[derive(Debug, PartialEq)]
+  struct Request {
+    method: String,
+    /// Path of the request below the base URL, with its query.
+    path: String,
+    body: Value,
+  }
+
+  type Requests = Arc<Mutex<Vec<Request>>>;
+
+  /// Serves a fake Cloudflare API on a local port, answering each request
+  /// with the result `respond` returns for its method and path, or with an
+  /// error if it returns nothing. Returns a client of the API along with the
+  /// requests it received.
+  async fn serve(respond: fn(&str, &str) -> Option<Value>) -> (Cloudflare, Requests) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let api_url = format!("http://{}/client/v4", listener.local_addr().unwrap());
+    let requests = Requests::default();
+    let received = requests.clone();
+
+    tokio::spawn(async move {
+      loop {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut stream = BufReader::new(stream);
+        let mut line = String::new();
+
+        stream.read_line(&mut line).await.unwrap();
+
+        let mut parts = line.split_whitespace();
+        let method = parts.next().unwrap().to_string();
+        let path = parts.next().unwrap().trim_start_matches("/client/v4");
+        let path = path.to_string();
+        let mut length = 0;
+        let mut authorized = false;
+
+        loop {
+          let mut header = String::new();
+
+          stream.read_line(&mut header).await.unwrap();
+
+          let Some((name, value)) = header.split_once(':') else {
+            break;
+          };
+
+          match name.to_ascii_lowercase().as_str() {
+            "content-length" => length = value.trim().parse().unwrap(),
+            "authorization" => authorized = value.trim() == "Bearer token",
+            _ => {}
+          }
+        }
+
+        let mut body = vec![0; length];
+
+        stream.read_exact(&mut body).await.unwrap();
+
+        let response = match respond(&method, &path).filter(|_| authorized) {
+          Some(result) => json!({ "success": true, "errors": [], "result": result }),
+          None => json!({
+            "success": false,
+            "errors": [{ "code": 10000, "message": "Authentication error" }],
+            "result": null,
+          }),
+        };
+
+        received.lock().unwrap().push(Request {
+          method,
+          path,
+          body: serde_json::from_slice(&body).unwrap_or_default(),
+        });
+
+        let response = response.to_string();
+        let response = format!(
+          "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+           Connection: close\r\n\r\n{response}",
+          response.len()
+        );
+
+        stream
+          .get_mut()
+          .write_all(response.as_bytes())
+          .await
+          .unwrap();
+      }
+    });
+
+    let cloudflare = Cloudflare::new(&Http::new().unwrap(), "token".into()).with_api_url(api_url);
+
+    (cloudflare, requests)
+  }
+
+  fn request(method: &str, path: &str, body: Value) -> Request {
+    Request {
+      method: method.into(),
+      path: path.into(),
+      body,
+    }
+  }
+
+  fn name() -> DomainName {
+    "home.example.com".parse().unwrap()
+  }
+
+  #[tokio::test]
+  async fn resolves_the_zone_of_the_deepest_parent() {
+    let (mut cloudflare, requests) = serve(|_, path| match path {
+      "/zones?name=example.com" => Some(json!([{ "id": "z1" }])),
+      _ => Some(json!([])),
+    })
+    .await;
+
+    let zone_id = cloudflare.resolve_zone(&name(), &ZoneChoice::Any).await;
+
+    assert_eq!(zone_id.unwrap(), "z1");
+    assert_eq!(
+      *requests.lock().unwrap(),
+      [
+        request("GET", "/zones?name=home.example.com", Value::Null),
+        request("GET", "/zones?name=example.com", Value::Null),
+      ]
+    );
+  }
+
+  #[tokio::test]
+  async fn patches_the_first_record_and_deletes_duplicates() {
+    let (cloudflare, requests) = serve(|method, _| match method {
+      "GET" => Some(json!([
+        { "id": "r1", "content": "192.0.2.1", "ttl": 300 },
+        { "id": "r2", "content": "192.0.2.2", "ttl": 300 },
+      ])),
+      _ => Some(json!({})),
+    })
+    .await;
+
+    let ip = "203.0.113.7".parse().unwrap();
+    let record = cloudflare.find_record("z1", &name(), ip).await.unwrap();
+
+    assert_eq!(record.unwrap().values, ["192.0.2.1", "192.0.2.2"]);
+
+    cloudflare
+      .upsert_record("z1", &name(), ip, 60)
+      .await
+      .unwrap();
+
+    let list = "/zones/z1/dns_records?type=A&name=home.example.com";
+
+    assert_eq!(
+      *requests.lock().unwrap(),
+      [
+        request("GET", list, Value::Null),
+        request("GET", list, Value::Null),
+        request(
+          "PATCH",
+          "/zones/z1/dns_records/r1",
+          json!({ "content": "203.0.113.7", "ttl": 60 })
+        ),
+        request("DELETE", "/zones/z1/dns_records/r2", Value::Null),
+      ]
+    );
+  }
+
+  #[tokio::test]
+  async fn creates_a_missing_record() {
+    let (cloudflare, requests) = serve(|method, _| match method {
+      "GET" => Some(json!([])),
+      _ => Some(json!({})),
+    })
+    .await;
+
+    let ip = "2001:db8::7".parse().unwrap();
+
+    cloudflare
+      .upsert_record("z1", &name(), ip, 60)
+      .await
+      .unwrap();
+
+    assert_eq!(
+      requests.lock().unwrap()[1],
+      request(
+        "POST",
+        "/zones/z1/dns_records",
+        json!({ "type": "AAAA", "name": "home.example.com", "content": "2001:db8::7", "ttl": 60 })
+      )
+    );
+  }
+
+  #[tokio::test]
+  async fn reports_api_errors() {
+    let (cloudflare, _) = serve(|_, _| None).await;
+    let ip = "203.0.113.7".parse().unwrap();
+
+    let err = cloudflare.find_record("z1", &name(), ip).await.unwrap_err();
+
+    assert!(format!("{err:#}").contains("Authentication error (code 10000)"));
+  }
+}
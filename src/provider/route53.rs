@@ -0,0 +1,101 @@
+use std::net::IpAddr;
+
+use anyhow::Result;
+use aws_sdk_route53::{
+  types::{HostedZone, RrType},
+  Client,
+};
+
+use super::{DnsProvider, Record};
+use crate::{
+  aws::{self, Routing},
+  name::DomainName,
+  zone::{self, ZoneChoice},
+};
+
+/// Hosted zones in Route 53, for programs that embed the [`Updater`].
+///
+/// The daemon batches its own Route 53 changes instead.
+///
+/// [`Updater`]: crate::Updater
+pub struct Route53 {
+  client: Client,
+  routing: Option<Routing>,
+  /// Hosted zones, once listed.
+  zones: Option<Vec<HostedZone>>,
+}
+
+impl Route53 {
+  /// Creates a provider that sends requests with the given client.
+  pub const fn new(client: Client) -> Self {
+    Self {
+      client,
+      routing: None,
+      zones: None,
+    }
+  }
+
+  /// Publishes records as this instance's members of routed record sets.
+  pub fn with_routing(self, routing: Routing) -> Self {
+    Self {
+      routing: Some(routing),
+      ..self
+    }
+  }
+}
+
+impl DnsProvider for Route53 {
+  /// The hosted zones are listed at most once, unless the zone is chosen by
+  /// ID.
+  async fn resolve_zone(&mut self, name: &DomainName, choice: &ZoneChoice) -> Result<String> {
+    if let Some(id) = choice.zone_id() {
+      return Ok(id);
+    }
+
+    let zones = match &mut self.zones {
+      Some(zones) => zones,
+      None => self.zones.insert(aws::list_zones(&self.client).await?),
+    };
+
+    Ok(zone::find(zones, name, choice)?.id.clone())
+  }
+
+  async fn find_record(
+    &self,
+    zone_id: &str,
+    name: &DomainName,
+    ip: IpAddr,
+  ) -> Result<Option<Record>> {
+    let rr_type = match ip {
+      IpAddr::V4(_) => RrType::A,
+      IpAddr::V6(_) => RrType::Aaaa,
+    };
+
+    let set = aws::find_record(&self.client, zone_id, name, rr_type, self.routing.as_ref()).await?;
+
+    Ok(set.map(|set| {
+      Record {
+        values: set
+          .resource_records()
+          .iter()
+          .map(|r| r.value().into())
+          .collect(),
+        ttl: set.ttl().unwrap_or_default(),
+      }
+    }))
+  }
+
+  async fn upsert_record(
+    &self,
+    zone_id: &str,
+    name: &DomainName,
+    ip: IpAddr,
+    ttl: i64,
+  ) -> Result<()> {
+    let change = aws::upsert(name, ip, ttl, self.routing.as_ref())?;
+
+    aws::change_records(&self.client, zone_id, vec![change]).await?;
+
+    Ok(())
+  }
+}
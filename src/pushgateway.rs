@@ -0,0 +1,93 @@
+use std::iter;
+
+use anyhow::{anyhow, ensure, Context, Result};
+use reqwest::{Client, Url};
+use tracing::info_span;
+
+use crate::{http::Http, metrics::Snapshot, trace::traced};
+
+/// Command line options for pushing metrics to a Prometheus Pushgateway.
+#[derive(clap::Args)]
+#[command(next_help_heading = "Prometheus Pushgateway")]
+pub struct PushgatewayOptions {
+  /// Base URL of a Prometheus Pushgateway to push the metrics of each update
+  /// pass to, for runs that cannot be scraped.
+  #[arg(long, value_name = "URL")]
+  pushgateway_url: Option<Url>,
+
+  /// Value of the `job` label to push metrics under.
+  #[arg(
+    long,
+    value_name = "JOB",
+    default_value = "ddns_route53",
+    requires = "pushgateway_url"
+  )]
+  pushgateway_job: String,
+
+  /// Value of the `instance` label to push metrics under, to tell several
+  /// machines pushing under the same job apart.
+  #[arg(long, value_name = "INSTANCE", requires = "pushgateway_url")]
+  pushgateway_instance: Option<String>,
+}
+
+/// Client for a Prometheus Pushgateway.
+pub struct Pushgateway {
+  http: Client,
+  url: Url,
+}
+
+impl Pushgateway {
+  /// Creates a client for the Pushgateway given in the options, if any.
+  pub fn new(options: PushgatewayOptions, http: &Http) -> Result<Option<Self>> {
+    let Some(mut url) = options.pushgateway_url else {
+      return Ok(None);
+    };
+
+    // the Pushgateway only accepts slashes in labels encoded as base64
+    let labels = iter::once(&options.pushgateway_job).chain(&options.pushgateway_instance);
+
+    for label in labels {
+      ensure!(
+        !label.is_empty() && !label.contains('/'),
+        "Invalid Pushgateway label `{label}`. Labels must not be empty or contain `/`."
+      );
+    }
+
+    {
+      let mut segments = url
+        .path_segments_mut()
+        .map_err(|()| anyhow!("Invalid Pushgateway URL."))?;
+
+      // labels are path segments, which the URL escapes
+      segments
+        .pop_if_empty()
+        .extend(["metrics", "job", &options.pushgateway_job]);
+
+      if let Some(instance) = &options.pushgateway_instance {
+        segments.extend(["instance", instance]);
+      }
+    }
+
+    Ok(Some(Self {
+      http: http.any.clone(),
+      url,
+    }))
+  }
+
+  /// Replaces the metrics of the job and instance with those of a pass.
+  pub async fn push(&self, snapshot: &Snapshot) -> Result<()> {
+    let request = self
+      .http
+      .put(self.url.clone())
+      .header("Content-Type", "text/plain; version=0.0.4")
+      .body(snapshot.prometheus());
+
+    traced(info_span!("pushgateway_push"), async {
+      request.send().await?.error_for_status()?;
+
+      Ok(())
+    })
+    .await
+    .with_context(|| format!("Failed to push metrics to {}.", self.url))
+  }
+}
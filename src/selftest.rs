@@ -0,0 +1,148 @@
+use std::{
+  collections::HashSet,
+  time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Result};
+use aws_sdk_route53::{
+  self as route53,
+  types::{ChangeAction, RrType},
+};
+use tracing::{error, info};
+
+use ddns_route53::{
+  aws::{self, Accounts, AwsOptions},
+  dns::{self, RecordType},
+};
+
+use crate::Domain;
+
+/// Label prepended to a domain name to build the name of the canary record.
+const PREFIX: &str = "_ddns-route53-self-test.";
+
+/// TTL of the canary record.
+const TTL: i64 = 60;
+
+/// Proves that the daemon can update each hosted zone its domains belong to by
+/// creating a temporary TXT record, waiting for Route 53 to report it in sync,
+/// checking that every name server of the zone answers with it, and deleting
+/// it again.
+pub async fn run(domains: &[Domain], options: &AwsOptions) -> Result<()> {
+  let mut accounts = Accounts::new(options);
+  let mut tested = HashSet::new();
+  let mut failures = 0;
+
+  for domain in domains {
+    let zone = accounts
+      .zone(&domain.identity, &domain.name, &domain.zone)
+      .await;
+
+    let zone = match zone {
+      Ok(zone) => zone,
+
+      Err(err) => {
+        error!("{err:#}");
+        failures += 1;
+        continue;
+      }
+    };
+
+    // one canary per zone is enough
+    if !tested.insert(zone.id.clone()) {
+      continue;
+    }
+
+    let name = format!("{PREFIX}{}", domain.name);
+    let label = zone.name.as_ref().unwrap_or(&zone.id);
+    let route53 = accounts.client(&domain.identity).await;
+
+    info!("Testing zone `{label}` with `{name}`.");
+
+    if let Err(err) = test_zone(route53, &zone.id, &name).await {
+      error!("Self-test of zone `{label}` failed. {err:#}");
+      failures += 1;
+    }
+  }
+
+  if failures > 0 {
+    bail!("The self-test failed for {failures} domains or hosted zones.");
+  }
+
+  info!("All hosted zones passed the self-test.");
+
+  Ok(())
+}
+
+/// Creates, verifies, and deletes a canary record in a zone.
+async fn test_zone(route53: &route53::Client, zone_id: &str, name: &str) -> Result<()> {
+  let token = format!("\"{}\"", canary_token());
+  let started = Instant::now();
+
+  let create = aws::change(ChangeAction::Upsert, RrType::Txt, name, &[&token], TTL)?;
+  let change_id = aws::change_records(route53, zone_id, vec![create])
+    .await?
+    .change_id;
+
+  info!("Created `{name}`. Waiting for Route 53 to sync it.");
+
+  // delete the record even if the test fails after it was created
+  let result = async {
+    aws::wait_for_sync(route53, &change_id).await?;
+
+    info!("In sync after {}s.", started.elapsed().as_secs());
+
+    verify(route53, zone_id, name, token.trim_matches('"')).await
+  }
+  .await;
+
+  let delete = aws::change(ChangeAction::Delete, RrType::Txt, name, &[&token], TTL)?;
+
+  aws::change_records(route53, zone_id, vec![delete]).await?;
+  info!("Deleted `{name}`.");
+
+  result
+}
+
+/// Checks that every name server of a zone answers with the canary token.
+async fn verify(route53: &route53::Client, zone_id: &str, name: &str, token: &str) -> Result<()> {
+  let servers = aws::name_servers(route53, zone_id).await?;
+
+  if servers.is_empty() {
+    info!("Skipping resolution because the zone is private.");
+    return Ok(());
+  }
+
+  let mut failed = Vec::new();
+
+  for host in &servers {
+    let answer = async {
+      let server = dns::server(host).await?;
+
+      dns::query(server, name, RecordType::Txt, false, dns::TIMEOUT).await
+    };
+
+    match answer.await {
+      Ok(records) if records.iter().any(|r| r == token) => {
+        info!("`{host}` answers with the canary.");
+      }
+
+      Ok(_) => failed.push(format!("`{host}` does not answer with the canary")),
+      Err(err) => failed.push(format!("{err:#}")),
+    }
+  }
+
+  if !failed.is_empty() {
+    bail!("{}.", failed.join("; "));
+  }
+
+  Ok(())
+}
+
+/// Returns a token unique to this run.
+fn canary_token() -> String {
+  let nanos = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map_or(0, |d| d.as_nanos());
+
+  format!("ddns-route53-self-test-{nanos:x}-{:x}", std::process::id())
+}
@@ -0,0 +1,128 @@
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use anyhow::{bail, Context, Result};
+use tokio::{
+  io::{AsyncReadExt, AsyncWriteExt},
+  net::{TcpListener, TcpStream},
+  time::timeout,
+};
+use tracing::{debug, error, info};
+
+/// Largest request head that is read before the request is rejected.
+const MAX_REQUEST: usize = 8192;
+
+/// How long a client may take to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Response to a request.
+pub struct Response {
+  /// Status code and reason, such as `200 OK`.
+  pub status: &'static str,
+  pub content_type: &'static str,
+  pub body: String,
+}
+
+impl Response {
+  /// Creates a response with a JSON body.
+  pub fn json(status: &'static str, body: serde_json::Value) -> Self {
+    Self {
+      status,
+      content_type: "application/json",
+      body: body.to_string(),
+    }
+  }
+}
+
+/// Serves HTTP requests on an address in the background, answering each with
+/// a handler that is given the method and path of the request.
+///
+/// Only the request head is read, which is enough for requests without a
+/// body, and each connection is closed after its response.
+pub async fn listen<H>(addr: SocketAddr, name: &str, handler: H) -> Result<()>
+where
+  H: Fn(&str, &str) -> Response + Send + Sync + 'static,
+{
+  let listener = TcpListener::bind(addr)
+    .await
+    .with_context(|| format!("Failed to listen on {addr} for the {name}."))?;
+
+  let name = name.to_string();
+  let handler = Arc::new(handler);
+
+  info!("Serving the {name} on http://{addr}.");
+
+  tokio::spawn(async move {
+    loop {
+      let stream = match listener.accept().await {
+        Ok((stream, _)) => stream,
+
+        Err(err) => {
+          error!("Failed to accept a {name} connection. {err}");
+          continue;
+        }
+      };
+
+      let handler = handler.clone();
+      let name = name.clone();
+
+      tokio::spawn(async move {
+        if let Err(err) = handle(stream, &*handler).await {
+          debug!(server = name, "Connection failed. {err:#}");
+        }
+      });
+    }
+  });
+
+  Ok(())
+}
+
+/// Handles a single request and closes the connection.
+async fn handle(mut stream: TcpStream, handler: &impl Fn(&str, &str) -> Response) -> Result<()> {
+  let head = timeout(READ_TIMEOUT, read_head(&mut stream))
+    .await
+    .context("Timed out.")??;
+
+  let request_line = head.lines().next().unwrap_or_default();
+  let mut parts = request_line.split(' ');
+  let method = parts.next().unwrap_or_default();
+  let path = parts.next().unwrap_or_default();
+
+  let Response {
+    status,
+    content_type,
+    body,
+  } = handler(method, path);
+
+  let response = format!(
+    "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: \
+     close\r\n\r\n{body}",
+    body.len()
+  );
+
+  stream.write_all(response.as_bytes()).await?;
+  stream.shutdown().await?;
+
+  Ok(())
+}
+
+/// Reads the request line and headers of a request.
+async fn read_head(stream: &mut TcpStream) -> Result<String> {
+  let mut buf = Vec::with_capacity(1024);
+  let mut chunk = [0; 1024];
+
+  while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+    if buf.len() >= MAX_REQUEST {
+      bail!("Request is too large.");
+    }
+
+    let len = stream.read(&mut chunk).await?;
+
+    if len == 0 {
+      bail!("Connection closed before the request was complete.");
+    }
+
+    buf.extend(&chunk[..len]);
+  }
+
+  Ok(String::from_utf8_lossy(&buf).into_owned())
+}
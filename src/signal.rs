@@ -0,0 +1,81 @@
+use anyhow::{Context, Result};
+#[cfg(unix)]
+use tokio::signal::unix::{signal, Signal, SignalKind};
+#[cfg(unix)]
+use tracing::info;
+
+use crate::control::Controls;
+
+/// Listens for signals asking the daemon to exit.
+///
+/// The listeners are installed once up front so that a signal received during
+/// an update pass is not lost and is handled when the pass completes.
+pub struct Shutdown {
+  #[cfg(unix)]
+  interrupt: Signal,
+  #[cfg(unix)]
+  terminate: Signal,
+  #[cfg(windows)]
+  ctrl_c: tokio::signal::windows::CtrlC,
+}
+
+impl Shutdown {
+  /// Installs the signal listeners.
+  pub fn listen() -> Result<Self> {
+    Ok(Self {
+      #[cfg(unix)]
+      interrupt: signal(SignalKind::interrupt()).context("Failed to listen for SIGINT.")?,
+      #[cfg(unix)]
+      terminate: signal(SignalKind::terminate()).context("Failed to listen for SIGTERM.")?,
+      #[cfg(windows)]
+      ctrl_c: tokio::signal::windows::ctrl_c().context("Failed to listen for Ctrl+C.")?,
+    })
+  }
+
+  /// Waits until the daemon is asked to exit.
+  pub async fn requested(&mut self) {
+    #[cfg(unix)]
+    tokio::select! {
+      _ = self.interrupt.recv() => {}
+      _ = self.terminate.recv() => {}
+    }
+
+    #[cfg(windows)]
+    self.ctrl_c.recv().await;
+  }
+}
+
+/// Runs an update pass as soon as possible whenever the daemon receives
+/// `SIGHUP` or `SIGUSR1`, such as from a script that runs after the network
+/// reconnects.
+///
+/// A signal received during a pass starts another pass once it completes.
+#[cfg(unix)]
+pub fn refresh_on_signal(controls: Controls) -> Result<()> {
+  let mut hangup = signal(SignalKind::hangup()).context("Failed to listen for SIGHUP.")?;
+  let mut user = signal(SignalKind::user_defined1()).context("Failed to listen for SIGUSR1.")?;
+
+  tokio::spawn(async move {
+    loop {
+      let received = tokio::select! {
+        received = hangup.recv() => received,
+        received = user.recv() => received,
+      };
+
+      if received.is_none() {
+        break;
+      }
+
+      info!("Received a signal to update now.");
+      controls.update_now();
+    }
+  });
+
+  Ok(())
+}
+
+/// There are no signals to refresh on outside of Unix.
+#[cfg(not(unix))]
+pub fn refresh_on_signal(_controls: Controls) -> Result<()> {
+  Ok(())
+}
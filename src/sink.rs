@@ -0,0 +1,315 @@
+use std::fmt;
+
+use anyhow::Result;
+use chrono::{SecondsFormat, Utc};
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+use tracing::{
+  field::{Field, Visit},
+  span, Event, Level, Subscriber,
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+/// Name the daemon logs under in native log systems.
+#[cfg(any(windows, target_os = "macos"))]
+const NAME: &str = "ddns-route53";
+
+/// Where log messages are written.
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum LogTarget {
+  /// Standard output, or standard error for warnings and errors.
+  #[default]
+  Stdout,
+  /// The Windows Event Log, as the `ddns-route53` source.
+  Eventlog,
+  /// The macOS unified logging system, as the `ddns-route53` subsystem.
+  Oslog,
+}
+
+/// How log messages are formatted.
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+  /// Lines of text with the time and message.
+  #[default]
+  Text,
+  /// A JSON object per line, with the time, level, message, and fields of the
+  /// event and the spans it happened in.
+  Json,
+}
+
+/// The log target in use.
+enum Target {
+  Stdout,
+  #[cfg(windows)]
+  EventLog(eventlog::Source),
+  #[cfg(target_os = "macos")]
+  OsLog(oslog::OsLog),
+}
+
+/// Layer that writes log messages to a target.
+///
+/// As text, only messages at `info` and above are written, and diagnostic
+/// messages are left to another layer. As JSON, every message is written.
+pub struct Sink {
+  target: Target,
+  format: LogFormat,
+}
+
+/// Fields of a span, stored in its extensions for JSON messages.
+struct SpanFields(Map<String, Value>);
+
+/// Collects the fields of an event or span as JSON values.
+#[derive(Default)]
+struct Fields(Map<String, Value>);
+
+impl Sink {
+  /// Creates a layer that writes log messages to a target in a format.
+  pub fn new(target: LogTarget, format: LogFormat) -> Result<Self> {
+    let target = match target {
+      LogTarget::Stdout => Target::Stdout,
+
+      #[cfg(windows)]
+      LogTarget::Eventlog => Target::EventLog(eventlog::Source::register(NAME)?),
+      #[cfg(not(windows))]
+      LogTarget::Eventlog => anyhow::bail!("The Windows Event Log is only available on Windows."),
+
+      #[cfg(target_os = "macos")]
+      LogTarget::Oslog => Target::OsLog(oslog::OsLog::new(NAME, "daemon")),
+      #[cfg(not(target_os = "macos"))]
+      LogTarget::Oslog => anyhow::bail!("Unified logging is only available on macOS."),
+    };
+
+    Ok(Self { target, format })
+  }
+
+  /// Writes a message to the target.
+  fn write(&self, level: Level, message: &str) {
+    match &self.target {
+      Target::Stdout => match (self.format, level) {
+        (LogFormat::Json, _) => println!("{message}"),
+
+        (LogFormat::Text, level) => {
+          let time = Utc::now().format("%F %T");
+
+          match level {
+            Level::ERROR => eprintln!("[{time}] ERROR — {message}"),
+            Level::WARN => eprintln!("[{time}] WARNING — {message}"),
+            _ => println!("[{time}] {message}"),
+          }
+        }
+      },
+
+      #[cfg(windows)]
+      Target::EventLog(source) => source.report(level, message),
+
+      #[cfg(target_os = "macos")]
+      Target::OsLog(log) => {
+        let level = match level {
+          Level::ERROR => oslog::Level::Error,
+          _ => oslog::Level::Default,
+        };
+
+        log.with_level(level, message);
+      }
+    }
+  }
+}
+
+impl<S> Layer<S> for Sink
+where
+  S: Subscriber + for<'a> LookupSpan<'a>,
+{
+  fn on_new_span(&self, attrs: &span::Attributes, id: &span::Id, ctx: Context<S>) {
+    if self.format != LogFormat::Json {
+      return;
+    }
+
+    let mut fields = Fields::default();
+
+    attrs.record(&mut fields);
+
+    if let Some(span) = ctx.span(id) {
+      span.extensions_mut().insert(SpanFields(fields.0));
+    }
+  }
+
+  fn on_record(&self, id: &span::Id, values: &span::Record, ctx: Context<S>) {
+    let Some(span) = ctx.span(id) else {
+      return;
+    };
+
+    let mut extensions = span.extensions_mut();
+
+    if let Some(SpanFields(map)) = extensions.get_mut::<SpanFields>() {
+      let mut fields = Fields(std::mem::take(map));
+
+      values.record(&mut fields);
+      *map = fields.0;
+    }
+  }
+
+  fn on_event(&self, event: &Event, ctx: Context<S>) {
+    let metadata = event.metadata();
+    let level = *metadata.level();
+    let mut fields = Fields::default();
+
+    match self.format {
+      LogFormat::Text => {
+        // more verbose levels are greater
+        if level > Level::INFO {
+          return;
+        }
+
+        event.record(&mut fields);
+
+        let message = match fields.0.remove("message") {
+          Some(Value::String(message)) => message,
+          _ => String::new(),
+        };
+
+        self.write(level, &message);
+      }
+
+      LogFormat::Json => {
+        let mut object = Map::new();
+
+        object.insert(
+          "time".into(),
+          Utc::now()
+            .to_rfc3339_opts(SecondsFormat::Millis, true)
+            .into(),
+        );
+
+        object.insert("level".into(), level.as_str().into());
+        object.insert("target".into(), metadata.target().into());
+
+        // fields of inner spans and of the event take precedence
+        if let Some(scope) = ctx.event_scope(event) {
+          let mut innermost = None;
+
+          for span in scope.from_root() {
+            if let Some(SpanFields(map)) = span.extensions().get::<SpanFields>() {
+              object.extend(map.clone());
+            }
+
+            innermost = Some(span.name());
+          }
+
+          object.extend(innermost.map(|name| ("span".into(), name.into())));
+        }
+
+        event.record(&mut fields);
+        object.extend(fields.0);
+
+        self.write(level, &Value::Object(object).to_string());
+      }
+    }
+  }
+}
+
+impl Visit for Fields {
+  fn record_str(&mut self, field: &Field, value: &str) {
+    self.0.insert(field.name().into(), value.into());
+  }
+
+  fn record_bool(&mut self, field: &Field, value: bool) {
+    self.0.insert(field.name().into(), value.into());
+  }
+
+  fn record_i64(&mut self, field: &Field, value: i64) {
+    self.0.insert(field.name().into(), value.into());
+  }
+
+  fn record_u64(&mut self, field: &Field, value: u64) {
+    self.0.insert(field.name().into(), value.into());
+  }
+
+  fn record_f64(&mut self, field: &Field, value: f64) {
+    self.0.insert(field.name().into(), value.into());
+  }
+
+  fn record_error(&mut self, field: &Field, value: &(dyn std::error::Error + 'static)) {
+    self
+      .0
+      .insert(field.name().into(), format!("{value:#}").into());
+  }
+
+  fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+    // messages and fields given with `%` are recorded with their `Display`
+    // output through this method
+    self
+      .0
+      .insert(field.name().into(), format!("{value:?}").into());
+  }
+}
+
+#[cfg(windows)]
+mod eventlog {
+  use std::{io, iter, ptr};
+
+  use anyhow::{bail, Result};
+  use tracing::Level;
+  use windows_sys::Win32::{
+    Foundation::HANDLE,
+    System::EventLog::{
+      RegisterEventSourceW, ReportEventW, EVENTLOG_ERROR_TYPE, EVENTLOG_INFORMATION_TYPE,
+      EVENTLOG_WARNING_TYPE,
+    },
+  };
+
+  /// An event source registered with the Windows Event Log.
+  pub struct Source(HANDLE);
+
+  // the handle is only used to report events, which is thread-safe
+  unsafe impl Send for Source {}
+  unsafe impl Sync for Source {}
+
+  impl Source {
+    /// Opens a handle to the event source with the given name.
+    pub fn register(name: &str) -> Result<Self> {
+      let name = wide(name);
+      let handle = unsafe { RegisterEventSourceW(ptr::null(), name.as_ptr()) };
+
+      if handle.is_null() {
+        bail!(
+          "Failed to register the Windows Event Log source. {}",
+          io::Error::last_os_error()
+        );
+      }
+
+      Ok(Self(handle))
+    }
+
+    /// Reports an event with the given level and message.
+    pub fn report(&self, level: Level, message: &str) {
+      let kind = match level {
+        Level::ERROR => EVENTLOG_ERROR_TYPE,
+        Level::WARN => EVENTLOG_WARNING_TYPE,
+        _ => EVENTLOG_INFORMATION_TYPE,
+      };
+
+      let message = wide(message);
+      let strings = [message.as_ptr()];
+
+      // there is nowhere left to report a failure to log
+      unsafe {
+        ReportEventW(
+          self.0,
+          kind,
+          0,
+          0,
+          ptr::null_mut(),
+          1,
+          0,
+          strings.as_ptr(),
+          ptr::null(),
+        );
+      }
+    }
+  }
+
+  /// Converts a string to a null-terminated UTF-16 string.
+  fn wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(iter::once(0)).collect()
+  }
+}
@@ -0,0 +1,140 @@
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  fs,
+  net::IpAddr,
+  path::Path,
+};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Daemon state saved to the state file after every update pass.
+///
+/// Statistics are restored from the state file when the daemon starts so that
+/// they accumulate across restarts.
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct State {
+  /// Time the daemon process started.
+  pub started_at: Option<DateTime<Utc>>,
+  /// Last known public IP.
+  #[serde(deserialize_with = "empty_as_none")]
+  pub public_ip: Option<IpAddr>,
+  /// Last known public IPv6 in dual-stack mode.
+  pub public_ipv6: Option<IpAddr>,
+  /// Time the public IP was first detected or last changed.
+  pub ip_changed_at: Option<DateTime<Utc>>,
+  /// Number of times the public IP has changed.
+  pub ip_changes: u64,
+  /// Time the daemon last completed an update pass.
+  pub checked_at: Option<DateTime<Utc>>,
+  /// Time of the most recent successful Route 53 write.
+  pub last_write_at: Option<DateTime<Utc>>,
+  /// Time until which updates are held down because the public IP is
+  /// flapping.
+  pub held_until: Option<DateTime<Utc>>,
+  /// Number of Route 53 requests that were throttled.
+  pub throttled: u64,
+  /// Number of garbage responses from each IP provider, keyed by URL.
+  pub garbage: BTreeMap<String, u64>,
+  /// Statistics for each domain, keyed by domain name.
+  pub domains: BTreeMap<String, DomainStats>,
+  /// Changes that failed to apply and are retried, keyed by domain name.
+  pub pending: BTreeMap<String, PendingChange>,
+  /// Changes of AAAA records in dual-stack mode that failed to apply and are
+  /// retried, keyed by domain name.
+  pub pending_v6: BTreeMap<String, PendingChange>,
+  /// Names of domains whose updates are paused.
+  pub paused: BTreeSet<String>,
+}
+
+/// Update statistics for a single domain.
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DomainStats {
+  /// Number of successful record updates.
+  pub updates: u64,
+  /// Number of failed update attempts.
+  pub failures: u64,
+  /// Number of failed update attempts since the last successful update.
+  pub consecutive_failures: u64,
+  /// Message of the most recent failure, if the domain has failed since its
+  /// last successful update.
+  pub last_error: Option<String>,
+  /// Time of the most recent successful record update.
+  pub last_change: Option<DateTime<Utc>>,
+  /// Time an inventory host last reported its IP.
+  pub last_seen: Option<DateTime<Utc>>,
+  /// Time an inventory host was reaped for not reporting its IP, if it has
+  /// not reported since.
+  pub reaped_at: Option<DateTime<Utc>>,
+  /// Full ID of the Route 53 hosted zone the domain's records are in, once it
+  /// is known.
+  pub zone_id: Option<String>,
+  /// AWS profile the domain's records are written with, if the domain has
+  /// its own.
+  pub profile: Option<String>,
+  /// ARN of the IAM role the domain's records are written with, if the
+  /// domain has its own.
+  pub role_arn: Option<String>,
+}
+
+/// A record change that has not been applied yet.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct PendingChange {
+  /// IP the record should be changed to.
+  pub ip: IpAddr,
+  /// Time the change was first queued.
+  pub queued_at: DateTime<Utc>,
+}
+
+impl State {
+  /// Loads state from a file.
+  pub fn load(path: &Path) -> Result<Self> {
+    let json =
+      fs::read_to_string(path).with_context(|| format!("Failed to read state file {path:?}."))?;
+
+    serde_json::from_str(&json).with_context(|| format!("Failed to parse state file {path:?}."))
+  }
+
+  /// Saves state to a file.
+  ///
+  /// The state is written to a temporary file first and then renamed over the
+  /// original so that readers never observe a partially written file.
+  pub fn save(&self, path: &Path) -> Result<()> {
+    let json = serde_json::to_string_pretty(self)?;
+    let tmp = path.with_extension("tmp");
+
+    fs::write(&tmp, json).with_context(|| format!("Failed to write state file {tmp:?}."))?;
+    fs::rename(&tmp, path).with_context(|| format!("Failed to replace state file {path:?}."))?;
+
+    Ok(())
+  }
+}
+
+impl DomainStats {
+  /// Records a successful record update.
+  pub fn record_success(&mut self) {
+    self.updates += 1;
+    self.consecutive_failures = 0;
+    self.last_error = None;
+    self.last_change = Some(Utc::now());
+  }
+
+  /// Records a failed update attempt.
+  pub fn record_failure(&mut self, err: &anyhow::Error) {
+    self.failures += 1;
+    self.consecutive_failures += 1;
+    self.last_error = Some(format!("{err:#}"));
+  }
+}
+
+/// Deserializes an optional IP, treating an empty string as none for state
+/// files that stored an unknown IP as one.
+fn empty_as_none<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<IpAddr>, D::Error> {
+  match Option::<String>::deserialize(deserializer)?.as_deref() {
+    None | Some("") => Ok(None),
+    Some(ip) => ip.parse().map(Some).map_err(serde::de::Error::custom),
+  }
+}
@@ -0,0 +1,56 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+
+use crate::{format_duration, Domain};
+
+/// Totals at the start of the session, which are subtracted from the totals
+/// at exit since statistics accumulate across restarts.
+pub struct Baseline {
+  started_at: DateTime<Utc>,
+  ip_changes: u64,
+  /// Updates and failures of each domain, keyed by domain name.
+  domains: BTreeMap<String, (u64, u64)>,
+}
+
+impl Baseline {
+  /// Records the totals at the start of a session.
+  pub fn new(started_at: DateTime<Utc>, ip_changes: u64, domains: &[Domain]) -> Self {
+    Self {
+      started_at,
+      ip_changes,
+      domains: domains
+        .iter()
+        .map(|d| (d.name.to_string(), (d.stats.updates, d.stats.failures)))
+        .collect(),
+    }
+  }
+
+  /// Describes what happened since the start of the session.
+  pub fn report(&self, ip_changes: u64, domains: &[Domain]) -> String {
+    let mut report = format!(
+      "Uptime:     {}\nIP changes: {}",
+      format_duration(Utc::now() - self.started_at),
+      ip_changes - self.ip_changes,
+    );
+
+    let width = domains.iter().map(|d| d.name.len()).max().unwrap_or(0);
+
+    for domain in domains {
+      let (updates, failures) = self
+        .domains
+        .get(domain.name.as_str())
+        .copied()
+        .unwrap_or((0, 0));
+
+      report.push_str(&format!(
+        "\n{:width$}  {} updates, {} failures",
+        domain.name.as_str(),
+        domain.stats.updates - updates,
+        domain.stats.failures - failures,
+      ));
+    }
+
+    report
+  }
+}
@@ -0,0 +1,124 @@
+use std::{future::Future, time::Duration};
+
+/// Connection to the systemd service manager, for a daemon running as a
+/// `Type=notify` service.
+pub struct Systemd {
+  #[cfg(unix)]
+  socket: std::os::unix::net::UnixDatagram,
+  /// How often the watchdog must be pinged, if it is enabled.
+  watchdog: Option<Duration>,
+  /// Whether readiness has been reported.
+  ready: bool,
+}
+
+impl Systemd {
+  /// Connects to the service manager if it set `NOTIFY_SOCKET`.
+  #[cfg(unix)]
+  pub fn from_env() -> Option<Self> {
+    use std::{
+      env,
+      os::unix::net::{SocketAddr, UnixDatagram},
+    };
+
+    let path = env::var_os("NOTIFY_SOCKET")?;
+    let socket = UnixDatagram::unbound().ok()?;
+
+    // a leading `@` stands for a socket in the abstract namespace
+    let addr = match path.as_encoded_bytes().strip_prefix(b"@") {
+      #[cfg(target_os = "linux")]
+      Some(name) => {
+        use std::os::linux::net::SocketAddrExt;
+
+        SocketAddr::from_abstract_name(name).ok()?
+      }
+
+      #[cfg(not(target_os = "linux"))]
+      Some(_) => return None,
+
+      None => SocketAddr::from_pathname(&path).ok()?,
+    };
+
+    socket.connect_addr(&addr).ok()?;
+
+    // the watchdog may be meant for another process, such as a shell script
+    // that started this one
+    let pid = env::var("WATCHDOG_PID").ok();
+    let watchdog = env::var("WATCHDOG_USEC")
+      .ok()
+      .filter(|_| pid.is_none_or(|pid| pid == std::process::id().to_string()))
+      .and_then(|usec| usec.parse().ok())
+      .filter(|&usec| usec > 0)
+      .map(Duration::from_micros);
+
+    Some(Self {
+      socket,
+      watchdog,
+      ready: false,
+    })
+  }
+
+  /// The service manager is only available on Unix.
+  #[cfg(not(unix))]
+  pub fn from_env() -> Option<Self> {
+    None
+  }
+
+  /// Reports that the daemon is ready, once.
+  pub fn ready(&mut self) {
+    if !self.ready {
+      self.ready = true;
+      self.send("READY=1");
+    }
+  }
+
+  /// Sets the status shown by `systemctl status`.
+  pub fn status(&self, status: &str) {
+    // each line of a notification is a separate assignment
+    self.send(&format!("STATUS={}", status.replace('\n', " ")));
+  }
+
+  /// Reports that the daemon is exiting.
+  pub fn stopping(&self) {
+    self.send("STOPPING=1");
+  }
+
+  /// Pings the watchdog, if it is enabled.
+  pub fn ping(&self) {
+    if self.watchdog.is_some() {
+      self.send("WATCHDOG=1");
+    }
+  }
+
+  /// Runs a future while pinging the watchdog often enough that it does not
+  /// time out, so that the update loop can wait longer than the watchdog
+  /// period but is still restarted if it stops responding.
+  pub async fn pinging<T>(&self, future: impl Future<Output = T>) -> T {
+    let Some(period) = self.watchdog else {
+      return future.await;
+    };
+
+    tokio::pin!(future);
+
+    // systemd recommends pinging at half the period
+    let mut ticks = tokio::time::interval(period / 2);
+
+    loop {
+      tokio::select! {
+        output = &mut future => return output,
+        _ = ticks.tick() => self.ping(),
+      }
+    }
+  }
+
+  /// Sends a notification, ignoring failures since the daemon works the same
+  /// without the service manager.
+  fn send(&self, message: &str) {
+    #[cfg(unix)]
+    if let Err(err) = self.socket.send(message.as_bytes()) {
+      tracing::debug!("Failed to notify systemd. {err}");
+    }
+
+    #[cfg(not(unix))]
+    let _ = message;
+  }
+}
@@ -0,0 +1,70 @@
+use std::{future::Future, io::IsTerminal, time::Instant};
+
+use anyhow::Result;
+use tracing::{debug, level_filters::LevelFilter, Instrument, Level, Span};
+use tracing_subscriber::{
+  filter::{filter_fn, Targets},
+  layer::SubscriberExt,
+  util::SubscriberInitExt,
+  Layer,
+};
+
+use crate::sink::{LogFormat, LogTarget, Sink};
+
+/// Initializes the tracing subscriber that logs spans and events of this
+/// crate at or above the given level.
+///
+/// As text, messages at `info` and above go to the log target, and more
+/// verbose diagnostic messages are printed to standard error along with the
+/// spans they happened in. As JSON, every message goes to the log target.
+///
+/// Warnings from dependencies such as the AWS SDK are only logged at `debug`
+/// and above.
+pub fn init(level: LevelFilter, format: LogFormat, target: LogTarget) -> Result<()> {
+  let dependencies = match level >= LevelFilter::DEBUG {
+    true => LevelFilter::WARN,
+    false => LevelFilter::OFF,
+  };
+
+  let filter = Targets::new()
+    .with_default(dependencies)
+    .with_target(env!("CARGO_CRATE_NAME"), level);
+
+  // more verbose levels are greater, and spans are kept for the context of
+  // the events inside them
+  let diagnostics = (format == LogFormat::Text).then(|| {
+    tracing_subscriber::fmt::layer()
+      .with_ansi(std::io::stderr().is_terminal())
+      .with_writer(std::io::stderr)
+      .with_filter(filter_fn(|meta| {
+        meta.is_span() || *meta.level() > Level::INFO
+      }))
+  });
+
+  tracing_subscriber::registry()
+    .with(Sink::new(target, format)?)
+    .with(diagnostics)
+    .with(filter)
+    .init();
+
+  Ok(())
+}
+
+/// Runs an external call inside a span, recording its duration and outcome
+/// at debug level.
+pub async fn traced<T>(span: Span, call: impl Future<Output = Result<T>>) -> Result<T> {
+  async {
+    let started = Instant::now();
+    let result = call.await;
+    let elapsed = started.elapsed();
+
+    match &result {
+      Ok(_) => debug!(?elapsed, "Call succeeded."),
+      Err(err) => debug!(?elapsed, error = format!("{err:#}"), "Call failed."),
+    }
+
+    result
+  }
+  .instrument(span)
+  .await
+}
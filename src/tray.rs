@@ -0,0 +1,141 @@
+use std::net::IpAddr;
+
+use anyhow::{Context, Result};
+use ksni::{menu::*, Handle, Icon, ToolTip, TrayMethods};
+use tracing::info;
+
+use ddns_route53::control::Controls;
+
+/// Size of the status icon in pixels.
+const ICON_SIZE: i32 = 22;
+
+/// System tray icon showing the daemon's status.
+pub struct TrayIcon {
+  handle: Handle<Tray>,
+}
+
+struct Tray {
+  controls: Controls,
+  healthy: bool,
+  ip: Option<IpAddr>,
+}
+
+impl TrayIcon {
+  /// Adds the icon to the system tray.
+  pub async fn spawn(controls: Controls) -> Result<Self> {
+    let tray = Tray {
+      controls,
+      healthy: true,
+      ip: None,
+    };
+
+    let handle = tray
+      .spawn()
+      .await
+      .context("Failed to create the system tray icon.")?;
+
+    Ok(Self { handle })
+  }
+
+  /// Updates the status shown by the icon.
+  pub async fn show(&self, healthy: bool, ip: Option<IpAddr>) {
+    self
+      .handle
+      .update(|tray| {
+        tray.healthy = healthy;
+        tray.ip = ip;
+      })
+      .await;
+  }
+}
+
+impl ksni::Tray for Tray {
+  fn id(&self) -> String {
+    env!("CARGO_PKG_NAME").into()
+  }
+
+  fn title(&self) -> String {
+    env!("CARGO_PKG_NAME").into()
+  }
+
+  fn icon_pixmap(&self) -> Vec<Icon> {
+    let color = match (self.controls.is_paused(), self.healthy) {
+      (true, _) => [0x99, 0x99, 0x99],
+      (false, true) => [0x2e, 0xc2, 0x7e],
+      (false, false) => [0xe0, 0x1b, 0x24],
+    };
+
+    vec![circle(color)]
+  }
+
+  fn tool_tip(&self) -> ToolTip {
+    let mut description = match self.ip {
+      Some(ip) => format!("Public IP: {ip}"),
+      None => "Public IP unknown".to_string(),
+    };
+
+    if self.controls.is_paused() {
+      description.push_str("\nUpdates paused");
+    } else if !self.healthy {
+      description.push_str("\nUpdates failing");
+    }
+
+    ToolTip {
+      title: env!("CARGO_PKG_NAME").into(),
+      description,
+      ..ToolTip::default()
+    }
+  }
+
+  fn menu(&self) -> Vec<ksni::MenuItem<Self>> {
+    vec![
+      StandardItem {
+        label: "Update now".into(),
+        activate: Box::new(|tray: &mut Self| tray.controls.update_now()),
+        ..StandardItem::default()
+      }
+      .into(),
+      CheckmarkItem {
+        label: "Pause".into(),
+        checked: self.controls.is_paused(),
+        activate: Box::new(|tray: &mut Self| {
+          let paused = !tray.controls.is_paused();
+
+          tray.controls.set_paused(paused);
+
+          match paused {
+            true => info!("Updates paused."),
+            false => info!("Updates resumed."),
+          }
+        }),
+        ..CheckmarkItem::default()
+      }
+      .into(),
+    ]
+  }
+}
+
+/// Draws a filled circle of the given RGB color.
+fn circle([r, g, b]: [u8; 3]) -> Icon {
+  let radius = ICON_SIZE as f32 / 2.0;
+  let mut data = Vec::with_capacity((ICON_SIZE * ICON_SIZE * 4) as usize);
+
+  for y in 0..ICON_SIZE {
+    for x in 0..ICON_SIZE {
+      let dx = x as f32 + 0.5 - radius;
+      let dy = y as f32 + 0.5 - radius;
+      let alpha = match (dx * dx + dy * dy).sqrt() < radius - 1.0 {
+        true => 0xff,
+        false => 0,
+      };
+
+      data.extend_from_slice(&[alpha, r, g, b]);
+    }
+  }
+
+  Icon {
+    width: ICON_SIZE,
+    height: ICON_SIZE,
+    data,
+  }
+}
@@ -0,0 +1,32 @@
+use chrono::TimeDelta;
+
+/// Chooses record TTLs that stay low while the public IP changes often and
+/// rise as it stays stable.
+pub struct DynamicTtl {
+  min: i64,
+  max: i64,
+}
+
+impl DynamicTtl {
+  /// Creates a policy that keeps TTLs between `min` and `max` seconds.
+  pub const fn new(min: i64, max: i64) -> Self {
+    Self { min, max }
+  }
+
+  /// Returns the TTL for a record whose IP has been stable for the given
+  /// duration.
+  ///
+  /// The TTL is about a tenth of the stable duration, rounded down to the
+  /// minimum TTL times a power of two so that it is raised in a few doubling
+  /// steps rather than on every update pass.
+  pub fn ttl(&self, stable: TimeDelta) -> i64 {
+    let target = stable.num_seconds() / 10;
+    let mut ttl = self.min;
+
+    while ttl * 2 <= target && ttl * 2 <= self.max {
+      ttl *= 2;
+    }
+
+    ttl.min(self.max)
+  }
+}
@@ -0,0 +1,255 @@
+use std::{env, fs, path::Path, time::Duration};
+
+use anyhow::{bail, Context, Result};
+use semver::Version;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tracing::{error, info};
+
+use ddns_route53::http::{Http, HttpOptions};
+
+/// URL of the latest release on GitHub.
+const LATEST_RELEASE_URL: &str =
+  "https://api.github.com/repos/alexfrydl/ddns-route53/releases/latest";
+
+/// Name of the release asset that lists the SHA-256 checksum of every other
+/// asset.
+const CHECKSUMS: &str = "SHA256SUMS";
+
+/// How long downloading the new binary may take.
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// A release published on GitHub.
+#[derive(Deserialize)]
+struct Release {
+  tag_name: String,
+  html_url: String,
+  assets: Vec<Asset>,
+}
+
+/// A file attached to a release.
+#[derive(Deserialize)]
+struct Asset {
+  name: String,
+  browser_download_url: String,
+}
+
+impl Release {
+  /// Returns the version of the release without the `v` prefix of its tag.
+  fn version(&self) -> Result<Version> {
+    let version = self.tag_name.trim_start_matches('v');
+
+    Version::parse(version)
+      .with_context(|| format!("Invalid version in release tag `{}`.", self.tag_name))
+  }
+
+  /// Finds an asset by name.
+  fn asset(&self, name: &str) -> Result<&Asset> {
+    match self.assets.iter().find(|a| a.name == name) {
+      Some(asset) => Ok(asset),
+      None => bail!("Release {} has no `{name}` asset.", self.tag_name),
+    }
+  }
+}
+
+/// Downloads the latest release for this platform, verifies its checksum, and
+/// replaces the running binary with it.
+///
+/// With `check`, only reports whether a newer release is available.
+pub async fn run(check: bool, options: &HttpOptions) -> Result<()> {
+  let http = Http::with_options(options)?;
+  let release = latest(&http).await?;
+  let current = current_version();
+
+  if release.version()? <= current {
+    println!("ddns-route53 {current} is up to date.");
+    return Ok(());
+  }
+
+  if check {
+    println!(
+      "ddns-route53 {} is available: {}",
+      release.tag_name, release.html_url
+    );
+
+    return Ok(());
+  }
+
+  let name = asset_name();
+  let binary = download(&http, release.asset(&name)?).await?;
+  let checksums = download(&http, release.asset(CHECKSUMS)?).await?;
+
+  verify(&name, &binary, &String::from_utf8_lossy(&checksums))?;
+
+  let exe = env::current_exe().context("Failed to find the running binary.")?;
+
+  replace(&exe, &binary)?;
+  println!("Updated {exe:?} from {current} to {}.", release.tag_name);
+
+  Ok(())
+}
+
+/// Logs whether a newer release is available, for the periodic check of the
+/// daemon.
+pub async fn check(http: &Http) {
+  let release = match latest(http).await {
+    Ok(release) => release,
+
+    Err(err) => {
+      error!("Failed to check for updates. {err:#}");
+      return;
+    }
+  };
+
+  if release.version().is_ok_and(|v| v > current_version()) {
+    info!(
+      "ddns-route53 {} is available: {}. Run `ddns-route53 self-update` to install it.",
+      release.tag_name, release.html_url,
+    );
+  }
+}
+
+/// Returns the version of the running binary.
+fn current_version() -> Version {
+  Version::parse(env!("CARGO_PKG_VERSION")).expect("Package version is valid semver.")
+}
+
+/// Returns the name of the release asset built for this platform, such as
+/// `ddns-route53-x86_64-unknown-linux-gnu`.
+fn asset_name() -> String {
+  format!(
+    "ddns-route53-{}{}",
+    env!("BUILD_TARGET"),
+    env::consts::EXE_SUFFIX
+  )
+}
+
+/// Fetches the latest release from GitHub.
+async fn latest(http: &Http) -> Result<Release> {
+  async {
+    http
+      .any
+      .get(LATEST_RELEASE_URL)
+      .header("Accept", "application/vnd.github+json")
+      .send()
+      .await?
+      .error_for_status()?
+      .json()
+      .await
+  }
+  .await
+  .context("Failed to fetch the latest release from GitHub.")
+}
+
+/// Downloads a release asset.
+async fn download(http: &Http, asset: &Asset) -> Result<Vec<u8>> {
+  async {
+    let response = http
+      .any
+      .get(&asset.browser_download_url)
+      .timeout(DOWNLOAD_TIMEOUT)
+      .send()
+      .await?
+      .error_for_status()?;
+
+    Ok::<_, reqwest::Error>(response.bytes().await?.to_vec())
+  }
+  .await
+  .with_context(|| format!("Failed to download `{}`.", asset.name))
+}
+
+/// Checks a downloaded asset against its line in a `SHA256SUMS` file.
+fn verify(name: &str, data: &[u8], checksums: &str) -> Result<()> {
+  // lines are `HASH  NAME`, with a `*` before the name in binary mode
+  let expected = checksums.lines().find_map(|line| {
+    let (hash, file) = line.split_once(char::is_whitespace)?;
+
+    (file.trim_start().trim_start_matches('*') == name).then_some(hash)
+  });
+
+  let Some(expected) = expected else {
+    bail!("`{CHECKSUMS}` has no checksum for `{name}`.");
+  };
+
+  let actual: String = Sha256::digest(data)
+    .iter()
+    .map(|b| format!("{b:02x}"))
+    .collect();
+
+  if !actual.eq_ignore_ascii_case(expected) {
+    bail!("Checksum of `{name}` does not match. Expected {expected} but got {actual}.");
+  }
+
+  Ok(())
+}
+
+/// Replaces a binary with new contents.
+///
+/// The new binary is written next to the old one and renamed over it so that
+/// the old binary stays intact if anything fails. Windows does not allow
+/// replacing a running binary, but does allow renaming it out of the way.
+fn replace(exe: &Path, binary: &[u8]) -> Result<()> {
+  let tmp = exe.with_extension("new");
+
+  fs::write(&tmp, binary).with_context(|| format!("Failed to write {tmp:?}."))?;
+
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = fs::metadata(exe)?.permissions().mode();
+
+    fs::set_permissions(&tmp, fs::Permissions::from_mode(mode))
+      .with_context(|| format!("Failed to make {tmp:?} executable."))?;
+  }
+
+  #[cfg(windows)]
+  {
+    let old = exe.with_extension("old");
+
+    let _ = fs::remove_file(&old);
+    fs::rename(exe, &old).with_context(|| format!("Failed to move {exe:?} out of the way."))?;
+  }
+
+  fs::rename(&tmp, exe).with_context(|| format!("Failed to replace {exe:?}."))?;
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// SHA-256 of `binary`.
+  const HASH: &str = "9a3a45d01531a20e89ac6ae10b0b0beb0492acd7216a368aa062d1a5fecaf9cd";
+
+  #[test]
+  fn accepts_a_matching_checksum() {
+    let checksums = format!("0000  other\n{HASH}  ddns-route53\n");
+
+    assert!(verify("ddns-route53", b"binary", &checksums).is_ok());
+  }
+
+  #[test]
+  fn accepts_the_binary_mode_form() {
+    let checksums = format!("{}  *ddns-route53\n", HASH.to_uppercase());
+
+    assert!(verify("ddns-route53", b"binary", &checksums).is_ok());
+  }
+
+  #[test]
+  fn rejects_a_mismatch() {
+    let checksums = format!("{HASH}  ddns-route53\n");
+    let err = verify("ddns-route53", b"tampered", &checksums).unwrap_err();
+
+    assert!(err.to_string().contains("does not match"));
+  }
+
+  #[test]
+  fn rejects_a_missing_entry() {
+    let checksums = format!("{HASH}  ddns-route53.exe\n{HASH}  ddns\n");
+    let err = verify("ddns-route53", b"binary", &checksums).unwrap_err();
+
+    assert!(err.to_string().contains("has no checksum"));
+  }
+}
@@ -0,0 +1,200 @@
+use std::{net::IpAddr, time::Duration};
+
+use anyhow::{anyhow, Result};
+
+use crate::{
+  http::Http,
+  ip::{self, Family, Provider},
+  name::DomainName,
+  provider::{DnsProvider, Record, Route53},
+  zone::ZoneChoice,
+};
+
+/// Where the IP that records are updated to comes from.
+pub enum IpSource {
+  /// Services or name servers that report the public IP of this machine,
+  /// tried in order until one answers.
+  Providers(Vec<Provider>),
+  /// The globally routable address of a network interface.
+  Interface(String),
+  /// A fixed address.
+  Fixed(IpAddr),
+}
+
+impl IpSource {
+  /// Returns a source that asks the built-in services for the public IP of a
+  /// family.
+  pub fn public(family: Family) -> Self {
+    Self::Providers(match family {
+      Family::V4 => Provider::builtin(ip::PROVIDERS_V4),
+      Family::V6 => Provider::builtin(ip::PROVIDERS_V6),
+    })
+  }
+
+  /// Detects the current IP of a family, giving each provider at most
+  /// `timeout` to answer.
+  pub async fn detect_ip(
+    &mut self,
+    http: &Http,
+    family: Family,
+    timeout: Duration,
+  ) -> Result<IpAddr> {
+    match self {
+      Self::Providers(providers) => {
+        let mut last_err = anyhow!("There are no IP providers to ask.");
+
+        for provider in providers {
+          match provider.fetch(http, family, timeout).await {
+            Ok(ip) => return Ok(ip),
+            Err(err) => last_err = err.context(format!("Failed to ask {}.", provider.url)),
+          }
+        }
+
+        Err(last_err)
+      }
+
+      Self::Interface(name) => ip::interface_address(name, family),
+      Self::Fixed(ip) => Ok(*ip),
+    }
+  }
+}
+
+/// A record that is kept pointed at an IP, an A or AAAA record depending on
+/// the family of the IP.
+pub struct RecordTarget {
+  pub name: DomainName,
+  /// Hosted zone the record is in, when several could contain its name.
+  pub zone: ZoneChoice,
+  pub ttl: i64,
+}
+
+impl RecordTarget {
+  /// Returns a target for the record of a name in the hosted zone it
+  /// matches, with a TTL of five minutes.
+  pub const fn new(name: DomainName) -> Self {
+    Self {
+      name,
+      zone: ZoneChoice::Any,
+      ttl: 300,
+    }
+  }
+}
+
+/// Writes the IPs of record targets to the zones of a DNS provider, which is
+/// Route 53 unless another is given.
+pub struct Updater<P = Route53> {
+  provider: P,
+}
+
+impl<P: DnsProvider> Updater<P> {
+  pub const fn new(provider: P) -> Self {
+    Self { provider }
+  }
+
+  /// Returns the ID of the zone the record of a target is in.
+  pub async fn resolve_zone(&mut self, target: &RecordTarget) -> Result<String> {
+    self.provider.resolve_zone(&target.name, &target.zone).await
+  }
+
+  /// Points the record of a target at an IP, returning `false` if the record
+  /// already has the IP and TTL.
+  pub async fn apply(&mut self, target: &RecordTarget, ip: IpAddr) -> Result<bool> {
+    let zone_id = self.resolve_zone(target).await?;
+    let existing = self
+      .provider
+      .find_record(&zone_id, &target.name, ip)
+      .await?;
+
+    if existing.is_some_and(|record| is_current(&record, ip, target.ttl)) {
+      return Ok(false);
+    }
+
+    self
+      .provider
+      .upsert_record(&zone_id, &target.name, ip, target.ttl)
+      .await?;
+
+    Ok(true)
+  }
+}
+
+/// Returns `true` if a record holds exactly the IP with the TTL.
+fn is_current(record: &Record, ip: IpAddr, ttl: i64) -> bool {
+  record.ttl == ttl && record.values == [ip.to_string()]
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{collections::HashMap, sync::Mutex};
+
+  use super::*;
+
+  /// A provider with a single zone whose records are kept in memory.
+  #[derive(Default)]
+  struct Memory {
+    records: Mutex<HashMap<(String, bool), Record>>,
+    writes: Mutex<u32>,
+  }
+
+  impl DnsProvider for Memory {
+    async fn resolve_zone(&mut self, _: &DomainName, _: &ZoneChoice) -> Result<String> {
+      Ok("zone".into())
+    }
+
+    async fn find_record(&self, _: &str, name: &DomainName, ip: IpAddr) -> Result<Option<Record>> {
+      let key = (name.to_string(), ip.is_ipv6());
+
+      Ok(self.records.lock().unwrap().get(&key).cloned())
+    }
+
+    async fn upsert_record(&self, _: &str, name: &DomainName, ip: IpAddr, ttl: i64) -> Result<()> {
+      let record = Record {
+        values: vec![ip.to_string()],
+        ttl,
+      };
+
+      *self.writes.lock().unwrap() += 1;
+      self
+        .records
+        .lock()
+        .unwrap()
+        .insert((name.to_string(), ip.is_ipv6()), record);
+
+      Ok(())
+    }
+  }
+
+  fn record(values: &[&str], ttl: i64) -> Record {
+    Record {
+      values: values.iter().map(|v| v.to_string()).collect(),
+      ttl,
+    }
+  }
+
+  #[test]
+  fn records_with_only_the_ip_and_ttl_are_current() {
+    let ip = "203.0.113.7".parse().unwrap();
+
+    assert!(is_current(&record(&["203.0.113.7"], 300), ip, 300));
+    assert!(!is_current(&record(&["203.0.113.7"], 60), ip, 300));
+    assert!(!is_current(&record(&["198.51.100.9"], 300), ip, 300));
+    assert!(!is_current(
+      &record(&["203.0.113.7", "198.51.100.9"], 300),
+      ip,
+      300
+    ));
+  }
+
+  #[tokio::test]
+  async fn records_are_written_only_when_they_change() {
+    let mut updater = Updater::new(Memory::default());
+    let target = RecordTarget::new("home.example.com".parse().unwrap());
+    let old = "203.0.113.7".parse().unwrap();
+    let new = "198.51.100.9".parse().unwrap();
+
+    assert!(updater.apply(&target, old).await.unwrap());
+    assert!(!updater.apply(&target, old).await.unwrap());
+    assert!(updater.apply(&target, new).await.unwrap());
+    assert_eq!(*updater.provider.writes.lock().unwrap(), 2);
+  }
+}
@@ -0,0 +1,344 @@
+use std::{
+  fmt,
+  net::{IpAddr, SocketAddr},
+  str::FromStr,
+  sync::Arc,
+  time::Duration,
+};
+
+use anyhow::{bail, Result};
+use aws_sdk_route53 as route53;
+use clap::builder::RangedU64ValueParser;
+use reqwest::{Client, Url};
+use tokio::{
+  task::JoinSet,
+  time::{sleep, Instant},
+};
+use tracing::{error, info};
+
+use crate::{
+  aws,
+  dns::{self, RecordType},
+  http::Http,
+};
+
+/// Resolvers used when `--verify` is given without `--verify-resolver`.
+const DEFAULT_RESOLVERS: [&str; 2] = ["1.1.1.1", "8.8.8.8"];
+
+/// How long to wait between attempts while resolvers disagree.
+const RETRY_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Extra time resolvers are given to pick up a change beyond the TTL of the
+/// old record.
+const MARGIN: Duration = Duration::from_secs(60);
+
+/// Options for verifying that updated records resolve.
+#[derive(clap::Args)]
+pub struct VerifyOptions {
+  /// After each update, check that resolvers answer with the new IP once
+  /// Route 53 reports the change in sync, and log an error and notify if not
+  /// enough of them do before the old record expires from their caches.
+  ///
+  /// With `--once`, the daemon waits for the checks before it exits, and
+  /// exits with an error if any fail.
+  #[arg(long)]
+  pub verify: bool,
+
+  /// Resolver to verify updates with: `authoritative` for every name server
+  /// of the domain's hosted zone, the IP[:PORT] of a recursive resolver, or
+  /// the `https://` URL of a DNS-over-HTTPS endpoint. May be given more than
+  /// once.
+  ///
+  /// Defaults to the public resolvers 1.1.1.1 and 8.8.8.8, which networks
+  /// that filter DNS may block.
+  #[arg(long = "verify-resolver", value_name = "RESOLVER", requires = "verify")]
+  pub resolvers: Vec<Resolver>,
+
+  /// How long to wait for each resolver to answer.
+  #[arg(long, default_value = "5s", value_parser = humantime::parse_duration, requires = "verify")]
+  pub verify_timeout: Duration,
+
+  /// Number of resolvers that must answer with the new IP for an update to
+  /// count as verified. Defaults to all of them, which is also the most that
+  /// are required.
+  #[arg(
+    long,
+    value_name = "COUNT",
+    requires = "verify",
+    value_parser = RangedU64ValueParser::<usize>::new().range(1..)
+  )]
+  pub verify_quorum: Option<usize>,
+}
+
+/// A resolver that verification queries are sent to.
+#[derive(Clone)]
+pub enum Resolver {
+  /// The name servers of the domain's hosted zone, each asked without
+  /// recursion.
+  Authoritative,
+  /// A recursive resolver reached over plain DNS.
+  Dns(SocketAddr),
+  /// A DNS-over-HTTPS endpoint.
+  Https(Url),
+}
+
+impl FromStr for Resolver {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    if s == "authoritative" {
+      return Ok(Self::Authoritative);
+    }
+
+    if s.starts_with("https://") {
+      return match Url::parse(s) {
+        Ok(url) => Ok(Self::Https(url)),
+        Err(err) => bail!("Invalid DNS-over-HTTPS URL `{s}`. {err}"),
+      };
+    }
+
+    if let Ok(ip) = s.parse::<IpAddr>() {
+      return Ok(Self::Dns((ip, 53).into()));
+    }
+
+    match s.parse() {
+      Ok(addr) => Ok(Self::Dns(addr)),
+      Err(_) => bail!(
+        "Invalid resolver `{s}`. Expected `authoritative`, an IP[:PORT], or an `https://` URL."
+      ),
+    }
+  }
+}
+
+/// A single server a verification query is sent to.
+enum Target {
+  /// An authoritative name server, by host name.
+  NameServer(String, SocketAddr),
+  Dns(SocketAddr),
+  Https(Url),
+}
+
+impl fmt::Display for Target {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::NameServer(host, _) => write!(f, "`{}`", host.trim_end_matches('.')),
+      Self::Dns(addr) => write!(f, "{addr}"),
+      Self::Https(url) => write!(f, "{url}"),
+    }
+  }
+}
+
+/// Verifies in the background that updated records resolve to their new IP.
+pub struct Verifier {
+  checker: Checker,
+  /// Checks in progress, each ending with the failure if it failed.
+  tasks: JoinSet<Option<Unverified>>,
+}
+
+/// An update that could not be verified.
+pub struct Unverified {
+  pub domain: String,
+  pub ip: IpAddr,
+  pub error: String,
+}
+
+/// Settings shared by the checks of each update.
+#[derive(Clone)]
+struct Checker {
+  resolvers: Arc<[Resolver]>,
+  quorum: Option<usize>,
+  timeout: Duration,
+  http: Client,
+}
+
+impl Verifier {
+  /// Creates a verifier if verification is enabled.
+  pub fn new(options: VerifyOptions, http: &Http) -> Option<Self> {
+    if !options.verify {
+      return None;
+    }
+
+    let resolvers = match options.resolvers.is_empty() {
+      true => DEFAULT_RESOLVERS
+        .iter()
+        .map(|r| r.parse().expect("Default resolvers are valid."))
+        .collect(),
+
+      false => options.resolvers.into(),
+    };
+
+    let checker = Checker {
+      resolvers,
+      quorum: options.verify_quorum,
+      timeout: options.verify_timeout,
+      http: http.any.clone(),
+    };
+
+    Some(Self {
+      checker,
+      tasks: JoinSet::new(),
+    })
+  }
+
+  /// Starts verifying that `name` resolves to `ip` once Route 53 reports the
+  /// change in sync, asking it with the client of the account that made the
+  /// change.
+  ///
+  /// Resolvers that return the old IP are asked again until `cached_for`, the
+  /// TTL of the old record, has passed.
+  pub fn spawn(
+    &mut self,
+    route53: &route53::Client,
+    name: String,
+    zone_id: String,
+    change_id: String,
+    ip: IpAddr,
+    cached_for: Duration,
+  ) {
+    let checker = self.checker.clone();
+    let route53 = route53.clone();
+
+    self.tasks.spawn(async move {
+      let result = checker
+        .run(&route53, &name, &zone_id, &change_id, ip, cached_for)
+        .await;
+
+      let err = result.err()?;
+
+      error!(domain = name, "Failed to verify `{name}`. {err:#}");
+
+      Some(Unverified {
+        domain: name,
+        ip,
+        error: format!("{err:#}"),
+      })
+    });
+  }
+
+  /// Returns the failures of the checks that finished since the last call.
+  pub fn failures(&mut self) -> Vec<Unverified> {
+    let mut failures = Vec::new();
+
+    while let Some(result) = self.tasks.try_join_next() {
+      failures.extend(result.ok().flatten());
+    }
+
+    failures
+  }
+
+  /// Waits for every check in progress to finish and returns the failures.
+  pub async fn finish(&mut self) -> Vec<Unverified> {
+    let mut failures = Vec::new();
+
+    if !self.tasks.is_empty() {
+      info!("Waiting for updates to be verified.");
+    }
+
+    while let Some(result) = self.tasks.join_next().await {
+      failures.extend(result.ok().flatten());
+    }
+
+    failures
+  }
+}
+
+impl Checker {
+  async fn run(
+    &self,
+    route53: &route53::Client,
+    name: &str,
+    zone_id: &str,
+    change_id: &str,
+    ip: IpAddr,
+    cached_for: Duration,
+  ) -> Result<()> {
+    aws::wait_for_sync(route53, change_id).await?;
+
+    let targets = self.targets(route53, zone_id).await?;
+    let deadline = Instant::now() + cached_for + MARGIN;
+
+    let rr_type = match ip {
+      IpAddr::V4(_) => RecordType::A,
+      IpAddr::V6(_) => RecordType::Aaaa,
+    };
+
+    let expected = ip.to_string();
+    // a quorum beyond the resolvers there are would never be met, such as
+    // when a zone has fewer name servers than expected
+    let needed = self.quorum.unwrap_or(targets.len()).min(targets.len());
+
+    loop {
+      let mut agreed = 0;
+      let mut failures = Vec::new();
+
+      for target in &targets {
+        match self.query(target, name, rr_type).await {
+          Ok(records) if records.contains(&expected) => agreed += 1,
+          Ok(records) if records.is_empty() => {
+            failures.push(format!("{target} has no {rr_type} record"))
+          }
+          Ok(records) => failures.push(format!("{target} answers with {}", records.join(", "))),
+          Err(err) => failures.push(format!("{err:#}")),
+        }
+      }
+
+      if agreed >= needed {
+        info!(
+          "Verified that `{name}` resolves to {ip} on {agreed} of {} resolvers.",
+          targets.len()
+        );
+
+        return Ok(());
+      }
+
+      if Instant::now() >= deadline {
+        bail!(
+          "Only {agreed} of {} resolvers answer with {ip}, but {needed} must. {}.",
+          targets.len(),
+          failures.join("; ")
+        );
+      }
+
+      sleep(RETRY_INTERVAL).await;
+    }
+  }
+
+  /// Expands the configured resolvers into the servers to query for a zone.
+  async fn targets(&self, route53: &route53::Client, zone_id: &str) -> Result<Vec<Target>> {
+    let mut targets = Vec::new();
+
+    for resolver in self.resolvers.iter() {
+      match resolver {
+        Resolver::Authoritative => {
+          for host in aws::name_servers(route53, zone_id).await? {
+            let addr = dns::server(&host).await?;
+
+            targets.push(Target::NameServer(host, addr));
+          }
+        }
+
+        Resolver::Dns(addr) => targets.push(Target::Dns(*addr)),
+        Resolver::Https(url) => targets.push(Target::Https(url.clone())),
+      }
+    }
+
+    if targets.is_empty() {
+      bail!(
+        "There are no resolvers to verify with, since private zones have no public name servers."
+      );
+    }
+
+    Ok(targets)
+  }
+
+  /// Queries a single server for the records of a name.
+  async fn query(&self, target: &Target, name: &str, rr_type: RecordType) -> Result<Vec<String>> {
+    match target {
+      Target::NameServer(_, addr) => dns::query(*addr, name, rr_type, false, self.timeout).await,
+      Target::Dns(addr) => dns::query(*addr, name, rr_type, true, self.timeout).await,
+      Target::Https(url) => {
+        dns::query_https(&self.http, url.as_str(), name, rr_type, self.timeout).await
+      }
+    }
+  }
+}
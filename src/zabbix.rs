@@ -0,0 +1,186 @@
+use std::{collections::HashMap, time::Duration};
+
+use anyhow::{bail, ensure, Context, Result};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::{
+  io::{AsyncReadExt, AsyncWriteExt},
+  net::TcpStream,
+  time::timeout,
+};
+use tracing::{debug, info_span};
+
+use crate::{metrics::Snapshot, trace::traced};
+
+/// How long sending values to Zabbix may take.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Header that starts every message of the Zabbix protocol.
+const HEADER: &[u8] = b"ZBXD\x01";
+
+/// Largest response that is read from the server.
+const MAX_RESPONSE: u64 = 64 * 1024;
+
+/// Command line options for sending values to Zabbix.
+#[derive(clap::Args)]
+#[command(next_help_heading = "Zabbix")]
+pub struct ZabbixOptions {
+  /// HOST[:PORT] of a Zabbix server or proxy to send the metrics of each
+  /// update pass to with the sender protocol, as trapper items.
+  #[arg(long, value_name = "HOST[:PORT]", requires = "zabbix_host")]
+  zabbix_server: Option<String>,
+
+  /// Name of the host in Zabbix that the items belong to.
+  #[arg(long, value_name = "HOST", requires = "zabbix_server")]
+  zabbix_host: Option<String>,
+
+  /// Prefix of item keys. A metric is sent as `PREFIX.METRIC`, or
+  /// `PREFIX.METRIC[DOMAIN]` for a metric of a domain.
+  #[arg(
+    long,
+    value_name = "PREFIX",
+    default_value = "ddns_route53",
+    requires = "zabbix_server"
+  )]
+  zabbix_key_prefix: String,
+
+  /// Send a metric under a different key, such as `updates=ddns.updates` to
+  /// match an existing template. The domain is still appended in brackets to
+  /// the keys of domain metrics. May be given more than once.
+  #[arg(long, value_name = "METRIC=KEY", value_parser = parse_key, requires = "zabbix_server")]
+  zabbix_key: Vec<(String, String)>,
+}
+
+/// Response of the server to sent values.
+#[derive(Deserialize)]
+struct Response {
+  response: String,
+  #[serde(default)]
+  info: String,
+}
+
+/// Client for a Zabbix server or proxy.
+pub struct Zabbix {
+  addr: String,
+  host: String,
+  prefix: String,
+  keys: HashMap<String, String>,
+}
+
+impl Zabbix {
+  /// Creates a client for the Zabbix server given in the options, if any.
+  pub fn new(options: ZabbixOptions) -> Option<Self> {
+    let server = options.zabbix_server?;
+
+    // 10051 is the port of the Zabbix trapper
+    let addr = match server.rsplit_once(':') {
+      Some((_, port)) if port.parse::<u16>().is_ok() && !server.ends_with(']') => server,
+      _ => format!("{server}:10051"),
+    };
+
+    Some(Self {
+      addr,
+      host: options.zabbix_host?,
+      prefix: options.zabbix_key_prefix,
+      keys: options.zabbix_key.into_iter().collect(),
+    })
+  }
+
+  /// Sends the metrics of a pass as item values.
+  pub async fn send(&self, snapshot: &Snapshot) -> Result<()> {
+    let clock = snapshot.time.timestamp();
+
+    let data: Vec<_> = snapshot
+      .samples()
+      .into_iter()
+      .map(|sample| {
+        let key = match self.keys.get(sample.metric) {
+          Some(key) => key.clone(),
+          None => format!("{}.{}", self.prefix, sample.metric),
+        };
+
+        let key = match sample.domain {
+          Some(domain) => format!("{key}[{domain}]"),
+          None => key,
+        };
+
+        json!({
+          "host": self.host,
+          "key": key,
+          "value": sample.value.to_string(),
+          "clock": clock,
+        })
+      })
+      .collect();
+
+    let body = json!({ "request": "sender data", "data": data, "clock": clock }).to_string();
+
+    let response = traced(info_span!("zabbix_send", addr = self.addr), async {
+      timeout(TIMEOUT, self.exchange(body.as_bytes()))
+        .await
+        .context("Timed out.")?
+    })
+    .await
+    .with_context(|| format!("Failed to send values to Zabbix at {}.", self.addr))?;
+
+    if response.response != "success" {
+      bail!(
+        "Zabbix at {} rejected the values. {}",
+        self.addr,
+        response.info
+      );
+    }
+
+    debug!(info = response.info, "Sent values to Zabbix.");
+
+    // the server counts values of items it does not know as failed
+    if !response.info.contains("failed: 0;") {
+      bail!(
+        "Zabbix at {} did not accept every value: {}. Check that host `{}` has trapper items with \
+         the keys that are sent.",
+        self.addr,
+        response.info,
+        self.host
+      );
+    }
+
+    Ok(())
+  }
+
+  /// Sends a message and reads the response.
+  async fn exchange(&self, body: &[u8]) -> Result<Response> {
+    let mut stream = TcpStream::connect(&self.addr).await?;
+    let mut message = Vec::with_capacity(HEADER.len() + 8 + body.len());
+
+    // the header is followed by the length of the body and reserved bytes
+    message.extend(HEADER);
+    message.extend((body.len() as u32).to_le_bytes());
+    message.extend(0u32.to_le_bytes());
+    message.extend(body);
+
+    stream.write_all(&message).await?;
+
+    let mut header = [0; 13];
+
+    stream.read_exact(&mut header).await?;
+    ensure!(header.starts_with(HEADER), "Invalid response header.");
+
+    let len = u64::from_le_bytes(header[5..].try_into()?);
+
+    ensure!(len <= MAX_RESPONSE, "Response is too large.");
+
+    let mut body = vec![0; len as usize];
+
+    stream.read_exact(&mut body).await?;
+
+    serde_json::from_slice(&body).context("Invalid response.")
+  }
+}
+
+/// Parses a `METRIC=KEY` mapping.
+fn parse_key(s: &str) -> Result<(String, String)> {
+  match s.split_once('=') {
+    Some((metric, key)) if !metric.is_empty() && !key.is_empty() => Ok((metric.into(), key.into())),
+    _ => bail!("Invalid key mapping `{s}`. Expected METRIC=KEY."),
+  }
+}
@@ -0,0 +1,533 @@
+use std::{
+  fmt,
+  str::FromStr,
+  time::{Duration, Instant},
+};
+
+use anyhow::{bail, Result};
+use aws_sdk_route53::types::HostedZone;
+
+use crate::name::DomainName;
+
+/// Which hosted zone a domain belongs to when several zones could contain it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum ZoneChoice {
+  /// The zone with the deepest matching name.
+  #[default]
+  Any,
+  /// Only public hosted zones.
+  Public,
+  /// Only private hosted zones.
+  Private,
+  /// The hosted zone with this ID.
+  Id(String),
+}
+
+/// Why a hosted zone was or was not chosen for a domain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ZoneVerdict {
+  /// The zone's name does not contain the domain.
+  Unrelated,
+  /// The zone is not allowed by the domain's zone choice.
+  Excluded,
+  /// Another acceptable zone with a deeper name contains the domain.
+  Shallower,
+  /// The zone is one of the deepest acceptable zones containing the domain.
+  Deepest,
+}
+
+impl ZoneChoice {
+  /// Returns `true` if the given hosted zone is acceptable.
+  pub fn allows(&self, zone: &HostedZone) -> bool {
+    match self {
+      Self::Any => true,
+      Self::Public => !is_private(zone),
+      Self::Private => is_private(zone),
+      Self::Id(id) => zone.id.trim_start_matches("/hostedzone/") == id,
+    }
+  }
+
+  /// Returns the full ID of the hosted zone if it is chosen by ID, such as
+  /// `/hostedzone/Z123`, so that it can be used without listing the hosted
+  /// zones.
+  pub fn zone_id(&self) -> Option<String> {
+    match self {
+      Self::Id(id) => Some(format!("/hostedzone/{id}")),
+      _ => None,
+    }
+  }
+}
+
+impl fmt::Display for ZoneChoice {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Any => f.write_str("any"),
+      Self::Public => f.write_str("public"),
+      Self::Private => f.write_str("private"),
+      Self::Id(id) => f.write_str(id),
+    }
+  }
+}
+
+impl FromStr for ZoneChoice {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    match s {
+      "public" => Ok(Self::Public),
+      "private" => Ok(Self::Private),
+
+      _ => {
+        let id = s.trim_start_matches("/hostedzone/");
+
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+          bail!("Invalid hosted zone {s:?}. Expected `public`, `private`, or a zone ID.");
+        }
+
+        Ok(Self::Id(id.to_string()))
+      }
+    }
+  }
+}
+
+/// Hosted zones of an account, kept for a while after they are listed since
+/// they rarely change.
+pub struct ZoneCache {
+  /// How long listed zones are kept.
+  ttl: Duration,
+  zones: Option<(Instant, Vec<HostedZone>)>,
+}
+
+impl ZoneCache {
+  pub const fn new(ttl: Duration) -> Self {
+    Self { ttl, zones: None }
+  }
+
+  /// Returns the zones if they were listed less than the TTL ago.
+  pub fn get(&self) -> Option<&[HostedZone]> {
+    self
+      .zones
+      .as_ref()
+      .filter(|(listed_at, _)| listed_at.elapsed() < self.ttl)
+      .map(|(_, zones)| zones.as_slice())
+  }
+
+  /// Keeps newly listed zones.
+  pub fn store(&mut self, zones: Vec<HostedZone>) {
+    self.zones = Some((Instant::now(), zones));
+  }
+
+  /// Forgets the zones so that they are listed again.
+  pub fn clear(&mut self) {
+    self.zones = None;
+  }
+}
+
+/// Judges every hosted zone as a candidate for the record of a domain name.
+pub fn judge<'a>(
+  zones: &'a [HostedZone],
+  name: &DomainName,
+  choice: &ZoneChoice,
+) -> Vec<(&'a HostedZone, ZoneVerdict)> {
+  let mut verdicts: Vec<_> = zones
+    .iter()
+    .map(|z| {
+      // find hosted zones that could contain this domain name
+      let depth = DomainName::from_route53(&z.name)
+        .ok()
+        .filter(|zone| name.is_within(zone))
+        .map(|zone| zone.depth());
+
+      let verdict = match (depth, choice.allows(z)) {
+        (None, _) => ZoneVerdict::Unrelated,
+        (Some(_), false) => ZoneVerdict::Excluded,
+        (Some(_), true) => ZoneVerdict::Deepest,
+      };
+
+      (z, verdict, depth)
+    })
+    .collect();
+
+  // keep only the hosted zones with the deepest subdomain match
+
+  let deepest = verdicts
+    .iter()
+    .filter(|(_, v, _)| *v == ZoneVerdict::Deepest)
+    .map(|(_, _, depth)| *depth)
+    .max();
+
+  for (_, verdict, depth) in &mut verdicts {
+    if *verdict == ZoneVerdict::Deepest && Some(*depth) != deepest {
+      *verdict = ZoneVerdict::Shallower;
+    }
+  }
+
+  verdicts
+    .into_iter()
+    .map(|(zone, verdict, _)| (zone, verdict))
+    .collect()
+}
+
+/// Finds the hosted zone the record of a domain name belongs to.
+///
+/// Of the acceptable zones that could contain the name, the one with the
+/// deepest name is chosen. If several zones share that name, for example a
+/// public and a private zone, the choice is ambiguous and an error is returned.
+pub fn find<'a>(
+  zones: &'a [HostedZone],
+  name: &DomainName,
+  choice: &ZoneChoice,
+) -> Result<&'a HostedZone> {
+  let matches: Vec<&HostedZone> = judge(zones, name, choice)
+    .into_iter()
+    .filter(|(_, v)| *v == ZoneVerdict::Deepest)
+    .map(|(z, _)| z)
+    .collect();
+
+  match matches[..] {
+    [] => bail!("Cannot find a hosted zone for `{name}`."),
+    [zone] => return Ok(zone),
+    _ => {}
+  }
+
+  let ids: Vec<String> = matches
+    .iter()
+    .map(|z| format!("{} ({})", z.id, visibility(z)))
+    .collect();
+
+  bail!(
+    "`{name}` matches several hosted zones named `{}`: {}. Choose one with `{name}=public`, \
+     `{name}=private`, or `{name}=ZONE_ID`.",
+    matches[0].name.trim_end_matches('.'),
+    ids.join(", "),
+  );
+}
+
+/// Returns `"private"` or `"public"` depending on a hosted zone's visibility.
+pub fn visibility(zone: &HostedZone) -> &'static str {
+  match is_private(zone) {
+    true => "private",
+    false => "public",
+  }
+}
+
+/// Returns `true` if a hosted zone is private.
+fn is_private(zone: &HostedZone) -> bool {
+  zone.config.as_ref().is_some_and(|c| c.private_zone)
+}
+
+#[cfg(test)]
+mod tests {
+  use aws_sdk_route53::types::HostedZoneConfig;
+  use proptest::prelude::*;
+
+  use super::*;
+
+  fn zone(id: &str, name: &str, private: bool) -> HostedZone {
+    HostedZone::builder()
+      .id(format!("/hostedzone/{id}"))
+      .name(name)
+      .caller_reference(id)
+      .config(HostedZoneConfig::builder().private_zone(private).build())
+      .build()
+      .unwrap()
+  }
+
+  fn name(s: &str) -> DomainName {
+    s.parse().unwrap()
+  }
+
+  fn found(zones: &[HostedZone], domain: &str, choice: &ZoneChoice) -> Option<String> {
+    find(zones, &name(domain), choice)
+      .ok()
+      .map(|z| z.id.trim_start_matches("/hostedzone/").to_string())
+  }
+
+  #[test]
+  fn matches_apex_and_subdomains() {
+    let zones = [zone("Z1", "example.com.", false)];
+
+    assert_eq!(
+      found(&zones, "example.com", &ZoneChoice::Any).as_deref(),
+      Some("Z1")
+    );
+    assert_eq!(
+      found(&zones, "a.b.example.com", &ZoneChoice::Any).as_deref(),
+      Some("Z1")
+    );
+  }
+
+  #[test]
+  fn prefers_delegated_subzone() {
+    let zones = [
+      zone("Z1", "example.com.", false),
+      zone("Z2", "home.example.com.", false),
+    ];
+
+    let verdicts: Vec<_> = judge(&zones, &name("nas.home.example.com"), &ZoneChoice::Any)
+      .into_iter()
+      .map(|(_, v)| v)
+      .collect();
+
+    assert_eq!(verdicts, [ZoneVerdict::Shallower, ZoneVerdict::Deepest]);
+    assert_eq!(
+      found(&zones, "www.example.com", &ZoneChoice::Any).as_deref(),
+      Some("Z1")
+    );
+  }
+
+  #[test]
+  fn ignores_overlapping_names() {
+    let zones = [
+      zone("Z1", "example.com.", false),
+      zone("Z2", "ample.com.", false),
+      zone("Z3", "le.com.", false),
+    ];
+
+    let verdicts: Vec<_> = judge(&zones, &name("www.example.com"), &ZoneChoice::Any)
+      .into_iter()
+      .map(|(_, v)| v)
+      .collect();
+
+    assert_eq!(
+      verdicts,
+      [
+        ZoneVerdict::Deepest,
+        ZoneVerdict::Unrelated,
+        ZoneVerdict::Unrelated
+      ]
+    );
+
+    assert_eq!(found(&zones, "myexample.com", &ZoneChoice::Any), None);
+  }
+
+  #[test]
+  fn ignores_trailing_dots_and_case() {
+    let zones = [
+      zone("Z1", "Example.COM.", false),
+      zone("Z2", "other.org", false),
+    ];
+
+    assert_eq!(
+      found(&zones, "WWW.example.com.", &ZoneChoice::Any).as_deref(),
+      Some("Z1")
+    );
+    assert_eq!(
+      found(&zones, "www.Other.Org", &ZoneChoice::Any).as_deref(),
+      Some("Z2")
+    );
+  }
+
+  #[test]
+  fn decodes_escaped_zone_names() {
+    let zones = [zone("Z1", "\\052.example.com.", false)];
+
+    assert_eq!(
+      found(&zones, "*.example.com", &ZoneChoice::Any).as_deref(),
+      Some("Z1")
+    );
+  }
+
+  #[test]
+  fn deepest_is_judged_before_choice() {
+    let zones = [
+      zone("Z1", "example.com.", false),
+      zone("Z2", "home.example.com.", true),
+    ];
+
+    // a shallower zone is still used when the deeper one is excluded
+    assert_eq!(
+      found(&zones, "nas.home.example.com", &ZoneChoice::Public).as_deref(),
+      Some("Z1")
+    );
+    assert_eq!(
+      found(&zones, "nas.home.example.com", &ZoneChoice::Private).as_deref(),
+      Some("Z2")
+    );
+  }
+
+  #[test]
+  fn split_horizon_is_ambiguous() {
+    let zones = [
+      zone("Z1", "example.com.", false),
+      zone("Z2", "example.com.", true),
+    ];
+
+    let err = find(&zones, &name("home.example.com"), &ZoneChoice::Any).unwrap_err();
+
+    assert!(err.to_string().contains("matches several hosted zones"));
+    assert_eq!(
+      found(&zones, "home.example.com", &ZoneChoice::Public).as_deref(),
+      Some("Z1")
+    );
+    assert_eq!(
+      found(&zones, "home.example.com", &ZoneChoice::Private).as_deref(),
+      Some("Z2")
+    );
+    assert_eq!(
+      found(&zones, "home.example.com", &"Z2".parse().unwrap()).as_deref(),
+      Some("Z2")
+    );
+  }
+
+  #[test]
+  fn parses_zone_choices() {
+    assert_eq!("public".parse::<ZoneChoice>().unwrap(), ZoneChoice::Public);
+    assert_eq!(
+      "private".parse::<ZoneChoice>().unwrap(),
+      ZoneChoice::Private
+    );
+    assert_eq!(
+      "/hostedzone/Z123".parse::<ZoneChoice>().unwrap(),
+      ZoneChoice::Id("Z123".into())
+    );
+    assert!("".parse::<ZoneChoice>().is_err());
+    assert!("Z-1".parse::<ZoneChoice>().is_err());
+  }
+
+  #[test]
+  fn only_zones_chosen_by_id_are_known_without_listing() {
+    let choice = "Z123".parse::<ZoneChoice>().unwrap();
+
+    assert_eq!(choice.zone_id().as_deref(), Some("/hostedzone/Z123"));
+    assert!(choice.allows(&zone("Z123", "example.com.", false)));
+    assert_eq!(ZoneChoice::Public.zone_id(), None);
+    assert_eq!(ZoneChoice::Any.zone_id(), None);
+  }
+
+  #[test]
+  fn cached_zones_expire_after_the_ttl() {
+    let mut cache = ZoneCache::new(Duration::from_secs(3600));
+
+    assert!(cache.get().is_none());
+    cache.store(vec![zone("Z1", "example.com.", false)]);
+    assert_eq!(cache.get().map(<[_]>::len), Some(1));
+    cache.clear();
+    assert!(cache.get().is_none());
+
+    let mut uncached = ZoneCache::new(Duration::ZERO);
+
+    uncached.store(vec![zone("Z1", "example.com.", false)]);
+    assert!(uncached.get().is_none());
+  }
+
+  fn label() -> impl Strategy<Value = String> {
+    "[a-z][a-z0-9]{0,5}"
+  }
+
+  fn labels(range: std::ops::Range<usize>) -> impl Strategy<Value = Vec<String>> {
+    prop::collection::vec(label(), range)
+  }
+
+  /// Randomizes the case of a name and whether it has a trailing dot.
+  fn disguise(name: &str, mask: u64, dot: bool) -> String {
+    let mut s: String = name
+      .chars()
+      .enumerate()
+      .map(|(i, c)| match mask >> (i % 64) & 1 {
+        1 => c.to_ascii_uppercase(),
+        _ => c,
+      })
+      .collect();
+
+    if dot {
+      s.push('.');
+    }
+
+    s
+  }
+
+  proptest! {
+    #[test]
+    fn finds_deepest_zone_containing_name(
+      apex in labels(2..4),
+      sub in labels(0..3),
+      host in labels(0..3),
+      mask: u64,
+      dot: bool,
+    ) {
+      let parent = apex.join(".");
+      let child = [&sub[..], &apex[..]].concat().join(".");
+      let domain = [&host[..], &sub[..], &apex[..]].concat().join(".");
+
+      let zones = [
+        zone("Z1", &format!("{parent}."), false),
+        zone("Z2", &format!("{child}."), false),
+      ];
+      let expected = match sub.is_empty() {
+        // both zones share a name, so the choice is ambiguous
+        true => None,
+        false => Some("Z2"),
+      };
+
+      let actual = found(&zones, &disguise(&domain, mask, dot), &ZoneChoice::Any);
+
+      prop_assert_eq!(actual.as_deref(), expected);
+    }
+
+    #[test]
+    fn never_matches_sibling_zones(
+      apex in labels(2..4),
+      prefix in "[a-z0-9]{1,4}",
+      host in labels(0..3),
+    ) {
+      let zone_name = apex.join(".");
+      let domain = [&host[..], &[format!("{prefix}{}", apex[0])], &apex[1..]].concat().join(".");
+      let zones = [zone("Z1", &format!("{zone_name}."), false)];
+
+      prop_assert_eq!(found(&zones, &domain, &ZoneChoice::Any), None);
+    }
+
+    #[test]
+    fn verdicts_are_consistent(
+      zone_names in prop::collection::vec(labels(1..4), 1..6),
+      domain in labels(2..5),
+      private in prop::collection::vec(any::<bool>(), 6),
+      choice in prop_oneof![
+        Just(ZoneChoice::Any),
+        Just(ZoneChoice::Public),
+        Just(ZoneChoice::Private),
+      ],
+    ) {
+      let zones: Vec<_> = zone_names
+        .iter()
+        .enumerate()
+        .map(|(i, labels)| zone(&format!("Z{i}"), &labels.join("."), private[i]))
+        .collect();
+
+      let domain = name(&domain.join("."));
+      let verdicts = judge(&zones, &domain, &choice);
+
+      prop_assert_eq!(verdicts.len(), zones.len());
+
+      let depth = |z: &HostedZone| DomainName::from_route53(&z.name).unwrap().depth();
+      let deepest: Vec<_> = verdicts
+        .iter()
+        .filter(|(_, v)| *v == ZoneVerdict::Deepest)
+        .map(|(z, _)| depth(z))
+        .collect();
+
+      // all deepest zones are equally deep
+      prop_assert!(deepest.windows(2).all(|w| w[0] == w[1]));
+
+      for (z, verdict) in &verdicts {
+        let contains = domain.is_within(&DomainName::from_route53(&z.name).unwrap());
+
+        match verdict {
+          ZoneVerdict::Unrelated => prop_assert!(!contains),
+          ZoneVerdict::Excluded => prop_assert!(contains && !choice.allows(z)),
+          ZoneVerdict::Shallower => {
+            prop_assert!(contains && choice.allows(z));
+            prop_assert!(deepest.iter().all(|&d| d > depth(z)));
+          }
+          ZoneVerdict::Deepest => prop_assert!(contains && choice.allows(z)),
+        }
+      }
+
+      prop_assert_eq!(
+        find(&zones, &domain, &choice).is_ok(),
+        deepest.len() == 1
+      );
+    }
+  }
+}